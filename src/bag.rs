@@ -0,0 +1,282 @@
+use std::cell::UnsafeCell;
+use std::mem::MaybeUninit;
+use std::ptr;
+use std::sync::atomic::Ordering::{Acquire, Relaxed, Release};
+use std::sync::atomic::{AtomicPtr, AtomicU32};
+
+/// The number of slots in a slot array.
+const ARRAY_SIZE: u32 = 16;
+
+/// The occupancy bits of a slot array.
+///
+/// A set occupancy bit represents that the corresponding slot contains a value that is ready to be popped.
+const OCCUPANCY_MASK: u32 = (1u32 << ARRAY_SIZE) - 1;
+
+/// The reserved bits of a slot array are placed right above the occupancy bits.
+///
+/// A set reserved bit represents that the corresponding slot is being written or read by a thread.
+const RESERVED_SHIFT: u32 = ARRAY_SIZE;
+
+/// A concurrent unordered container implementation.
+///
+/// scc::Bag is targeted at distributing work items among threads; any thread can pop any value without ordering
+/// guarantees. Values are stored in slot arrays of sixteen slots, and each slot array manages the state of the slots
+/// in a single 32-bit metadata word, therefore pushing or popping a value requires only a single compare-and-swap
+/// operation on the metadata word and another atomic operation to publish the result.
+///
+/// The key features of scc::Bag.
+/// * Lock-free push and pop.
+/// * Overflow slot arrays: when all the slot arrays are full, a new slot array is linked to the list of slot arrays.
+///   Linked slot arrays are retained until the Bag is dropped.
+pub struct Bag<T: Send> {
+    array: SlotArray<T>,
+    link: AtomicPtr<SlotArray<T>>,
+}
+
+impl<T: Send> Bag<T> {
+    /// Creates an empty Bag instance.
+    ///
+    /// # Examples
+    /// ```
+    /// use scc::Bag;
+    ///
+    /// let bag: Bag<u64> = Bag::new();
+    ///
+    /// let result = bag.pop();
+    /// assert!(result.is_none());
+    /// ```
+    pub fn new() -> Bag<T> {
+        Bag {
+            array: SlotArray::new(),
+            link: AtomicPtr::new(ptr::null_mut()),
+        }
+    }
+
+    /// Pushes a value into the Bag.
+    ///
+    /// # Examples
+    /// ```
+    /// use scc::Bag;
+    ///
+    /// let bag: Bag<u64> = Bag::new();
+    ///
+    /// bag.push(1);
+    /// assert_eq!(bag.len(), 1);
+    /// ```
+    pub fn push(&self, value: T) {
+        let mut value = match self.array.push(value) {
+            Ok(()) => return,
+            Err(value) => value,
+        };
+        let mut link_ptr = self.link.load(Acquire);
+        loop {
+            // tries every linked slot array
+            let mut current_ptr = link_ptr;
+            while let Some(slot_array) = unsafe { current_ptr.as_ref() } {
+                value = match slot_array.push(value) {
+                    Ok(()) => return,
+                    Err(value) => value,
+                };
+                current_ptr = slot_array.link.load(Acquire);
+            }
+
+            // links a new slot array containing the value
+            let new_array = Box::new(SlotArray::new());
+            new_array
+                .push(value)
+                .unwrap_or_else(|_| unreachable!("a new slot array is empty"));
+            new_array.link.store(link_ptr, Relaxed);
+            let new_array_ptr = Box::into_raw(new_array);
+            match self
+                .link
+                .compare_exchange(link_ptr, new_array_ptr, Release, Acquire)
+            {
+                Ok(_) => return,
+                Err(actual) => {
+                    let new_array = unsafe { Box::from_raw(new_array_ptr) };
+                    value = new_array.pop().unwrap();
+                    link_ptr = actual;
+                }
+            }
+        }
+    }
+
+    /// Pops any value in the Bag.
+    ///
+    /// # Examples
+    /// ```
+    /// use scc::Bag;
+    ///
+    /// let bag: Bag<u64> = Bag::new();
+    ///
+    /// bag.push(1);
+    ///
+    /// let result = bag.pop();
+    /// assert_eq!(result, Some(1));
+    ///
+    /// let result = bag.pop();
+    /// assert!(result.is_none());
+    /// ```
+    pub fn pop(&self) -> Option<T> {
+        if let Some(value) = self.array.pop() {
+            return Some(value);
+        }
+        let mut current_ptr = self.link.load(Acquire);
+        while let Some(slot_array) = unsafe { current_ptr.as_ref() } {
+            if let Some(value) = slot_array.pop() {
+                return Some(value);
+            }
+            current_ptr = slot_array.link.load(Acquire);
+        }
+        None
+    }
+
+    /// Returns the number of values that are ready to be popped.
+    ///
+    /// The number is not exact when there are concurrent push or pop operations.
+    ///
+    /// # Examples
+    /// ```
+    /// use scc::Bag;
+    ///
+    /// let bag: Bag<u64> = Bag::new();
+    ///
+    /// for value in 0..32 {
+    ///     bag.push(value);
+    /// }
+    /// assert_eq!(bag.len(), 32);
+    /// ```
+    pub fn len(&self) -> usize {
+        let mut len = self.array.len();
+        let mut current_ptr = self.link.load(Acquire);
+        while let Some(slot_array) = unsafe { current_ptr.as_ref() } {
+            len += slot_array.len();
+            current_ptr = slot_array.link.load(Acquire);
+        }
+        len
+    }
+
+    /// Checks if the Bag is empty.
+    ///
+    /// # Examples
+    /// ```
+    /// use scc::Bag;
+    ///
+    /// let bag: Bag<u64> = Bag::new();
+    /// assert!(bag.is_empty());
+    ///
+    /// bag.push(1);
+    /// assert!(!bag.is_empty());
+    /// ```
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+}
+
+impl<T: Send> Default for Bag<T> {
+    fn default() -> Self {
+        Bag::new()
+    }
+}
+
+impl<T: Send> Drop for Bag<T> {
+    fn drop(&mut self) {
+        let mut current_ptr = self.link.swap(ptr::null_mut(), Relaxed);
+        while !current_ptr.is_null() {
+            let slot_array = unsafe { Box::from_raw(current_ptr) };
+            current_ptr = slot_array.link.load(Relaxed);
+        }
+    }
+}
+
+unsafe impl<T: Send> Sync for Bag<T> {}
+
+struct SlotArray<T> {
+    /// The metadata word consisting of the occupancy bits and the reserved bits.
+    metadata: AtomicU32,
+    slots: [UnsafeCell<MaybeUninit<T>>; ARRAY_SIZE as usize],
+    link: AtomicPtr<SlotArray<T>>,
+}
+
+impl<T> SlotArray<T> {
+    fn new() -> SlotArray<T> {
+        SlotArray {
+            metadata: AtomicU32::new(0),
+            slots: unsafe { MaybeUninit::uninit().assume_init() },
+            link: AtomicPtr::new(ptr::null_mut()),
+        }
+    }
+
+    /// Pushes the value into a slot that is neither occupied nor reserved.
+    fn push(&self, value: T) -> Result<(), T> {
+        let mut metadata = self.metadata.load(Relaxed);
+        loop {
+            let used = (metadata | (metadata >> RESERVED_SHIFT)) & OCCUPANCY_MASK;
+            if used == OCCUPANCY_MASK {
+                return Err(value);
+            }
+            let index = (!used).trailing_zeros();
+            let reserved_bit = 1u32 << (index + RESERVED_SHIFT);
+            match self.metadata.compare_exchange(
+                metadata,
+                metadata | reserved_bit,
+                Acquire,
+                Relaxed,
+            ) {
+                Ok(_) => {
+                    unsafe {
+                        (*self.slots[index as usize].get())
+                            .as_mut_ptr()
+                            .write(value)
+                    };
+                    // clears the reserved bit, and sets the occupancy bit
+                    self.metadata
+                        .fetch_xor(reserved_bit | (1u32 << index), Release);
+                    return Ok(());
+                }
+                Err(actual) => metadata = actual,
+            }
+        }
+    }
+
+    /// Pops a value from an occupied slot.
+    fn pop(&self) -> Option<T> {
+        let mut metadata = self.metadata.load(Relaxed);
+        loop {
+            let occupied = metadata & OCCUPANCY_MASK;
+            if occupied == 0 {
+                return None;
+            }
+            let index = occupied.trailing_zeros();
+            let reserved_bit = 1u32 << (index + RESERVED_SHIFT);
+            match self.metadata.compare_exchange(
+                metadata,
+                metadata ^ (reserved_bit | (1u32 << index)),
+                Acquire,
+                Relaxed,
+            ) {
+                Ok(_) => {
+                    let value = unsafe { (*self.slots[index as usize].get()).as_ptr().read() };
+                    self.metadata.fetch_and(!reserved_bit, Release);
+                    return Some(value);
+                }
+                Err(actual) => metadata = actual,
+            }
+        }
+    }
+
+    fn len(&self) -> usize {
+        (self.metadata.load(Relaxed) & OCCUPANCY_MASK).count_ones() as usize
+    }
+}
+
+impl<T> Drop for SlotArray<T> {
+    fn drop(&mut self) {
+        let occupied = *self.metadata.get_mut() & OCCUPANCY_MASK;
+        for index in 0..ARRAY_SIZE {
+            if occupied & (1u32 << index) != 0 {
+                unsafe { ptr::drop_in_place((*self.slots[index as usize].get()).as_mut_ptr()) };
+            }
+        }
+    }
+}
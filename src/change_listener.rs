@@ -0,0 +1,76 @@
+use std::sync::Arc;
+use std::time::Duration;
+
+/// ChangeListener is notified of the modifications made to an scc::HashMap.
+///
+/// It is invoked while the cell owning the key is exclusively locked and before the modification becomes visible to
+/// other threads, therefore the notifications of the same key are delivered in the order in which the key was
+/// modified, and a write-ahead log record written by the listener always precedes the modification. The listener
+/// must not access the HashMap, otherwise the thread may deadlock.
+pub trait ChangeListener<K, V>: Send + Sync {
+    /// Notifies the listener of a modification of the key.
+    ///
+    /// old_value is None if the key is newly inserted, and new_value is None if the key is removed.
+    fn on_change(&self, key: &K, old_value: Option<&V>, new_value: Option<&V>);
+
+    /// Notifies the listener of a modification of the key along with the deadline of the new value.
+    ///
+    /// The deadline is the time measured by the Clock of the HashMap at which the key-value pair expires, and it is
+    /// only given when a key that expires is inserted, since updating a value retains the deadline and expired
+    /// key-value pairs are notified as removals. A write-ahead log that records the deadline restores it through
+    /// HashMap::replay_with_deadlines. It calls on_change by default.
+    fn on_change_with_deadline(
+        &self,
+        key: &K,
+        old_value: Option<&V>,
+        new_value: Option<&V>,
+        deadline: Option<Duration>,
+    ) {
+        let _ = deadline;
+        self.on_change(key, old_value, new_value);
+    }
+}
+
+/// A closure taking the key, the old value and the new value is a ChangeListener.
+///
+/// # Examples
+/// ```
+/// use scc::HashMap;
+/// use std::collections::hash_map::RandomState;
+/// use std::sync::{Arc, Mutex};
+///
+/// let log = Arc::new(Mutex::new(Vec::new()));
+/// let log_copied = log.clone();
+/// let hashmap: HashMap<u64, u32, RandomState> = HashMap::with_listener(
+///     RandomState::new(),
+///     None,
+///     move |key: &u64, _: Option<&u32>, new_value: Option<&u32>| {
+///         log_copied.lock().unwrap().push((*key, new_value.copied()));
+///     },
+/// );
+///
+/// assert!(hashmap.insert(1, 0).is_ok());
+/// assert!(hashmap.remove(1));
+/// assert_eq!(*log.lock().unwrap(), vec![(1, Some(0)), (1, None)]);
+/// ```
+impl<K, V, F: Fn(&K, Option<&V>, Option<&V>) + Send + Sync> ChangeListener<K, V> for F {
+    fn on_change(&self, key: &K, old_value: Option<&V>, new_value: Option<&V>) {
+        self(key, old_value, new_value)
+    }
+}
+
+impl<K, V, L: ChangeListener<K, V> + ?Sized> ChangeListener<K, V> for Arc<L> {
+    fn on_change(&self, key: &K, old_value: Option<&V>, new_value: Option<&V>) {
+        (**self).on_change(key, old_value, new_value)
+    }
+
+    fn on_change_with_deadline(
+        &self,
+        key: &K,
+        old_value: Option<&V>,
+        new_value: Option<&V>,
+        deadline: Option<Duration>,
+    ) {
+        (**self).on_change_with_deadline(key, old_value, new_value, deadline)
+    }
+}
@@ -0,0 +1,77 @@
+use std::sync::atomic::AtomicU64;
+use std::sync::atomic::Ordering::Relaxed;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+/// Clock provides the current time to containers supporting time-to-live.
+pub trait Clock: Send + Sync {
+    /// Returns the time elapsed since an arbitrary fixed point in time.
+    ///
+    /// The returned value must never decrease.
+    fn now(&self) -> Duration;
+}
+
+/// SystemClock measures the time elapsed since it was created using std::time::Instant.
+///
+/// # Examples
+/// ```
+/// use scc::{Clock, SystemClock};
+///
+/// let clock = SystemClock::default();
+/// let before = clock.now();
+/// assert!(clock.now() >= before);
+/// ```
+pub struct SystemClock {
+    origin: Instant,
+}
+
+impl Default for SystemClock {
+    fn default() -> Self {
+        SystemClock {
+            origin: Instant::now(),
+        }
+    }
+}
+
+impl Clock for SystemClock {
+    fn now(&self) -> Duration {
+        self.origin.elapsed()
+    }
+}
+
+/// ManualClock only advances when told to, which makes time-to-live deterministic in tests.
+///
+/// # Examples
+/// ```
+/// use scc::{Clock, ManualClock};
+/// use std::time::Duration;
+///
+/// let clock = ManualClock::default();
+/// assert_eq!(clock.now(), Duration::from_secs(0));
+///
+/// clock.advance(Duration::from_secs(1));
+/// assert_eq!(clock.now(), Duration::from_secs(1));
+/// ```
+#[derive(Default)]
+pub struct ManualClock {
+    elapsed: AtomicU64,
+}
+
+impl ManualClock {
+    /// Advances the clock by the given duration.
+    pub fn advance(&self, duration: Duration) {
+        self.elapsed.fetch_add(duration.as_nanos() as u64, Relaxed);
+    }
+}
+
+impl Clock for ManualClock {
+    fn now(&self) -> Duration {
+        Duration::from_nanos(self.elapsed.load(Relaxed))
+    }
+}
+
+impl<C: Clock + ?Sized> Clock for Arc<C> {
+    fn now(&self) -> Duration {
+        (**self).now()
+    }
+}
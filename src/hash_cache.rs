@@ -0,0 +1,362 @@
+use super::map::array::Array;
+use super::map::cell::{CellLocker, CellReader, WeighFn, Weigher};
+use super::map::HashMap;
+use crossbeam_epoch::Atomic;
+use std::hash::{BuildHasher, Hash, Hasher};
+use std::sync::atomic::AtomicUsize;
+use std::sync::atomic::Ordering::Relaxed;
+
+/// A concurrent bounded cache implementation.
+///
+/// scc::HashCache is a fixed-capacity variant of scc::HashMap that evicts key-value pairs instead of enlarging the
+/// internal array. Eviction is done per cell: when a cell is full, a key-value pair in the cell is evicted according to
+/// the CLOCK policy, also known as second-chance, using a reference bit per entry that is stored in the cell metadata.
+/// Reading a key-value pair sets the reference bit under a shared lock on the cell, and inserting a key-value pair
+/// exclusively locks a single cell, therefore there is no global lock or global list to maintain.
+///
+/// The key features of scc::HashCache.
+/// * Bounded capacity: the capacity is fixed when the HashCache is created.
+/// * Per-cell CLOCK eviction: evicted key-value pairs are returned to the caller.
+/// * Non-blocking reads: readers only share-lock a cell.
+/// * Weighted capacity: a HashCache created by with_weigher additionally limits the total weight of the key-value
+///   pairs, and evicts key-value pairs from cells in turn until the total weight falls within the budget.
+pub struct HashCache<K: Eq + Hash + Sync, V: Sync, H: BuildHasher> {
+    array: Array<K, V>,
+    hasher: H,
+    weigher: Option<Box<WeighFn<K, V>>>,
+    max_weight: usize,
+    weight: AtomicUsize,
+    /// The index of the cell where the next weight-based eviction starts.
+    eviction_cursor: AtomicUsize,
+}
+
+impl<K: Eq + Hash + Sync, V: Sync, H: BuildHasher> HashCache<K, V, H> {
+    /// Creates an empty HashCache instance with the given hasher and capacity.
+    ///
+    /// The capacity is rounded up to the nearest power of two multiple of the cell size.
+    ///
+    /// # Examples
+    /// ```
+    /// use scc::HashCache;
+    /// use std::collections::hash_map::RandomState;
+    ///
+    /// let hashcache: HashCache<u64, u32, RandomState> = HashCache::new(RandomState::new(), 1000);
+    ///
+    /// let result = hashcache.capacity();
+    /// assert_eq!(result, 1024);
+    /// ```
+    pub fn new(hasher: H, capacity: usize) -> HashCache<K, V, H> {
+        Self::create(hasher, capacity, usize::MAX, None)
+    }
+
+    /// Creates an empty HashCache instance that limits the total weight of the key-value pairs.
+    ///
+    /// The weigher calculates the weight of a key-value pair, and it must return the same weight for the same
+    /// key-value pair. A key-value pair heavier than the maximum weight is evicted right after it is put.
+    ///
+    /// Only HashCache supports weights: scc::HashMap hands out mutable references to its values, through which the
+    /// weight of a key-value pair could change without the total weight being updated, and it has no constructor
+    /// taking a weigher.
+    ///
+    /// # Examples
+    /// ```
+    /// use scc::HashCache;
+    /// use std::collections::hash_map::RandomState;
+    ///
+    /// let hashcache: HashCache<u64, Vec<u8>, RandomState> =
+    ///     HashCache::with_weigher(RandomState::new(), 1024, 4096, |_, value: &Vec<u8>| value.len());
+    ///
+    /// let result = hashcache.put(1, vec![0; 3072]);
+    /// assert_eq!(result, Ok(Vec::new()));
+    ///
+    /// let result = hashcache.put(2, vec![0; 3072]);
+    /// assert_eq!(result.unwrap().len(), 1);
+    /// assert_eq!(hashcache.weight(), 3072);
+    /// ```
+    pub fn with_weigher<W: Fn(&K, &V) -> usize + Send + Sync + 'static>(
+        hasher: H,
+        capacity: usize,
+        max_weight: usize,
+        weigher: W,
+    ) -> HashCache<K, V, H> {
+        Self::create(hasher, capacity, max_weight, Some(Box::new(weigher)))
+    }
+
+    /// Creates an empty HashCache instance.
+    fn create(
+        hasher: H,
+        capacity: usize,
+        max_weight: usize,
+        weigher: Option<Box<WeighFn<K, V>>>,
+    ) -> HashCache<K, V, H> {
+        HashCache {
+            array: Array::new(capacity, false, false, Atomic::null()),
+            hasher,
+            weigher,
+            max_weight,
+            weight: AtomicUsize::new(0),
+            eviction_cursor: AtomicUsize::new(0),
+        }
+    }
+
+    /// Puts a key-value pair into the HashCache.
+    ///
+    /// It returns the evicted key-value pairs, or the given key-value pair if the key exists. A key-value pair is
+    /// evicted if the cell of the key was full, or the total weight exceeded the maximum weight.
+    ///
+    /// # Examples
+    /// ```
+    /// use scc::HashCache;
+    /// use std::collections::hash_map::RandomState;
+    ///
+    /// let hashcache: HashCache<u64, u32, RandomState> = HashCache::new(RandomState::new(), 32);
+    ///
+    /// let result = hashcache.put(1, 0);
+    /// assert_eq!(result, Ok(Vec::new()));
+    ///
+    /// let result = hashcache.put(1, 1);
+    /// assert_eq!(result, Err((1, 1)));
+    ///
+    /// let evicted: usize = (2..128).map(|key| hashcache.put(key, 0).unwrap().len()).sum();
+    /// assert_eq!(evicted + hashcache.len(), 127);
+    /// ```
+    pub fn put(&self, key: K, value: V) -> Result<Vec<(K, V)>, (K, V)> {
+        let (hash, partial_hash) = self.hash(&key);
+        let mut evicted = Vec::new();
+        {
+            let mut cell_locker = self.lock(hash);
+            if cell_locker.search(partial_hash, |k| *k == key).is_some() {
+                return Err((key, value));
+            }
+            if cell_locker.full() {
+                evicted.extend(cell_locker.evict());
+            }
+            cell_locker.insert(key, partial_hash, value);
+        }
+        if self.weight.load(Relaxed) > self.max_weight {
+            self.evict_overweight(&mut evicted);
+        }
+        Ok(evicted)
+    }
+
+    /// Reads a key-value pair, and marks it referenced.
+    ///
+    /// It acquires a shared lock on the cell.
+    ///
+    /// # Examples
+    /// ```
+    /// use scc::HashCache;
+    /// use std::collections::hash_map::RandomState;
+    ///
+    /// let hashcache: HashCache<u64, u32, RandomState> = HashCache::new(RandomState::new(), 32);
+    ///
+    /// let result = hashcache.put(1, 10);
+    /// assert!(result.is_ok());
+    ///
+    /// let result = hashcache.read(1, |key, value| *value);
+    /// assert_eq!(result.unwrap(), 10);
+    /// ```
+    pub fn read<U, F: FnOnce(&K, &V) -> U>(&self, key: K, f: F) -> Option<U> {
+        let (hash, partial_hash) = self.hash(&key);
+        let cell_index = self.array.calculate_cell_index(hash);
+        let cell_reader = CellReader::lock(
+            self.array.cell(cell_index),
+            self.array.entry_array(cell_index),
+        );
+        cell_reader
+            .search_and_reference(partial_hash, |k| *k == key)
+            .map(|entry_ptr| {
+                let entry_ref = unsafe { &(*entry_ptr) };
+                f(&entry_ref.0, &entry_ref.1)
+            })
+    }
+
+    /// Removes a key-value pair.
+    ///
+    /// # Examples
+    /// ```
+    /// use scc::HashCache;
+    /// use std::collections::hash_map::RandomState;
+    ///
+    /// let hashcache: HashCache<u64, u32, RandomState> = HashCache::new(RandomState::new(), 32);
+    ///
+    /// let result = hashcache.remove(1);
+    /// assert!(!result);
+    ///
+    /// let result = hashcache.put(1, 0);
+    /// assert!(result.is_ok());
+    ///
+    /// let result = hashcache.remove(1);
+    /// assert!(result);
+    /// ```
+    pub fn remove(&self, key: K) -> bool {
+        let (hash, partial_hash) = self.hash(&key);
+        let mut cell_locker = self.lock(hash);
+        if let Some((sub_index, entry_array_link_ptr, entry_ptr)) =
+            cell_locker.search(partial_hash, |k| *k == key)
+        {
+            cell_locker.remove(true, sub_index, entry_array_link_ptr, entry_ptr);
+            return true;
+        }
+        false
+    }
+
+    /// Clears all the key-value pairs.
+    ///
+    /// # Examples
+    /// ```
+    /// use scc::HashCache;
+    /// use std::collections::hash_map::RandomState;
+    ///
+    /// let hashcache: HashCache<u64, u32, RandomState> = HashCache::new(RandomState::new(), 32);
+    ///
+    /// let result = hashcache.put(1, 0);
+    /// assert!(result.is_ok());
+    ///
+    /// let result = hashcache.clear();
+    /// assert_eq!(result, 1);
+    /// ```
+    pub fn clear(&self) -> usize {
+        let mut num_removed = 0;
+        for cell_index in 0..self.array.num_cells() {
+            let mut cell_locker = self.lock_cell(cell_index);
+            while let Some((sub_index, entry_array_link_ptr, entry_ptr)) = cell_locker.first() {
+                cell_locker.remove(true, sub_index, entry_array_link_ptr, entry_ptr);
+                num_removed += 1;
+            }
+        }
+        num_removed
+    }
+
+    /// Returns the number of key-value pairs in the HashCache.
+    ///
+    /// It reads the metadata of every cell.
+    ///
+    /// # Examples
+    /// ```
+    /// use scc::HashCache;
+    /// use std::collections::hash_map::RandomState;
+    ///
+    /// let hashcache: HashCache<u64, u32, RandomState> = HashCache::new(RandomState::new(), 32);
+    ///
+    /// let result = hashcache.put(1, 0);
+    /// assert!(result.is_ok());
+    ///
+    /// let result = hashcache.len();
+    /// assert_eq!(result, 1);
+    /// ```
+    pub fn len(&self) -> usize {
+        (0..self.array.num_cells())
+            .map(|cell_index| self.array.cell(cell_index).size().0)
+            .sum()
+    }
+
+    /// Checks if the HashCache is empty.
+    ///
+    /// # Examples
+    /// ```
+    /// use scc::HashCache;
+    /// use std::collections::hash_map::RandomState;
+    ///
+    /// let hashcache: HashCache<u64, u32, RandomState> = HashCache::new(RandomState::new(), 32);
+    /// assert!(hashcache.is_empty());
+    /// ```
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Returns the capacity of the HashCache.
+    ///
+    /// # Examples
+    /// ```
+    /// use scc::HashCache;
+    /// use std::collections::hash_map::RandomState;
+    ///
+    /// let hashcache: HashCache<u64, u32, RandomState> = HashCache::new(RandomState::new(), 100);
+    ///
+    /// let result = hashcache.capacity();
+    /// assert_eq!(result, 128);
+    /// ```
+    pub fn capacity(&self) -> usize {
+        self.array.capacity()
+    }
+
+    /// Returns the total weight of the key-value pairs in the HashCache.
+    ///
+    /// It always returns zero if the HashCache was not created by with_weigher.
+    ///
+    /// # Examples
+    /// ```
+    /// use scc::HashCache;
+    /// use std::collections::hash_map::RandomState;
+    ///
+    /// let hashcache: HashCache<u64, u32, RandomState> =
+    ///     HashCache::with_weigher(RandomState::new(), 32, 100, |_, value| *value as usize);
+    ///
+    /// let result = hashcache.put(1, 10);
+    /// assert!(result.is_ok());
+    ///
+    /// let result = hashcache.put(2, 20);
+    /// assert!(result.is_ok());
+    /// assert_eq!(hashcache.weight(), 30);
+    ///
+    /// let result = hashcache.remove(1);
+    /// assert!(result);
+    /// assert_eq!(hashcache.weight(), 20);
+    /// ```
+    pub fn weight(&self) -> usize {
+        self.weight.load(Relaxed)
+    }
+
+    fn hash(&self, key: &K) -> (u64, u16) {
+        let mut h = self.hasher.build_hasher();
+        key.hash(&mut h);
+        HashMap::<K, V, H>::mix(h.finish())
+    }
+
+    /// Locks the cell that is supposed to own the hash value.
+    fn lock<'a>(&'a self, hash: u64) -> CellLocker<'a, K, V> {
+        self.lock_cell(self.array.calculate_cell_index(hash))
+    }
+
+    /// Locks the cell at the given index.
+    fn lock_cell<'a>(&'a self, cell_index: usize) -> CellLocker<'a, K, V> {
+        let mut cell_locker = CellLocker::lock(
+            self.array.cell(cell_index),
+            self.array.entry_array(cell_index),
+        );
+        cell_locker.set_weigher(
+            self.weigher
+                .as_ref()
+                .map(|weigh| Weigher::new(weigh.as_ref(), &self.weight)),
+        );
+        cell_locker
+    }
+
+    /// Evicts key-value pairs until the total weight falls within the maximum weight.
+    ///
+    /// Cells are locked one at a time starting from the eviction cursor, and each cell is visited at most once.
+    fn evict_overweight(&self, evicted: &mut Vec<(K, V)>) {
+        let num_cells = self.array.num_cells();
+        for _ in 0..num_cells {
+            let cell_index = self.eviction_cursor.fetch_add(1, Relaxed) % num_cells;
+            let mut cell_locker = self.lock_cell(cell_index);
+            while self.weight.load(Relaxed) > self.max_weight {
+                if let Some(entry) = cell_locker.evict() {
+                    evicted.push(entry);
+                } else {
+                    break;
+                }
+            }
+            if self.weight.load(Relaxed) <= self.max_weight {
+                return;
+            }
+        }
+    }
+}
+
+impl<K: Eq + Hash + Sync, V: Sync, H: BuildHasher> Drop for HashCache<K, V, H> {
+    fn drop(&mut self) {
+        self.clear();
+    }
+}
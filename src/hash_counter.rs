@@ -0,0 +1,180 @@
+use super::map::HashMap;
+use std::hash::{BuildHasher, Hash};
+use std::sync::atomic::AtomicU64;
+use std::sync::atomic::Ordering::Relaxed;
+
+/// A scalable concurrent counter map implementation.
+///
+/// scc::HashCounter is built on the metadata cells and entry arrays of scc::HashMap, and each key owns a 64-bit
+/// atomic counter. Adding a value to an existing counter is done by an atomic instruction under a shared lock on the
+/// cell, therefore threads incrementing the same counter or counters in the same cell do not block each other; the
+/// cell is exclusively locked only when a new counter is inserted.
+///
+/// The key features of scc::HashCounter.
+/// * Lock-free increments of existing counters with a shared lock on the cell.
+/// * Counters wrap around on overflow.
+pub struct HashCounter<K: Eq + Hash + Sync, H: BuildHasher> {
+    map: HashMap<K, AtomicU64, H>,
+}
+
+impl<K: Eq + Hash + Sync, H: BuildHasher> HashCounter<K, H> {
+    /// Creates an empty HashCounter instance with the given hasher and minimum capacity.
+    ///
+    /// # Examples
+    /// ```
+    /// use scc::HashCounter;
+    /// use std::collections::hash_map::RandomState;
+    ///
+    /// let hashcounter: HashCounter<u64, RandomState> = HashCounter::new(RandomState::new(), Some(1000));
+    ///
+    /// let result = hashcounter.capacity();
+    /// assert_eq!(result, 1024);
+    /// ```
+    pub fn new(hasher: H, minimum_capacity: Option<usize>) -> HashCounter<K, H> {
+        HashCounter {
+            map: HashMap::new(hasher, minimum_capacity),
+        }
+    }
+
+    /// Adds the given value to the counter of the key, and returns the updated counter.
+    ///
+    /// A new counter is inserted if the key does not exist.
+    ///
+    /// # Examples
+    /// ```
+    /// use scc::HashCounter;
+    /// use std::collections::hash_map::RandomState;
+    ///
+    /// let hashcounter: HashCounter<u64, RandomState> = HashCounter::new(RandomState::new(), None);
+    ///
+    /// let result = hashcounter.add(1, 2);
+    /// assert_eq!(result, 2);
+    ///
+    /// let result = hashcounter.add(1, 3);
+    /// assert_eq!(result, 5);
+    /// ```
+    pub fn add(&self, key: K, delta: u64) -> u64 {
+        let (hash, partial_hash) = self.map.hash(&key);
+        let add = |counter: &AtomicU64| counter.fetch_add(delta, Relaxed).wrapping_add(delta);
+        if let Some(result) =
+            self.map
+                .read_entry(|k| *k == key, hash, partial_hash, |_, counter| add(counter))
+        {
+            return result;
+        }
+        match self
+            .map
+            .insert_entry(key, hash, partial_hash, AtomicU64::new(delta), 0)
+        {
+            Ok(_) => delta,
+            // the counter has been inserted by another thread in the meantime
+            Err((accessor, _)) => add(accessor.get().1),
+        }
+    }
+
+    /// Returns the counter of the key.
+    ///
+    /// It returns zero if the key does not exist.
+    ///
+    /// # Examples
+    /// ```
+    /// use scc::HashCounter;
+    /// use std::collections::hash_map::RandomState;
+    ///
+    /// let hashcounter: HashCounter<u64, RandomState> = HashCounter::new(RandomState::new(), None);
+    ///
+    /// let result = hashcounter.get(1);
+    /// assert_eq!(result, 0);
+    ///
+    /// hashcounter.add(1, 1);
+    ///
+    /// let result = hashcounter.get(1);
+    /// assert_eq!(result, 1);
+    /// ```
+    pub fn get(&self, key: K) -> u64 {
+        self.map
+            .read(key, |_, counter| counter.load(Relaxed))
+            .unwrap_or(0)
+    }
+
+    /// Removes the counter of the key, and returns the last value of the counter.
+    ///
+    /// # Examples
+    /// ```
+    /// use scc::HashCounter;
+    /// use std::collections::hash_map::RandomState;
+    ///
+    /// let hashcounter: HashCounter<u64, RandomState> = HashCounter::new(RandomState::new(), None);
+    ///
+    /// let result = hashcounter.remove(1);
+    /// assert!(result.is_none());
+    ///
+    /// hashcounter.add(1, 1);
+    ///
+    /// let result = hashcounter.remove(1);
+    /// assert_eq!(result, Some(1));
+    /// ```
+    pub fn remove(&self, key: K) -> Option<u64> {
+        self.map.get(key).map(|accessor| {
+            // no other threads can update the counter while the cell is exclusively locked
+            let counter = accessor.get().1.load(Relaxed);
+            accessor.erase();
+            counter
+        })
+    }
+
+    /// Clears all the counters.
+    ///
+    /// # Examples
+    /// ```
+    /// use scc::HashCounter;
+    /// use std::collections::hash_map::RandomState;
+    ///
+    /// let hashcounter: HashCounter<u64, RandomState> = HashCounter::new(RandomState::new(), None);
+    ///
+    /// hashcounter.add(1, 1);
+    /// hashcounter.add(2, 1);
+    ///
+    /// let result = hashcounter.clear();
+    /// assert_eq!(result, 2);
+    /// ```
+    pub fn clear(&self) -> usize {
+        self.map.clear()
+    }
+
+    /// Returns an estimated number of counters in the HashCounter.
+    ///
+    /// It passes the capacity of the HashCounter to the given function.
+    ///
+    /// # Examples
+    /// ```
+    /// use scc::HashCounter;
+    /// use std::collections::hash_map::RandomState;
+    ///
+    /// let hashcounter: HashCounter<u64, RandomState> = HashCounter::new(RandomState::new(), None);
+    ///
+    /// hashcounter.add(1, 1);
+    ///
+    /// let result = hashcounter.len(|capacity| capacity);
+    /// assert_eq!(result, 1);
+    /// ```
+    pub fn len<F: FnOnce(usize) -> usize>(&self, f: F) -> usize {
+        self.map.len(f)
+    }
+
+    /// Returns the capacity of the HashCounter.
+    ///
+    /// # Examples
+    /// ```
+    /// use scc::HashCounter;
+    /// use std::collections::hash_map::RandomState;
+    ///
+    /// let hashcounter: HashCounter<u64, RandomState> = HashCounter::new(RandomState::new(), Some(1000000));
+    ///
+    /// let result = hashcounter.capacity();
+    /// assert_eq!(result, 1048576);
+    /// ```
+    pub fn capacity(&self) -> usize {
+        self.map.capacity()
+    }
+}
@@ -0,0 +1,686 @@
+extern crate crossbeam_epoch;
+
+use super::map::array::{Array, Rehashing};
+use super::map::cell::ARRAY_SIZE;
+use super::map::HashMap;
+use crossbeam_epoch::{Atomic, Guard, Owned, Shared};
+use std::hash::{BuildHasher, Hash, Hasher};
+use std::sync::atomic::AtomicBool;
+use std::sync::atomic::Ordering::{Acquire, Relaxed, Release};
+use std::sync::Mutex;
+
+/// The tag of a bucket pointer representing that the cell has been killed.
+const KILLED_TAG: usize = 1;
+
+/// A read-optimized concurrent hash index implementation.
+///
+/// scc::HashIndex is a concurrent hash index data structure that is targeted at a read-mostly workload.
+/// Readers of scc::HashIndex never acquire a lock or modify shared memory, instead they search an immutable bucket of
+/// key-value pairs published by writers through crossbeam_epoch::Atomic pointers.
+/// Writers exclusively lock a cell, make a copy of the bucket of the cell, modify it, and publish the copy,
+/// and the epoch-based reclamation technique guarantees that the replaced bucket is not reclaimed while being read.
+/// The array of cells is sized, indexed, resized and incrementally rehashed in the same way as the array of
+/// scc::HashMap, and the hash value of each key-value pair is cached in the bucket, thus relocating key-value pairs
+/// never hashes the keys.
+///
+/// The key features of scc::HashIndex.
+/// * Lock-free read: readers never block, and never write shared memory.
+/// * Copy-on-write: each modification replaces the bucket of a cell.
+/// * Auto resizing: it automatically enlarges or shrinks the internal array.
+/// * Incremental resizing: each modification relocates a certain number of key-value pairs.
+pub struct HashIndex<K: Eq + Hash + Sync, V: Sync, H: BuildHasher> {
+    array: Atomic<IndexArray<K, V>>,
+    minimum_capacity: usize,
+    resize_mutex: AtomicBool,
+    hasher: H,
+}
+
+impl<K: Eq + Hash + Sync, V: Sync, H: BuildHasher> HashIndex<K, V, H> {
+    /// Creates an empty HashIndex instance with the given hasher and minimum capacity.
+    ///
+    /// # Examples
+    /// ```
+    /// use scc::HashIndex;
+    /// use std::collections::hash_map::RandomState;
+    ///
+    /// let hashindex: HashIndex<u64, u32, RandomState> = HashIndex::new(RandomState::new(), Some(1000));
+    ///
+    /// let result = hashindex.capacity();
+    /// assert_eq!(result, 1024);
+    ///
+    /// let hashindex: HashIndex<u64, u32, RandomState> = HashIndex::new(RandomState::new(), None);
+    /// let result = hashindex.capacity();
+    /// assert_eq!(result, 256);
+    /// ```
+    pub fn new(hasher: H, minimum_capacity: Option<usize>) -> HashIndex<K, V, H> {
+        let initial_capacity = if let Some(capacity) = minimum_capacity {
+            capacity.max(256)
+        } else {
+            256
+        };
+        HashIndex {
+            array: Atomic::new(IndexArray::new(initial_capacity, Atomic::null())),
+            minimum_capacity: initial_capacity,
+            resize_mutex: AtomicBool::new(false),
+            hasher,
+        }
+    }
+
+    /// Inserts a key-value pair into the HashIndex.
+    ///
+    /// It returns the key-value pair if the key exists.
+    ///
+    /// # Examples
+    /// ```
+    /// use scc::HashIndex;
+    /// use std::collections::hash_map::RandomState;
+    ///
+    /// let hashindex: HashIndex<u64, u32, RandomState> = HashIndex::new(RandomState::new(), None);
+    ///
+    /// let result = hashindex.insert(1, 0);
+    /// assert!(result.is_ok());
+    ///
+    /// let result = hashindex.insert(1, 1);
+    /// assert_eq!(result, Err((1, 1)));
+    /// ```
+    pub fn insert(&self, key: K, value: V) -> Result<(), (K, V)> {
+        let hash = self.hash(&key);
+        let mut resize_triggered = false;
+        let guard = crossbeam_epoch::pin();
+        loop {
+            let (cell_ref, lock, cell_index) = self.acquire(hash, &guard);
+            let bucket_ref = unsafe { cell_ref.bucket.load(Acquire, &guard).deref() };
+            if bucket_ref.search(&key, hash, &guard).is_some() {
+                return Err((key, value));
+            }
+            if !resize_triggered
+                && bucket_ref.entries.len() >= ARRAY_SIZE as usize
+                && cell_index < ARRAY_SIZE as usize
+            {
+                drop(lock);
+                self.resize(false);
+                resize_triggered = true;
+                continue;
+            }
+            let mut new_bucket = bucket_ref.clone_entries(&guard);
+            new_bucket.entries.push((hash, Atomic::new((key, value))));
+            cell_ref.replace(new_bucket, &guard);
+            return Ok(());
+        }
+    }
+
+    /// Upserts a key-value pair into the HashIndex.
+    ///
+    /// The existing key-value pair is replaced with a newly allocated one, and therefore readers
+    /// holding a reference to the old one are not affected.
+    ///
+    /// # Examples
+    /// ```
+    /// use scc::HashIndex;
+    /// use std::collections::hash_map::RandomState;
+    ///
+    /// let hashindex: HashIndex<u64, u32, RandomState> = HashIndex::new(RandomState::new(), None);
+    ///
+    /// hashindex.upsert(1, 0);
+    /// hashindex.upsert(1, 1);
+    ///
+    /// let result = hashindex.read(1, |key, value| *value);
+    /// assert_eq!(result.unwrap(), 1);
+    /// ```
+    pub fn upsert(&self, key: K, value: V) {
+        if let Err((key, value)) = self.insert(key, value) {
+            let hash = self.hash(&key);
+            let guard = crossbeam_epoch::pin();
+            let (cell_ref, _lock, _) = self.acquire(hash, &guard);
+            let bucket_ref = unsafe { cell_ref.bucket.load(Acquire, &guard).deref() };
+            let mut new_bucket = bucket_ref.clone_entries(&guard);
+            if let Some(index) = bucket_ref.search(&key, hash, &guard) {
+                let old_entry =
+                    new_bucket.entries[index]
+                        .1
+                        .swap(Owned::new((key, value)), Relaxed, &guard);
+                cell_ref.replace(new_bucket, &guard);
+                unsafe { guard.defer_destroy(old_entry) };
+            } else {
+                new_bucket.entries.push((hash, Atomic::new((key, value))));
+                cell_ref.replace(new_bucket, &guard);
+            }
+        }
+    }
+
+    /// Removes a key-value pair.
+    ///
+    /// # Examples
+    /// ```
+    /// use scc::HashIndex;
+    /// use std::collections::hash_map::RandomState;
+    ///
+    /// let hashindex: HashIndex<u64, u32, RandomState> = HashIndex::new(RandomState::new(), None);
+    ///
+    /// let result = hashindex.remove(1);
+    /// assert!(!result);
+    ///
+    /// let result = hashindex.insert(1, 0);
+    /// assert!(result.is_ok());
+    ///
+    /// let result = hashindex.remove(1);
+    /// assert!(result);
+    /// ```
+    pub fn remove(&self, key: K) -> bool {
+        let hash = self.hash(&key);
+        let guard = crossbeam_epoch::pin();
+        let (cell_ref, lock, cell_index) = self.acquire(hash, &guard);
+        let bucket_ref = unsafe { cell_ref.bucket.load(Acquire, &guard).deref() };
+        if let Some(index) = bucket_ref.search(&key, hash, &guard) {
+            let mut new_bucket = bucket_ref.clone_entries(&guard);
+            let (_, removed_entry) = new_bucket.entries.swap_remove(index);
+            let empty = new_bucket.entries.is_empty();
+            cell_ref.replace(new_bucket, &guard);
+            unsafe { guard.defer_destroy(removed_entry.load(Relaxed, &guard)) };
+            drop(lock);
+            if empty && cell_index < ARRAY_SIZE as usize {
+                self.resize(true);
+            }
+            return true;
+        }
+        false
+    }
+
+    /// Reads a key-value pair.
+    ///
+    /// It neither acquires a lock nor modifies shared memory.
+    ///
+    /// # Examples
+    /// ```
+    /// use scc::HashIndex;
+    /// use std::collections::hash_map::RandomState;
+    ///
+    /// let hashindex: HashIndex<u64, u32, RandomState> = HashIndex::new(RandomState::new(), None);
+    ///
+    /// let result = hashindex.insert(1, 0);
+    /// assert!(result.is_ok());
+    ///
+    /// let result = hashindex.read(1, |key, value| *value);
+    /// assert_eq!(result.unwrap(), 0);
+    /// ```
+    pub fn read<U, F: FnOnce(&K, &V) -> U>(&self, key: K, f: F) -> Option<U> {
+        let hash = self.hash(&key);
+        let guard = crossbeam_epoch::pin();
+
+        // an acquire fence is required to correctly load the contents of the array
+        let mut current_array = self.array.load(Acquire, &guard);
+        loop {
+            let current_array_ref = unsafe { current_array.deref() };
+            let old_array = current_array_ref.old_array.load(Acquire, &guard);
+            if !old_array.is_null() {
+                // the old cell is authoritative until it is killed
+                let old_array_ref = unsafe { old_array.deref() };
+                let bucket = old_array_ref.cell(hash).bucket.load(Acquire, &guard);
+                if bucket.tag() != KILLED_TAG {
+                    return unsafe { bucket.deref() }.read(&key, hash, f, &guard);
+                }
+            }
+            let bucket = current_array_ref.cell(hash).bucket.load(Acquire, &guard);
+            if bucket.tag() != KILLED_TAG {
+                return unsafe { bucket.deref() }.read(&key, hash, f, &guard);
+            }
+
+            // the array has been replaced in the meantime
+            current_array = self.array.load(Acquire, &guard);
+        }
+    }
+
+    /// Retains the key-value pairs that satisfy the given predicate.
+    ///
+    /// It returns the number of entries remaining and removed.
+    ///
+    /// # Examples
+    /// ```
+    /// use scc::HashIndex;
+    /// use std::collections::hash_map::RandomState;
+    ///
+    /// let hashindex: HashIndex<u64, u32, RandomState> = HashIndex::new(RandomState::new(), None);
+    ///
+    /// assert!(hashindex.insert(1, 0).is_ok());
+    /// assert!(hashindex.insert(2, 0).is_ok());
+    ///
+    /// let result = hashindex.retain(|key, value| *key == 1 && *value == 0);
+    /// assert_eq!(result, (1, 1));
+    ///
+    /// assert!(hashindex.read(1, |_, _| ()).is_some());
+    /// assert!(hashindex.read(2, |_, _| ()).is_none());
+    /// ```
+    pub fn retain<F: Fn(&K, &V) -> bool>(&self, f: F) -> (usize, usize) {
+        let mut retained_entries = 0;
+        let mut removed_entries = 0;
+        let guard = crossbeam_epoch::pin();
+
+        // relocate all the key-value pairs into the current array before scanning it
+        let mut current_array = self.array.load(Acquire, &guard);
+        loop {
+            let current_array_ref = unsafe { current_array.deref() };
+            while !current_array_ref.old_array.load(Acquire, &guard).is_null() {
+                current_array_ref.partial_rehash(&guard);
+            }
+            for cell_ref in current_array_ref.cells.iter() {
+                let _lock = cell_ref.mutex.lock().unwrap();
+                let bucket = cell_ref.bucket.load(Acquire, &guard);
+                if bucket.tag() == KILLED_TAG {
+                    continue;
+                }
+                let bucket_ref = unsafe { bucket.deref() };
+                let mut new_bucket = Bucket::default();
+                let mut removed = Vec::new();
+                for (hash, entry) in bucket_ref.entries.iter() {
+                    let entry_shared = entry.load(Relaxed, &guard);
+                    let entry_ref = unsafe { entry_shared.deref() };
+                    if f(&entry_ref.0, &entry_ref.1) {
+                        new_bucket.entries.push((*hash, Atomic::from(entry_shared)));
+                    } else {
+                        removed.push(entry_shared);
+                    }
+                }
+                retained_entries += new_bucket.entries.len();
+                removed_entries += removed.len();
+                if !removed.is_empty() {
+                    cell_ref.replace(new_bucket, &guard);
+                    for entry in removed {
+                        unsafe { guard.defer_destroy(entry) };
+                    }
+                }
+            }
+
+            // scan the new array if the array has been replaced in the meantime
+            let new_array = self.array.load(Acquire, &guard);
+            if new_array == current_array {
+                break;
+            }
+            current_array = new_array;
+        }
+
+        if removed_entries > retained_entries {
+            self.resize(true);
+        }
+        (retained_entries, removed_entries)
+    }
+
+    /// Clears all the key-value pairs.
+    ///
+    /// # Examples
+    /// ```
+    /// use scc::HashIndex;
+    /// use std::collections::hash_map::RandomState;
+    ///
+    /// let hashindex: HashIndex<u64, u32, RandomState> = HashIndex::new(RandomState::new(), None);
+    ///
+    /// assert!(hashindex.insert(1, 0).is_ok());
+    /// assert!(hashindex.insert(2, 0).is_ok());
+    ///
+    /// let result = hashindex.clear();
+    /// assert_eq!(result, 2);
+    /// ```
+    pub fn clear(&self) -> usize {
+        self.retain(|_, _| false).1
+    }
+
+    /// Returns an estimated size of the HashIndex.
+    ///
+    /// It passes the capacity of the HashIndex to the given function.
+    ///
+    /// # Examples
+    /// ```
+    /// use scc::HashIndex;
+    /// use std::collections::hash_map::RandomState;
+    ///
+    /// let hashindex: HashIndex<u64, u32, RandomState> = HashIndex::new(RandomState::new(), None);
+    ///
+    /// assert!(hashindex.insert(1, 0).is_ok());
+    ///
+    /// let result = hashindex.len(|capacity| capacity);
+    /// assert_eq!(result, 1);
+    /// ```
+    pub fn len<F: FnOnce(usize) -> usize>(&self, f: F) -> usize {
+        let guard = crossbeam_epoch::pin();
+        let current_array = self.array.load(Acquire, &guard);
+        let current_array_ref = unsafe { current_array.deref() };
+        let capacity = current_array_ref.capacity();
+        let num_samples = std::cmp::min(f(capacity), capacity).next_power_of_two();
+        let num_cells_to_sample = (num_samples / ARRAY_SIZE as usize).max(1);
+        if !current_array_ref.old_array.load(Acquire, &guard).is_null() {
+            for _ in 0..num_cells_to_sample {
+                if current_array_ref.partial_rehash(&guard) {
+                    break;
+                }
+            }
+        }
+        let mut num_entries = 0;
+        for cell_ref in current_array_ref.cells.iter().take(num_cells_to_sample) {
+            let bucket = cell_ref.bucket.load(Acquire, &guard);
+            if bucket.tag() != KILLED_TAG {
+                num_entries += unsafe { bucket.deref() }.entries.len();
+            }
+        }
+        num_entries * (current_array_ref.num_cells() / num_cells_to_sample)
+    }
+
+    /// Returns the capacity of the HashIndex.
+    ///
+    /// # Examples
+    /// ```
+    /// use scc::HashIndex;
+    /// use std::collections::hash_map::RandomState;
+    ///
+    /// let hashindex: HashIndex<u64, u32, RandomState> = HashIndex::new(RandomState::new(), Some(1000000));
+    ///
+    /// let result = hashindex.capacity();
+    /// assert_eq!(result, 1048576);
+    /// ```
+    pub fn capacity(&self) -> usize {
+        let guard = crossbeam_epoch::pin();
+        let current_array = self.array.load(Acquire, &guard);
+        let current_array_ref = unsafe { current_array.deref() };
+        if !current_array_ref.old_array.load(Acquire, &guard).is_null() {
+            current_array_ref.partial_rehash(&guard);
+        }
+        current_array_ref.capacity()
+    }
+
+    /// Returns a hash value of the given key.
+    fn hash(&self, key: &K) -> u64 {
+        let mut h = self.hasher.build_hasher();
+        key.hash(&mut h);
+        HashMap::<K, V, H>::mix(h.finish()).0
+    }
+
+    /// Locks the cell that is supposed to own the hash value.
+    ///
+    /// The corresponding cell in the old array is killed before the cell in the current array is locked.
+    fn acquire<'g>(
+        &self,
+        hash: u64,
+        guard: &'g Guard,
+    ) -> (&'g IndexCell<K, V>, std::sync::MutexGuard<'g, ()>, usize) {
+        loop {
+            // an acquire fence is required to correctly load the contents of the array
+            let current_array = self.array.load(Acquire, guard);
+            let current_array_ref = unsafe { current_array.deref() };
+            let old_array = current_array_ref.old_array.load(Acquire, guard);
+            if !old_array.is_null() {
+                if current_array_ref.partial_rehash(guard) {
+                    continue;
+                }
+                let old_array_ref = unsafe { old_array.deref() };
+                let old_cell_index = old_array_ref.calculate_cell_index(hash);
+                current_array_ref.kill_cell(old_array_ref, old_cell_index, guard);
+            }
+            let cell_index = current_array_ref.calculate_cell_index(hash);
+            let cell_ref = current_array_ref.cell(hash);
+            let lock = cell_ref.mutex.lock().unwrap();
+            if cell_ref.bucket.load(Acquire, guard).tag() != KILLED_TAG {
+                return (cell_ref, lock, cell_index);
+            }
+            // reaching here indicates that self.array is updated
+        }
+    }
+
+    /// Resizes the array.
+    fn resize(&self, shrink: bool) {
+        let guard = crossbeam_epoch::pin();
+        let current_array = self.array.load(Acquire, &guard);
+        let current_array_ref = unsafe { current_array.deref() };
+        if !current_array_ref.old_array.load(Acquire, &guard).is_null() {
+            if !current_array_ref.partial_rehash(&guard) {
+                return;
+            }
+        } else if shrink && current_array_ref.capacity() == self.minimum_capacity {
+            return;
+        }
+
+        if !self.resize_mutex.swap(true, Acquire) {
+            if current_array != self.array.load(Acquire, &guard) {
+                self.resize_mutex.store(false, Release);
+                return;
+            }
+
+            let capacity = current_array_ref.capacity();
+            let estimated_num_entries = self.len(|capacity| (capacity / 16).min(16384));
+            let new_capacity = Array::<K, V>::calculate_new_capacity(
+                capacity,
+                estimated_num_entries,
+                self.minimum_capacity,
+            );
+
+            if new_capacity != capacity {
+                let new_array = IndexArray::new(new_capacity, Atomic::from(current_array));
+                self.array.store(Owned::new(new_array), Release);
+            }
+
+            self.resize_mutex.store(false, Release);
+        }
+    }
+}
+
+impl<K: Eq + Hash + Sync, V: Sync, H: BuildHasher> Drop for HashIndex<K, V, H> {
+    fn drop(&mut self) {
+        self.clear();
+        let guard = unsafe { crossbeam_epoch::unprotected() };
+        let current_array = self.array.swap(Shared::null(), Relaxed, guard);
+        if !current_array.is_null() {
+            drop(unsafe { current_array.into_owned() });
+        }
+    }
+}
+
+/// IndexArray is the array of IndexCell instances, sharing the layout of the Array of HashMap.
+struct IndexArray<K: Eq, V> {
+    cells: Vec<IndexCell<K, V>>,
+    lb_capacity: u8,
+    rehashing: Rehashing,
+    old_array: Atomic<IndexArray<K, V>>,
+}
+
+impl<K: Eq, V> IndexArray<K, V> {
+    fn new(capacity: usize, old_array: Atomic<IndexArray<K, V>>) -> IndexArray<K, V> {
+        let lb_capacity = Array::<K, V>::calculate_lb_metadata_array_size(capacity);
+        IndexArray {
+            cells: (0..(1usize << lb_capacity))
+                .map(|_| IndexCell::default())
+                .collect(),
+            lb_capacity,
+            rehashing: Rehashing::new(),
+            old_array,
+        }
+    }
+
+    fn cell(&self, hash: u64) -> &IndexCell<K, V> {
+        &self.cells[self.calculate_cell_index(hash)]
+    }
+
+    fn num_cells(&self) -> usize {
+        1usize << self.lb_capacity
+    }
+
+    fn capacity(&self) -> usize {
+        (1usize << self.lb_capacity) * (ARRAY_SIZE as usize)
+    }
+
+    fn calculate_cell_index(&self, hash: u64) -> usize {
+        Array::<K, V>::cell_index(hash, self.lb_capacity)
+    }
+
+    /// Relocates the key-value pairs in the old cell, and kills it.
+    fn kill_cell(&self, old_array: &IndexArray<K, V>, old_cell_index: usize, guard: &Guard) {
+        let old_cell_ref = &old_array.cells[old_cell_index];
+        let _lock = old_cell_ref.mutex.lock().unwrap();
+        let old_bucket = old_cell_ref.bucket.load(Acquire, guard);
+        if old_bucket.tag() == KILLED_TAG {
+            return;
+        }
+
+        let (target_cell_index, num_target_cells) = Array::<K, V>::relocation_target(
+            old_array.lb_capacity,
+            self.lb_capacity,
+            old_cell_index,
+        );
+        let old_bucket_ref = unsafe { old_bucket.deref() };
+        if !old_bucket_ref.entries.is_empty() {
+            // target cells are locked in ascending order
+            let mut new_buckets: Vec<Option<Bucket<K, V>>> =
+                (0..num_target_cells).map(|_| None).collect();
+            let target_locks: Vec<std::sync::MutexGuard<()>> = (0..num_target_cells)
+                .map(|i| self.cells[target_cell_index + i].mutex.lock().unwrap())
+                .collect();
+            for (hash, entry) in old_bucket_ref.entries.iter() {
+                // cached hash values spare the cost of hashing the key
+                let offset = self.calculate_cell_index(*hash) - target_cell_index;
+                debug_assert!(offset < num_target_cells);
+                new_buckets[offset]
+                    .get_or_insert_with(|| {
+                        let bucket = self.cells[target_cell_index + offset]
+                            .bucket
+                            .load(Acquire, guard);
+                        unsafe { bucket.deref() }.clone_entries(guard)
+                    })
+                    .entries
+                    .push((*hash, Atomic::from(entry.load(Relaxed, guard))));
+            }
+
+            // the relocated key-value pairs are published before the old cell is killed
+            for (offset, new_bucket) in new_buckets.into_iter().enumerate() {
+                if let Some(new_bucket) = new_bucket {
+                    self.cells[target_cell_index + offset].replace(new_bucket, guard);
+                }
+            }
+            drop(target_locks);
+        }
+
+        let killed = old_cell_ref
+            .bucket
+            .swap(Shared::null().with_tag(KILLED_TAG), Release, guard);
+        unsafe { guard.defer_destroy(killed) };
+    }
+
+    /// Relocates a fixed number of cells in the old array.
+    ///
+    /// It returns true if the old array is completely rehashed.
+    fn partial_rehash(&self, guard: &Guard) -> bool {
+        let old_array = self.old_array.load(Acquire, guard);
+        if old_array.is_null() {
+            return true;
+        }
+
+        let old_array_ref = unsafe { old_array.deref() };
+        let old_array_size = old_array_ref.num_cells();
+        let old_cell_range = if let Some(old_cell_range) = self.rehashing.claim(old_array_size) {
+            old_cell_range
+        } else {
+            return false;
+        };
+        for old_cell_index in old_cell_range {
+            self.kill_cell(old_array_ref, old_cell_index, guard);
+        }
+
+        if self.rehashing.complete(old_array_size) {
+            let old_array = self.old_array.swap(Shared::null(), Release, guard);
+            if !old_array.is_null() {
+                unsafe { guard.defer_destroy(old_array) };
+            }
+            return true;
+        }
+        false
+    }
+}
+
+impl<K: Eq, V> Drop for IndexArray<K, V> {
+    fn drop(&mut self) {
+        // key-value pairs are owned by the HashIndex, and buckets are owned by the IndexArray
+        let guard = unsafe { crossbeam_epoch::unprotected() };
+        for cell in self.cells.iter() {
+            let bucket = cell.bucket.swap(Shared::null(), Relaxed, guard);
+            if !bucket.with_tag(0).is_null() {
+                drop(unsafe { bucket.into_owned() });
+            }
+        }
+
+        // the old array is not reclaimed yet only if the HashIndex is being dropped
+        let old_array = self.old_array.swap(Shared::null(), Relaxed, guard);
+        if !old_array.is_null() {
+            drop(unsafe { old_array.into_owned() });
+        }
+    }
+}
+
+/// IndexCell publishes an immutable Bucket, and serializes writers.
+struct IndexCell<K: Eq, V> {
+    mutex: Mutex<()>,
+    bucket: Atomic<Bucket<K, V>>,
+}
+
+impl<K: Eq, V> IndexCell<K, V> {
+    /// Publishes the new bucket, and reclaims the old one.
+    ///
+    /// The cell must be locked.
+    fn replace(&self, new_bucket: Bucket<K, V>, guard: &Guard) {
+        let old_bucket = self.bucket.swap(Owned::new(new_bucket), Release, guard);
+        unsafe { guard.defer_destroy(old_bucket) };
+    }
+}
+
+impl<K: Eq, V> Default for IndexCell<K, V> {
+    fn default() -> Self {
+        IndexCell {
+            mutex: Mutex::new(()),
+            bucket: Atomic::new(Bucket::default()),
+        }
+    }
+}
+
+/// Bucket is an immutable array of key-value pairs and their hash values.
+///
+/// Dropping a Bucket does not drop the key-value pairs.
+struct Bucket<K: Eq, V> {
+    entries: Vec<(u64, Atomic<(K, V)>)>,
+}
+
+impl<K: Eq, V> Bucket<K, V> {
+    fn search(&self, key: &K, hash: u64, guard: &Guard) -> Option<usize> {
+        for (index, (entry_hash, entry)) in self.entries.iter().enumerate() {
+            if *entry_hash == hash && unsafe { entry.load(Acquire, guard).deref() }.0 == *key {
+                return Some(index);
+            }
+        }
+        None
+    }
+
+    fn read<U, F: FnOnce(&K, &V) -> U>(
+        &self,
+        key: &K,
+        hash: u64,
+        f: F,
+        guard: &Guard,
+    ) -> Option<U> {
+        self.search(key, hash, guard).map(|index| {
+            let entry_ref = unsafe { self.entries[index].1.load(Acquire, guard).deref() };
+            f(&entry_ref.0, &entry_ref.1)
+        })
+    }
+
+    /// Makes a copy of the Bucket sharing the key-value pairs.
+    fn clone_entries(&self, guard: &Guard) -> Bucket<K, V> {
+        Bucket {
+            entries: self
+                .entries
+                .iter()
+                .map(|(hash, entry)| (*hash, Atomic::from(entry.load(Relaxed, guard))))
+                .collect(),
+        }
+    }
+}
+
+impl<K: Eq, V> Default for Bucket<K, V> {
+    fn default() -> Self {
+        Bucket {
+            entries: Vec::new(),
+        }
+    }
+}
@@ -0,0 +1,246 @@
+use super::map::HashMap;
+use std::hash::{BuildHasher, Hash};
+
+/// A scalable concurrent multimap implementation.
+///
+/// scc::HashMultiMap is built on the metadata cells and entry arrays of scc::HashMap; each key owns a vector of
+/// values that is stored in the entry of the key. Modifying the values of a key exclusively locks the cell
+/// owning the key only once, and reading them acquires a shared lock on the cell.
+///
+/// The key features of scc::HashMultiMap.
+/// * Compact: the values of a key are stored in a single vector, and the entry is removed when the last value is
+///   removed.
+/// * Multiset semantics: the same key-value pair can be inserted more than once, and inserting a value only appends
+///   it to the vector.
+pub struct HashMultiMap<K: Eq + Hash + Sync, V: Sync, H: BuildHasher> {
+    map: HashMap<K, Vec<V>, H>,
+}
+
+impl<K: Eq + Hash + Sync, V: Sync, H: BuildHasher> HashMultiMap<K, V, H> {
+    /// Creates an empty HashMultiMap instance with the given hasher and minimum capacity.
+    ///
+    /// # Examples
+    /// ```
+    /// use scc::HashMultiMap;
+    /// use std::collections::hash_map::RandomState;
+    ///
+    /// let hashmultimap: HashMultiMap<u64, u32, RandomState> =
+    ///     HashMultiMap::new(RandomState::new(), Some(1000));
+    ///
+    /// let result = hashmultimap.capacity();
+    /// assert_eq!(result, 1024);
+    /// ```
+    pub fn new(hasher: H, minimum_capacity: Option<usize>) -> HashMultiMap<K, V, H> {
+        HashMultiMap {
+            map: HashMap::new(hasher, minimum_capacity),
+        }
+    }
+
+    /// Inserts a key-value pair into the HashMultiMap.
+    ///
+    /// The value is appended to the values of the key even if the key-value pair exists, and it returns the number of
+    /// values of the key after the insertion.
+    ///
+    /// # Examples
+    /// ```
+    /// use scc::HashMultiMap;
+    /// use std::collections::hash_map::RandomState;
+    ///
+    /// let hashmultimap: HashMultiMap<u64, u32, RandomState> = HashMultiMap::new(RandomState::new(), None);
+    ///
+    /// let result = hashmultimap.insert(1, 0);
+    /// assert_eq!(result, 1);
+    ///
+    /// let result = hashmultimap.insert(1, 1);
+    /// assert_eq!(result, 2);
+    ///
+    /// let result = hashmultimap.insert(1, 0);
+    /// assert_eq!(result, 3);
+    /// ```
+    pub fn insert(&self, key: K, value: V) -> usize {
+        match self.map.insert(key, vec![value]) {
+            Ok(_) => 1,
+            Err((accessor, mut values)) => {
+                let (_, current_values) = accessor.get();
+                current_values.append(&mut values);
+                current_values.len()
+            }
+        }
+    }
+
+    /// Removes a key-value pair.
+    ///
+    /// Only one of the values equal to the given value is removed, and the key is removed along with its last value.
+    ///
+    /// # Examples
+    /// ```
+    /// use scc::HashMultiMap;
+    /// use std::collections::hash_map::RandomState;
+    ///
+    /// let hashmultimap: HashMultiMap<u64, u32, RandomState> = HashMultiMap::new(RandomState::new(), None);
+    ///
+    /// let result = hashmultimap.remove(1, 0);
+    /// assert!(!result);
+    ///
+    /// assert_eq!(hashmultimap.insert(1, 0), 1);
+    /// assert_eq!(hashmultimap.insert(1, 1), 2);
+    ///
+    /// let result = hashmultimap.remove(1, 0);
+    /// assert!(result);
+    /// assert_eq!(hashmultimap.count(1), 1);
+    /// ```
+    pub fn remove(&self, key: K, value: V) -> bool
+    where
+        V: Eq,
+    {
+        if let Some(accessor) = self.map.get(key) {
+            let (_, values) = accessor.get();
+            if let Some(position) = values.iter().position(|v| *v == value) {
+                values.swap_remove(position);
+                if values.is_empty() {
+                    accessor.erase();
+                }
+                return true;
+            }
+        }
+        false
+    }
+
+    /// Removes a key and all its values.
+    ///
+    /// It returns the number of removed values.
+    ///
+    /// # Examples
+    /// ```
+    /// use scc::HashMultiMap;
+    /// use std::collections::hash_map::RandomState;
+    ///
+    /// let hashmultimap: HashMultiMap<u64, u32, RandomState> = HashMultiMap::new(RandomState::new(), None);
+    ///
+    /// assert_eq!(hashmultimap.insert(1, 0), 1);
+    /// assert_eq!(hashmultimap.insert(1, 1), 2);
+    ///
+    /// let result = hashmultimap.remove_all(1);
+    /// assert_eq!(result, 2);
+    ///
+    /// let result = hashmultimap.remove_all(1);
+    /// assert_eq!(result, 0);
+    /// ```
+    pub fn remove_all(&self, key: K) -> usize {
+        if let Some(accessor) = self.map.get(key) {
+            let num_values = accessor.get().1.len();
+            accessor.erase();
+            return num_values;
+        }
+        0
+    }
+
+    /// Calls the given function for each value of the key.
+    ///
+    /// It acquires a shared lock on the cell, and returns the number of values.
+    ///
+    /// # Examples
+    /// ```
+    /// use scc::HashMultiMap;
+    /// use std::collections::hash_map::RandomState;
+    ///
+    /// let hashmultimap: HashMultiMap<u64, u32, RandomState> = HashMultiMap::new(RandomState::new(), None);
+    ///
+    /// assert_eq!(hashmultimap.insert(1, 1), 1);
+    /// assert_eq!(hashmultimap.insert(1, 2), 2);
+    ///
+    /// let mut sum = 0;
+    /// let result = hashmultimap.for_each_value(1, |value| sum += *value);
+    /// assert_eq!(result, 2);
+    /// assert_eq!(sum, 3);
+    /// ```
+    pub fn for_each_value<F: FnMut(&V)>(&self, key: K, f: F) -> usize {
+        self.map
+            .read(key, |_, values| {
+                values.iter().for_each(f);
+                values.len()
+            })
+            .unwrap_or(0)
+    }
+
+    /// Returns the number of values of the key.
+    ///
+    /// # Examples
+    /// ```
+    /// use scc::HashMultiMap;
+    /// use std::collections::hash_map::RandomState;
+    ///
+    /// let hashmultimap: HashMultiMap<u64, u32, RandomState> = HashMultiMap::new(RandomState::new(), None);
+    ///
+    /// let result = hashmultimap.count(1);
+    /// assert_eq!(result, 0);
+    ///
+    /// assert_eq!(hashmultimap.insert(1, 0), 1);
+    /// assert_eq!(hashmultimap.insert(1, 1), 2);
+    ///
+    /// let result = hashmultimap.count(1);
+    /// assert_eq!(result, 2);
+    /// ```
+    pub fn count(&self, key: K) -> usize {
+        self.map.read(key, |_, values| values.len()).unwrap_or(0)
+    }
+
+    /// Clears all the key-value pairs.
+    ///
+    /// It returns the number of removed keys.
+    ///
+    /// # Examples
+    /// ```
+    /// use scc::HashMultiMap;
+    /// use std::collections::hash_map::RandomState;
+    ///
+    /// let hashmultimap: HashMultiMap<u64, u32, RandomState> = HashMultiMap::new(RandomState::new(), None);
+    ///
+    /// assert_eq!(hashmultimap.insert(1, 0), 1);
+    /// assert_eq!(hashmultimap.insert(1, 1), 2);
+    ///
+    /// let result = hashmultimap.clear();
+    /// assert_eq!(result, 1);
+    /// ```
+    pub fn clear(&self) -> usize {
+        self.map.clear()
+    }
+
+    /// Returns an estimated number of keys in the HashMultiMap.
+    ///
+    /// It passes the capacity of the HashMultiMap to the given function.
+    ///
+    /// # Examples
+    /// ```
+    /// use scc::HashMultiMap;
+    /// use std::collections::hash_map::RandomState;
+    ///
+    /// let hashmultimap: HashMultiMap<u64, u32, RandomState> = HashMultiMap::new(RandomState::new(), None);
+    ///
+    /// assert_eq!(hashmultimap.insert(1, 0), 1);
+    /// assert_eq!(hashmultimap.insert(1, 1), 2);
+    ///
+    /// let result = hashmultimap.len(|capacity| capacity);
+    /// assert_eq!(result, 1);
+    /// ```
+    pub fn len<F: FnOnce(usize) -> usize>(&self, f: F) -> usize {
+        self.map.len(f)
+    }
+
+    /// Returns the capacity of the HashMultiMap.
+    ///
+    /// # Examples
+    /// ```
+    /// use scc::HashMultiMap;
+    /// use std::collections::hash_map::RandomState;
+    ///
+    /// let hashmultimap: HashMultiMap<u64, u32, RandomState> =
+    ///     HashMultiMap::new(RandomState::new(), Some(1000000));
+    ///
+    /// let result = hashmultimap.capacity();
+    /// assert_eq!(result, 1048576);
+    /// ```
+    pub fn capacity(&self) -> usize {
+        self.map.capacity()
+    }
+}
@@ -1,8 +1,61 @@
 //! Scalable concurrent containers.
 
+mod bag;
+mod change_listener;
+mod clock;
+mod hash_cache;
+mod hash_counter;
+mod hash_index;
+mod hash_multi_map;
+mod lock_table;
 mod map;
+#[cfg(unix)]
+mod persistent_map;
+mod queue;
+mod set;
+mod stack;
+mod subscription;
+mod transaction;
+mod tree_index;
+mod versioned_map;
 
+pub use bag::Bag;
+pub use change_listener::ChangeListener;
+pub use clock::Clock;
+pub use clock::ManualClock;
+pub use clock::SystemClock;
+pub use hash_cache::HashCache;
+pub use hash_counter::HashCounter;
+pub use hash_index::HashIndex;
+pub use hash_multi_map::HashMultiMap;
+pub use lock_table::Deadlock;
+pub use lock_table::LockMode;
+pub use lock_table::LockTable;
 pub use map::Accessor;
+pub use map::HashDiagnostics;
 pub use map::HashMap;
+pub use map::MultiAccessor;
+pub use map::RawEntry;
 pub use map::Scanner;
 pub use map::Statistics;
+#[cfg(unix)]
+pub use persistent_map::Full;
+#[cfg(unix)]
+pub use persistent_map::PersistentMap;
+#[cfg(unix)]
+pub use persistent_map::Pod;
+pub use queue::Queue;
+pub use set::HashSet;
+pub use set::SetScanner;
+pub use stack::Stack;
+pub use subscription::Event;
+pub use subscription::Subscription;
+pub use transaction::Conflict;
+pub use transaction::Transaction;
+pub use tree_index::Range;
+pub use tree_index::TreeIndex;
+pub use versioned_map::Snapshot;
+pub use versioned_map::VersionedMap;
+
+pub use crossbeam_epoch::pin;
+pub use crossbeam_epoch::Guard;
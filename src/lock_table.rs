@@ -0,0 +1,621 @@
+use super::map::cell::WaitQueueEntry;
+use super::map::HashMap;
+use std::collections::hash_map::RandomState;
+use std::collections::VecDeque;
+use std::error::Error;
+use std::fmt;
+use std::hash::{BuildHasher, Hash};
+use std::ptr;
+use std::sync::atomic::AtomicBool;
+use std::sync::atomic::Ordering::Relaxed;
+use std::sync::Arc;
+use std::time::Duration;
+
+/// LockMode is the mode of a lock on a record.
+///
+/// Intention modes are meant for locking a container of records, such as a table, before locking the records in it.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum LockMode {
+    /// Intention to lock records in shared mode.
+    IntentionShared,
+    /// Intention to lock records in exclusive mode.
+    IntentionExclusive,
+    /// Shared.
+    Shared,
+    /// Shared, and intention to lock records in exclusive mode.
+    SharedIntentionExclusive,
+    /// Exclusive.
+    Exclusive,
+}
+
+impl LockMode {
+    /// Checks if two transactions can hold the lock modes at the same time.
+    ///
+    /// # Examples
+    /// ```
+    /// use scc::LockMode;
+    ///
+    /// assert!(LockMode::Shared.compatible(LockMode::IntentionShared));
+    /// assert!(!LockMode::Shared.compatible(LockMode::IntentionExclusive));
+    /// ```
+    pub fn compatible(self, other: LockMode) -> bool {
+        use LockMode::*;
+        match (self, other) {
+            (Exclusive, _) | (_, Exclusive) => false,
+            (IntentionShared, _) | (_, IntentionShared) => true,
+            (IntentionExclusive, IntentionExclusive) | (Shared, Shared) => true,
+            _ => false,
+        }
+    }
+
+    /// Checks if the lock mode grants every permission that the other lock mode grants.
+    ///
+    /// # Examples
+    /// ```
+    /// use scc::LockMode;
+    ///
+    /// assert!(LockMode::SharedIntentionExclusive.covers(LockMode::Shared));
+    /// assert!(!LockMode::Shared.covers(LockMode::IntentionExclusive));
+    /// ```
+    pub fn covers(self, other: LockMode) -> bool {
+        use LockMode::*;
+        match (self, other) {
+            (Exclusive, _) => true,
+            (SharedIntentionExclusive, other) => other != Exclusive,
+            (Shared, Shared) | (Shared, IntentionShared) => true,
+            (IntentionExclusive, IntentionExclusive) | (IntentionExclusive, IntentionShared) => {
+                true
+            }
+            (IntentionShared, IntentionShared) => true,
+            _ => false,
+        }
+    }
+
+    /// Returns the weakest lock mode that covers both lock modes.
+    ///
+    /// # Examples
+    /// ```
+    /// use scc::LockMode;
+    ///
+    /// assert_eq!(
+    ///     LockMode::Shared.supremum(LockMode::IntentionExclusive),
+    ///     LockMode::SharedIntentionExclusive
+    /// );
+    /// assert_eq!(LockMode::Shared.supremum(LockMode::IntentionShared), LockMode::Shared);
+    /// ```
+    pub fn supremum(self, other: LockMode) -> LockMode {
+        if self.covers(other) {
+            self
+        } else if other.covers(self) {
+            other
+        } else {
+            LockMode::SharedIntentionExclusive
+        }
+    }
+}
+
+/// Deadlock is returned to a transaction chosen as the victim of a deadlock.
+///
+/// The lock request of the victim is canceled, and the transaction is supposed to release its locks by calling
+/// unlock_all.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub struct Deadlock;
+
+impl fmt::Display for Deadlock {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "the transaction was chosen as the victim of a deadlock")
+    }
+}
+
+impl Error for Deadlock {}
+
+/// A concurrent lock manager implementation.
+///
+/// scc::LockTable maps keys, such as record identifiers, to lock heads stored in an scc::HashMap, and each lock head
+/// consists of the granted locks and the queue of waiting lock requests. A lock head is only modified while the cell
+/// owning the key is exclusively locked, and a waiting thread blocks on a wait queue entry of its own that is signaled
+/// when the lock is granted.
+///
+/// The key features of scc::LockTable.
+/// * Multi-granularity lock modes: IS, IX, S, SIX and X.
+/// * FIFO grants: a new lock request is granted only if no other lock requests are waiting for the key.
+/// * Upgrades: a transaction requesting a stronger lock mode on a key that it has locked is placed at the head of the
+///   queue.
+/// * Releasing all the locks of a transaction in one call.
+/// * Optional deadlock detection: a waiting transaction periodically searches the wait-for graph for a cycle that
+///   it belongs to, and the youngest transaction in the cycle, the one with the largest identifier, is woken up with
+///   a Deadlock error.
+///
+/// The wait-for graph consists of the lock requests made through the LockTable; deadlocks among threads holding
+/// Accessors of an scc::HashMap are detected by a HashMap created by HashMap::with_deadlock_detection.
+pub struct LockTable<K: Clone + Eq + Hash + Sync, H: BuildHasher> {
+    lock_heads: HashMap<K, LockHead, H>,
+    transactions: HashMap<u64, Vec<K>, RandomState>,
+    /// The key that each waiting transaction is waiting for, which forms the wait-for graph with the lock heads.
+    waiting_for: HashMap<u64, K, RandomState>,
+    detection_interval: Option<Duration>,
+}
+
+impl<K: Clone + Eq + Hash + Sync, H: BuildHasher> LockTable<K, H> {
+    /// Creates an empty LockTable instance with the given hasher.
+    ///
+    /// # Examples
+    /// ```
+    /// use scc::{LockMode, LockTable};
+    /// use std::collections::hash_map::RandomState;
+    ///
+    /// let locktable: LockTable<u64, RandomState> = LockTable::new(RandomState::new());
+    ///
+    /// let result = locktable.mode(1, 0);
+    /// assert!(result.is_none());
+    /// ```
+    pub fn new(hasher: H) -> LockTable<K, H> {
+        Self::create(hasher, None)
+    }
+
+    /// Creates an empty LockTable instance that detects deadlocks.
+    ///
+    /// A waiting transaction searches the wait-for graph for a deadlock whenever the given interval elapses. Since the
+    /// wait-for graph is not examined atomically, a transaction may rarely be chosen as a victim even if the lock
+    /// requests forming the cycle are being granted at the same time.
+    ///
+    /// # Examples
+    /// ```
+    /// use scc::{Deadlock, LockMode, LockTable};
+    /// use std::collections::hash_map::RandomState;
+    /// use std::sync::{Arc, Barrier};
+    /// use std::thread;
+    /// use std::time::Duration;
+    ///
+    /// let locktable: Arc<LockTable<u64, RandomState>> = Arc::new(LockTable::with_deadlock_detection(
+    ///     RandomState::new(),
+    ///     Duration::from_millis(1),
+    /// ));
+    /// let barrier = Arc::new(Barrier::new(2));
+    ///
+    /// let locktable_copied = locktable.clone();
+    /// let barrier_copied = barrier.clone();
+    /// let thread_handle = thread::spawn(move || {
+    ///     assert!(locktable_copied.lock(1, 0, LockMode::Exclusive).is_ok());
+    ///     barrier_copied.wait();
+    ///     let result = locktable_copied.lock(1, 1, LockMode::Exclusive);
+    ///     locktable_copied.unlock_all(1);
+    ///     result
+    /// });
+    ///
+    /// assert!(locktable.lock(2, 1, LockMode::Exclusive).is_ok());
+    /// barrier.wait();
+    /// let result = locktable.lock(2, 0, LockMode::Exclusive);
+    /// locktable.unlock_all(2);
+    ///
+    /// // the transaction with the larger identifier is chosen as the victim
+    /// assert_eq!(result, Err(Deadlock));
+    /// assert!(thread_handle.join().unwrap().is_ok());
+    /// ```
+    pub fn with_deadlock_detection(hasher: H, interval: Duration) -> LockTable<K, H> {
+        Self::create(hasher, Some(interval))
+    }
+
+    /// Creates an empty LockTable instance.
+    fn create(hasher: H, detection_interval: Option<Duration>) -> LockTable<K, H> {
+        LockTable {
+            lock_heads: HashMap::new(hasher, None),
+            transactions: HashMap::new(RandomState::new(), None),
+            waiting_for: HashMap::new(RandomState::new(), None),
+            detection_interval,
+        }
+    }
+
+    /// Locks the key in the given mode on behalf of the transaction.
+    ///
+    /// It blocks the thread until the lock is granted. If the transaction has already locked the key, the lock is
+    /// upgraded to the weakest lock mode that covers both lock modes. It returns an error if the LockTable detects
+    /// deadlocks, and the transaction is chosen as the victim of a deadlock.
+    ///
+    /// # Examples
+    /// ```
+    /// use scc::{LockMode, LockTable};
+    /// use std::collections::hash_map::RandomState;
+    ///
+    /// let locktable: LockTable<u64, RandomState> = LockTable::new(RandomState::new());
+    ///
+    /// assert!(locktable.lock(1, 0, LockMode::Shared).is_ok());
+    /// assert!(locktable.lock(2, 0, LockMode::IntentionShared).is_ok());
+    /// assert_eq!(locktable.mode(1, 0), Some(LockMode::Shared));
+    ///
+    /// assert!(locktable.lock(1, 0, LockMode::IntentionExclusive).is_ok());
+    /// assert_eq!(locktable.mode(1, 0), Some(LockMode::SharedIntentionExclusive));
+    /// ```
+    pub fn lock(&self, transaction_id: u64, key: K, mode: LockMode) -> Result<(), Deadlock> {
+        self.acquire(transaction_id, key, mode, true).map(|_| ())
+    }
+
+    /// Tries to lock the key in the given mode on behalf of the transaction without blocking.
+    ///
+    /// It returns false if the lock cannot be granted immediately.
+    ///
+    /// # Examples
+    /// ```
+    /// use scc::{LockMode, LockTable};
+    /// use std::collections::hash_map::RandomState;
+    ///
+    /// let locktable: LockTable<u64, RandomState> = LockTable::new(RandomState::new());
+    ///
+    /// let result = locktable.try_lock(1, 0, LockMode::Exclusive);
+    /// assert!(result);
+    ///
+    /// let result = locktable.try_lock(2, 0, LockMode::IntentionShared);
+    /// assert!(!result);
+    /// ```
+    pub fn try_lock(&self, transaction_id: u64, key: K, mode: LockMode) -> bool {
+        matches!(self.acquire(transaction_id, key, mode, false), Ok(true))
+    }
+
+    /// Unlocks the key locked by the transaction.
+    ///
+    /// Waiting lock requests are granted in FIFO order as long as they are compatible with the granted locks.
+    ///
+    /// # Examples
+    /// ```
+    /// use scc::{LockMode, LockTable};
+    /// use std::collections::hash_map::RandomState;
+    ///
+    /// let locktable: LockTable<u64, RandomState> = LockTable::new(RandomState::new());
+    ///
+    /// assert!(locktable.lock(1, 0, LockMode::Exclusive).is_ok());
+    ///
+    /// let result = locktable.unlock(1, 0);
+    /// assert!(result);
+    ///
+    /// let result = locktable.unlock(1, 0);
+    /// assert!(!result);
+    /// ```
+    pub fn unlock(&self, transaction_id: u64, key: K) -> bool {
+        if let Some(accessor) = self.transactions.get(transaction_id) {
+            let (_, keys) = accessor.get();
+            if let Some(position) = keys.iter().position(|k| *k == key) {
+                keys.swap_remove(position);
+                if keys.is_empty() {
+                    accessor.erase();
+                }
+            }
+        }
+        self.release(transaction_id, key)
+    }
+
+    /// Unlocks all the keys locked by the transaction.
+    ///
+    /// It returns the number of unlocked keys.
+    ///
+    /// # Examples
+    /// ```
+    /// use scc::{LockMode, LockTable};
+    /// use std::collections::hash_map::RandomState;
+    ///
+    /// let locktable: LockTable<u64, RandomState> = LockTable::new(RandomState::new());
+    ///
+    /// assert!(locktable.lock(1, 0, LockMode::Exclusive).is_ok());
+    /// assert!(locktable.lock(1, 1, LockMode::Shared).is_ok());
+    ///
+    /// let result = locktable.unlock_all(1);
+    /// assert_eq!(result, 2);
+    ///
+    /// let result = locktable.try_lock(2, 0, LockMode::Exclusive);
+    /// assert!(result);
+    /// ```
+    pub fn unlock_all(&self, transaction_id: u64) -> usize {
+        let keys = self
+            .transactions
+            .get(transaction_id)
+            .map_or_else(Vec::new, |accessor| {
+                let keys = std::mem::take(accessor.get().1);
+                accessor.erase();
+                keys
+            });
+        keys.into_iter()
+            .filter(|key| self.release(transaction_id, key.clone()))
+            .count()
+    }
+
+    /// Returns the lock mode of the key granted to the transaction.
+    ///
+    /// # Examples
+    /// ```
+    /// use scc::{LockMode, LockTable};
+    /// use std::collections::hash_map::RandomState;
+    ///
+    /// let locktable: LockTable<u64, RandomState> = LockTable::new(RandomState::new());
+    ///
+    /// assert!(locktable.lock(1, 0, LockMode::IntentionShared).is_ok());
+    ///
+    /// let result = locktable.mode(1, 0);
+    /// assert_eq!(result, Some(LockMode::IntentionShared));
+    ///
+    /// let result = locktable.mode(2, 0);
+    /// assert!(result.is_none());
+    /// ```
+    pub fn mode(&self, transaction_id: u64, key: K) -> Option<LockMode> {
+        self.lock_heads
+            .read(key, |_, lock_head| lock_head.granted_mode(transaction_id))
+            .flatten()
+    }
+
+    /// Grants the lock, or waits for the lock to be granted if allowed.
+    fn acquire(
+        &self,
+        transaction_id: u64,
+        key: K,
+        mode: LockMode,
+        wait: bool,
+    ) -> Result<bool, Deadlock> {
+        let request = {
+            let accessor = match self.lock_heads.insert(key.clone(), LockHead::default()) {
+                Ok(accessor) => accessor,
+                Err((accessor, _)) => accessor,
+            };
+            let (_, lock_head) = accessor.get();
+            if lock_head.try_grant(transaction_id, mode) {
+                None
+            } else if !wait {
+                if lock_head.is_empty() {
+                    accessor.erase();
+                }
+                return Ok(false);
+            } else {
+                Some(lock_head.enqueue(transaction_id, mode))
+            }
+        };
+        if let Some(request) = request {
+            // the cell is unlocked while waiting
+            self.wait(transaction_id, &key, &request)?;
+        }
+        match self.transactions.insert(transaction_id, vec![key]) {
+            Ok(_) => (),
+            Err((accessor, mut keys)) => {
+                let key = keys.pop().unwrap();
+                let (_, current_keys) = accessor.get();
+                if !current_keys.contains(&key) {
+                    current_keys.push(key);
+                }
+            }
+        }
+        Ok(true)
+    }
+
+    /// Waits for the lock request to be granted while periodically detecting deadlocks.
+    fn wait(&self, transaction_id: u64, key: &K, request: &LockRequest) -> Result<(), Deadlock> {
+        let interval = match self.detection_interval {
+            Some(interval) => interval,
+            None => {
+                request.signal.wait();
+                return Ok(());
+            }
+        };
+        self.waiting_for.upsert(transaction_id, key.clone());
+        while !request.signal.wait_timeout(interval) {
+            if let Some(victim) = self.detect(transaction_id) {
+                self.abort(victim);
+            }
+        }
+        self.waiting_for.remove(transaction_id);
+        if request.aborted.load(Relaxed) {
+            return Err(Deadlock);
+        }
+        Ok(())
+    }
+
+    /// Searches the wait-for graph for a cycle containing the transaction, and returns the youngest transaction in the
+    /// cycle.
+    fn detect(&self, transaction_id: u64) -> Option<u64> {
+        // (transaction, index of the transaction in the path that it was found from)
+        let mut path: Vec<(u64, usize)> = vec![(transaction_id, 0)];
+        let mut visited = vec![transaction_id];
+        let mut stack: Vec<(u64, usize)> = self
+            .blockers(transaction_id)
+            .into_iter()
+            .map(|blocker| (blocker, 0))
+            .collect();
+        while let Some((current, parent)) = stack.pop() {
+            path.truncate(parent + 1);
+            if current == transaction_id {
+                return path.iter().map(|(id, _)| *id).max();
+            }
+            if visited.contains(&current) {
+                continue;
+            }
+            visited.push(current);
+            path.push((current, parent));
+            let index = path.len() - 1;
+            stack.extend(
+                self.blockers(current)
+                    .into_iter()
+                    .map(|blocker| (blocker, index)),
+            );
+        }
+        None
+    }
+
+    /// Returns the transactions that the transaction is waiting for.
+    fn blockers(&self, transaction_id: u64) -> Vec<u64> {
+        self.waiting_for
+            .read(transaction_id, |_, key| key.clone())
+            .and_then(|key| {
+                self.lock_heads
+                    .read(key, |_, lock_head| lock_head.blockers(transaction_id))
+            })
+            .unwrap_or_default()
+    }
+
+    /// Cancels the lock request of the transaction, and wakes it up.
+    fn abort(&self, transaction_id: u64) {
+        if let Some(key) = self.waiting_for.read(transaction_id, |_, key| key.clone()) {
+            if let Some(accessor) = self.lock_heads.get(key) {
+                let (_, lock_head) = accessor.get();
+                lock_head.abort(transaction_id);
+                if lock_head.is_empty() {
+                    accessor.erase();
+                }
+            }
+        }
+    }
+
+    /// Releases the lock, and grants waiting lock requests.
+    fn release(&self, transaction_id: u64, key: K) -> bool {
+        if let Some(accessor) = self.lock_heads.get(key) {
+            let (_, lock_head) = accessor.get();
+            let released = lock_head.release(transaction_id);
+            if lock_head.is_empty() {
+                accessor.erase();
+            }
+            return released;
+        }
+        false
+    }
+}
+
+/// LockHead consists of the granted locks and the waiting lock requests on a key.
+#[derive(Default)]
+struct LockHead {
+    granted: Vec<(u64, LockMode)>,
+    waiting: VecDeque<Arc<LockRequest>>,
+}
+
+impl LockHead {
+    fn is_empty(&self) -> bool {
+        self.granted.is_empty() && self.waiting.is_empty()
+    }
+
+    fn granted_mode(&self, transaction_id: u64) -> Option<LockMode> {
+        self.granted
+            .iter()
+            .find(|(id, _)| *id == transaction_id)
+            .map(|(_, mode)| *mode)
+    }
+
+    /// Checks if the lock mode is compatible with the locks granted to the other transactions.
+    fn compatible(&self, transaction_id: u64, mode: LockMode) -> bool {
+        self.granted
+            .iter()
+            .all(|(id, granted_mode)| *id == transaction_id || granted_mode.compatible(mode))
+    }
+
+    /// Grants the lock if no waiting lock request precedes it.
+    ///
+    /// Upgrades are not blocked by waiting lock requests.
+    fn try_grant(&mut self, transaction_id: u64, mode: LockMode) -> bool {
+        let granted_mode = self.granted_mode(transaction_id);
+        let mode = granted_mode.map_or(mode, |granted_mode| granted_mode.supremum(mode));
+        if granted_mode == Some(mode) {
+            return true;
+        }
+        if (granted_mode.is_some() || self.waiting.is_empty())
+            && self.compatible(transaction_id, mode)
+        {
+            self.grant(transaction_id, mode);
+            return true;
+        }
+        false
+    }
+
+    fn grant(&mut self, transaction_id: u64, mode: LockMode) {
+        if let Some(granted) = self
+            .granted
+            .iter_mut()
+            .find(|(id, _)| *id == transaction_id)
+        {
+            granted.1 = mode;
+        } else {
+            self.granted.push((transaction_id, mode));
+        }
+    }
+
+    /// Appends a lock request to the queue; upgrades are placed at the head of the queue.
+    fn enqueue(&mut self, transaction_id: u64, mode: LockMode) -> Arc<LockRequest> {
+        let granted_mode = self.granted_mode(transaction_id);
+        let request = Arc::new(LockRequest {
+            transaction_id,
+            mode: granted_mode.map_or(mode, |granted_mode| granted_mode.supremum(mode)),
+            signal: WaitQueueEntry::new(ptr::null_mut()),
+            aborted: AtomicBool::new(false),
+        });
+        if granted_mode.is_some() {
+            self.waiting.push_front(request.clone());
+        } else {
+            self.waiting.push_back(request.clone());
+        }
+        request
+    }
+
+    fn release(&mut self, transaction_id: u64) -> bool {
+        if let Some(position) = self
+            .granted
+            .iter()
+            .position(|(id, _)| *id == transaction_id)
+        {
+            self.granted.swap_remove(position);
+            self.grant_waiting();
+            return true;
+        }
+        false
+    }
+
+    /// Returns the transactions that block the waiting lock request of the transaction.
+    ///
+    /// Every lock request ahead in the queue blocks the lock request as lock requests are granted in FIFO order.
+    fn blockers(&self, transaction_id: u64) -> Vec<u64> {
+        let position = match self
+            .waiting
+            .iter()
+            .position(|request| request.transaction_id == transaction_id)
+        {
+            Some(position) => position,
+            None => return Vec::new(),
+        };
+        let mode = self.waiting[position].mode;
+        self.granted
+            .iter()
+            .filter(|(id, granted_mode)| *id != transaction_id && !granted_mode.compatible(mode))
+            .map(|(id, _)| *id)
+            .chain(
+                self.waiting
+                    .iter()
+                    .take(position)
+                    .map(|request| request.transaction_id),
+            )
+            .collect()
+    }
+
+    /// Cancels the waiting lock request of the transaction.
+    fn abort(&mut self, transaction_id: u64) {
+        if let Some(position) = self
+            .waiting
+            .iter()
+            .position(|request| request.transaction_id == transaction_id)
+        {
+            let request = self.waiting.remove(position).unwrap();
+            request.aborted.store(true, Relaxed);
+            request.signal.signal();
+            self.grant_waiting();
+        }
+    }
+
+    /// Grants waiting lock requests in FIFO order until an incompatible one is found.
+    fn grant_waiting(&mut self) {
+        while let Some(request) = self.waiting.front() {
+            if !self.compatible(request.transaction_id, request.mode) {
+                break;
+            }
+            let request = self.waiting.pop_front().unwrap();
+            self.grant(request.transaction_id, request.mode);
+            request.signal.signal();
+        }
+    }
+}
+
+struct LockRequest {
+    transaction_id: u64,
+    mode: LockMode,
+    signal: WaitQueueEntry,
+    /// Set when the lock request is canceled to resolve a deadlock.
+    aborted: AtomicBool,
+}
@@ -2,17 +2,36 @@ extern crate crossbeam_epoch;
 
 pub mod array;
 pub mod cell;
+mod checkpoint;
 pub mod link;
+#[cfg(feature = "serde")]
+mod serialization;
+mod wait_for_graph;
 
-use array::{Array, MAX_ENLARGE_FACTOR};
-use cell::{CellLocker, CellReader};
-use crossbeam_epoch::{Atomic, Owned, Shared};
+use super::change_listener::ChangeListener;
+use super::clock::{Clock, SystemClock};
+use super::lock_table::Deadlock;
+use super::subscription::{Subscribers, Subscription};
+use super::transaction::Transaction;
+use array::Array;
+use cell::{CellLocker, CellReader, Expiry, ExpiryListener};
+use checkpoint::CheckpointState;
+use crossbeam_epoch::{Atomic, Guard, Owned, Shared};
 use link::EntryArrayLink;
 use std::convert::TryInto;
 use std::fmt;
 use std::hash::{BuildHasher, Hash, Hasher};
 use std::sync::atomic::AtomicBool;
-use std::sync::atomic::Ordering::{Acquire, Release};
+use std::sync::atomic::Ordering::{Acquire, Relaxed, Release};
+use std::time::Duration;
+use wait_for_graph::WaitForGraph;
+
+/// CellVersion identifies a cell by the address of the array and the index of the cell, and records its version.
+pub(crate) type CellVersion<K, V> = (*const Array<K, V>, usize, u32);
+
+/// AcquireResult consists of the Accessor owning an acquired cell, the array containing the cell, and the index of the
+/// cell.
+type AcquireResult<'a, K, V, H> = (Accessor<'a, K, V, H>, *const Array<K, V>, usize);
 
 /// A scalable concurrent hash map implementation.
 ///
@@ -45,6 +64,13 @@ pub struct HashMap<K: Eq + Hash + Sync, V: Sync, H: BuildHasher> {
     minimum_capacity: usize,
     resize_mutex: AtomicBool,
     hasher: H,
+    clock: Option<Box<dyn Clock>>,
+    listener: Option<Box<dyn ChangeListener<K, V>>>,
+    subscribers: Subscribers<K, V>,
+    /// The state of the checkpoint in progress, refer to HashMap::checkpoint.
+    checkpoint: Atomic<CheckpointState<K, V>>,
+    /// The wait-for graph of the threads locking cells, refer to HashMap::with_deadlock_detection.
+    wait_for_graph: Option<WaitForGraph>,
 }
 
 impl<K: Eq + Hash + Sync, V: Sync, H: BuildHasher> HashMap<K, V, H> {
@@ -65,19 +91,288 @@ impl<K: Eq + Hash + Sync, V: Sync, H: BuildHasher> HashMap<K, V, H> {
     /// assert_eq!(result, 256);
     /// ```
     pub fn new(hasher: H, minimum_capacity: Option<usize>) -> HashMap<K, V, H> {
+        Self::create(hasher, minimum_capacity, false, None, None)
+    }
+
+    /// Creates an empty HashMap instance that caches the hash value of each key.
+    ///
+    /// Caching hash values spares resizing the cost of hashing keys when relocating key-value pairs,
+    /// at the expense of eight bytes of memory for each entry.
+    ///
+    /// # Examples
+    /// ```
+    /// use scc::HashMap;
+    /// use std::collections::hash_map::RandomState;
+    ///
+    /// let hashmap: HashMap<String, u32, RandomState> =
+    ///     HashMap::with_hash_cache(RandomState::new(), None);
+    ///
+    /// for i in 0..1024 {
+    ///     assert!(hashmap.insert(i.to_string(), i).is_ok());
+    /// }
+    ///
+    /// let result = hashmap.read(String::from("1"), |key, value| *value);
+    /// assert_eq!(result.unwrap(), 1);
+    /// ```
+    pub fn with_hash_cache(hasher: H, minimum_capacity: Option<usize>) -> HashMap<K, V, H> {
+        Self::create(hasher, minimum_capacity, true, None, None)
+    }
+
+    /// Creates an empty HashMap instance that supports time-to-live using the system clock.
+    ///
+    /// Tracking deadlines costs eight bytes of memory for each entry.
+    ///
+    /// # Examples
+    /// ```
+    /// use scc::HashMap;
+    /// use std::collections::hash_map::RandomState;
+    /// use std::time::Duration;
+    ///
+    /// let hashmap: HashMap<u64, u32, RandomState> = HashMap::with_ttl(RandomState::new(), None);
+    ///
+    /// let result = hashmap.insert_with_ttl(1, 0, Duration::from_secs(60));
+    /// assert!(result.is_ok());
+    /// ```
+    pub fn with_ttl(hasher: H, minimum_capacity: Option<usize>) -> HashMap<K, V, H> {
+        Self::with_clock(hasher, minimum_capacity, SystemClock::default())
+    }
+
+    /// Creates an empty HashMap instance that supports time-to-live using the given clock.
+    ///
+    /// # Examples
+    /// ```
+    /// use scc::{HashMap, ManualClock};
+    /// use std::collections::hash_map::RandomState;
+    /// use std::sync::Arc;
+    /// use std::time::Duration;
+    ///
+    /// let clock = Arc::new(ManualClock::default());
+    /// let hashmap: HashMap<u64, u32, RandomState> =
+    ///     HashMap::with_clock(RandomState::new(), None, clock.clone());
+    ///
+    /// assert!(hashmap.insert_with_ttl(1, 0, Duration::from_secs(1)).is_ok());
+    ///
+    /// clock.advance(Duration::from_secs(1));
+    /// assert!(hashmap.read(1, |_, _| ()).is_none());
+    /// ```
+    pub fn with_clock<C: Clock + 'static>(
+        hasher: H,
+        minimum_capacity: Option<usize>,
+        clock: C,
+    ) -> HashMap<K, V, H> {
+        Self::create(hasher, minimum_capacity, false, Some(Box::new(clock)), None)
+    }
+
+    /// Creates an empty HashMap instance that notifies the given ChangeListener of modifications.
+    ///
+    /// The listener is notified by insert, insert_with_ttl, insert_with_hash, upsert, remove, remove_with_hash,
+    /// Accessor::erase, retain, clear, MultiAccessor::insert, MultiAccessor::remove and Transaction::commit while the
+    /// cell owning the key is locked, and expired key-value pairs are notified as removals when they are erased.
+    /// Since the HashMap cannot tell whether or how a value was changed through the mutable references returned by
+    /// Accessor::get, MultiAccessor::get_mut or Scanner, each of those values is notified as an update when the
+    /// Accessor or MultiAccessor is dropped or the Scanner moves on, and the notification carries the value at the
+    /// time as both the old and the new value.
+    ///
+    /// # Examples
+    /// ```
+    /// use scc::{ChangeListener, HashMap};
+    /// use std::collections::hash_map::RandomState;
+    /// use std::sync::{Arc, Mutex};
+    ///
+    /// #[derive(Default)]
+    /// struct Log(Mutex<Vec<(u64, Option<u32>)>>);
+    ///
+    /// impl ChangeListener<u64, u32> for Log {
+    ///     fn on_change(&self, key: &u64, _: Option<&u32>, new_value: Option<&u32>) {
+    ///         self.0.lock().unwrap().push((*key, new_value.copied()));
+    ///     }
+    /// }
+    ///
+    /// let log = Arc::new(Log::default());
+    /// let hashmap: HashMap<u64, u32, RandomState> =
+    ///     HashMap::with_listener(RandomState::new(), None, log.clone());
+    ///
+    /// assert!(hashmap.insert(1, 0).is_ok());
+    /// hashmap.upsert(1, 1);
+    /// assert!(hashmap.remove(1));
+    /// assert_eq!(*log.0.lock().unwrap(), vec![(1, Some(0)), (1, Some(1)), (1, None)]);
+    /// ```
+    pub fn with_listener<L: ChangeListener<K, V> + 'static>(
+        hasher: H,
+        minimum_capacity: Option<usize>,
+        listener: L,
+    ) -> HashMap<K, V, H> {
+        Self::create(
+            hasher,
+            minimum_capacity,
+            false,
+            None,
+            Some(Box::new(listener)),
+        )
+    }
+
+    /// Creates an empty HashMap instance that supports time-to-live using the given clock, and notifies the given
+    /// ChangeListener of modifications.
+    ///
+    /// The listener receives the deadlines of the inserted key-value pairs through
+    /// ChangeListener::on_change_with_deadline, refer to with_listener for the notified modifications.
+    ///
+    /// # Examples
+    /// ```
+    /// use scc::{ChangeListener, HashMap, ManualClock};
+    /// use std::collections::hash_map::RandomState;
+    /// use std::sync::{Arc, Mutex};
+    /// use std::time::Duration;
+    ///
+    /// #[derive(Default)]
+    /// struct Log(Mutex<Vec<(u64, Option<u32>, Option<Duration>)>>);
+    ///
+    /// impl ChangeListener<u64, u32> for Log {
+    ///     fn on_change(&self, key: &u64, old_value: Option<&u32>, new_value: Option<&u32>) {
+    ///         self.on_change_with_deadline(key, old_value, new_value, None);
+    ///     }
+    ///
+    ///     fn on_change_with_deadline(
+    ///         &self,
+    ///         key: &u64,
+    ///         _: Option<&u32>,
+    ///         new_value: Option<&u32>,
+    ///         deadline: Option<Duration>,
+    ///     ) {
+    ///         self.0.lock().unwrap().push((*key, new_value.copied(), deadline));
+    ///     }
+    /// }
+    ///
+    /// let clock = Arc::new(ManualClock::default());
+    /// let log = Arc::new(Log::default());
+    /// let hashmap: HashMap<u64, u32, RandomState> =
+    ///     HashMap::with_clock_and_listener(RandomState::new(), None, clock.clone(), log.clone());
+    ///
+    /// assert!(hashmap.insert_with_ttl(1, 0, Duration::from_secs(1)).is_ok());
+    /// clock.advance(Duration::from_secs(1));
+    /// assert_eq!(hashmap.purge_expired(), 1);
+    /// assert_eq!(
+    ///     *log.0.lock().unwrap(),
+    ///     vec![(1, Some(0), Some(Duration::from_secs(1))), (1, None, None)]
+    /// );
+    /// ```
+    pub fn with_clock_and_listener<C: Clock + 'static, L: ChangeListener<K, V> + 'static>(
+        hasher: H,
+        minimum_capacity: Option<usize>,
+        clock: C,
+        listener: L,
+    ) -> HashMap<K, V, H> {
+        Self::create(
+            hasher,
+            minimum_capacity,
+            false,
+            Some(Box::new(clock)),
+            Some(Box::new(listener)),
+        )
+    }
+
+    /// Creates an empty HashMap instance that detects deadlocks among the threads locking its cells.
+    ///
+    /// A thread waiting for a cell is recorded in a wait-for graph along with the thread exclusively locking the
+    /// cell, and it searches the wait-for graph for a cycle that it belongs to whenever the given interval elapses;
+    /// the youngest thread in the cycle that is waiting in HashMap::lock, the one that first accessed a HashMap
+    /// latest, is woken up with a Deadlock error. Threads waiting in the other methods are never chosen as the victim,
+    /// therefore a deadlock is only resolved if one of the threads in the cycle is waiting in HashMap::lock. Every
+    /// exclusive lock on a cell is recorded in the wait-for graph, which is protected by a mutex.
+    ///
+    /// # Examples
+    /// ```
+    /// use scc::{Deadlock, HashMap};
+    /// use std::collections::hash_map::RandomState;
+    /// use std::sync::{Arc, Barrier};
+    /// use std::thread;
+    /// use std::time::Duration;
+    ///
+    /// let hashmap: Arc<HashMap<u64, u32, RandomState>> = Arc::new(
+    ///     HashMap::with_deadlock_detection(RandomState::new(), None, Duration::from_millis(1)),
+    /// );
+    /// assert!(hashmap.insert(1, 0).is_ok());
+    /// assert!(hashmap.insert(2, 0).is_ok());
+    /// let barrier = Arc::new(Barrier::new(2));
+    ///
+    /// let hashmap_copied = hashmap.clone();
+    /// let barrier_copied = barrier.clone();
+    /// let thread_handle = thread::spawn(move || {
+    ///     let accessor = hashmap_copied.lock(1).unwrap().unwrap();
+    ///     barrier_copied.wait();
+    ///     let result = hashmap_copied.lock(2).map(|accessor| accessor.is_some());
+    ///     drop(accessor);
+    ///     result
+    /// });
+    ///
+    /// let accessor = hashmap.lock(2).unwrap().unwrap();
+    /// barrier.wait();
+    /// let result = hashmap.lock(1).map(|accessor| accessor.is_some());
+    /// drop(accessor);
+    ///
+    /// // exactly one of the two threads is chosen as the victim
+    /// let results = [result, thread_handle.join().unwrap()];
+    /// assert_eq!(results.iter().filter(|result| **result == Err(Deadlock)).count(), 1);
+    /// assert!(results.contains(&Ok(true)));
+    /// ```
+    pub fn with_deadlock_detection(
+        hasher: H,
+        minimum_capacity: Option<usize>,
+        interval: Duration,
+    ) -> HashMap<K, V, H> {
+        let mut hashmap = Self::create(hasher, minimum_capacity, false, None, None);
+        hashmap.wait_for_graph = Some(WaitForGraph::new(interval));
+        hashmap
+    }
+
+    /// Creates an empty HashMap instance.
+    fn create(
+        hasher: H,
+        minimum_capacity: Option<usize>,
+        cache_hash: bool,
+        clock: Option<Box<dyn Clock>>,
+        listener: Option<Box<dyn ChangeListener<K, V>>>,
+    ) -> HashMap<K, V, H> {
         let initial_capacity = if let Some(capacity) = minimum_capacity {
             capacity.max(256)
         } else {
             256
         };
         HashMap {
-            array: Atomic::new(Array::<K, V>::new(initial_capacity, Atomic::null())),
+            array: Atomic::new(Array::<K, V>::new(
+                initial_capacity,
+                cache_hash,
+                clock.is_some(),
+                Atomic::null(),
+            )),
             minimum_capacity: initial_capacity,
             resize_mutex: AtomicBool::new(false),
             hasher: hasher,
+            clock,
+            listener,
+            subscribers: Subscribers::new(),
+            checkpoint: Atomic::null(),
+            wait_for_graph: None,
         }
     }
 
+    /// Creates an empty HashMap instance of which the array is allocated in advance for the given capacity.
+    ///
+    /// The minimum capacity is not affected by the given capacity.
+    fn presized(hasher: H, capacity: usize) -> HashMap<K, V, H> {
+        let hashmap = Self::new(hasher, None);
+        if capacity > hashmap.capacity() {
+            let guard = unsafe { crossbeam_epoch::unprotected() };
+            let old_array = hashmap.array.swap(
+                Owned::new(Array::<K, V>::new(capacity, false, false, Atomic::null())),
+                Relaxed,
+                guard,
+            );
+            drop(unsafe { old_array.into_owned() });
+        }
+        hashmap
+    }
+
     /// Inserts a key-value pair into the HashMap.
     ///
     /// # Examples
@@ -104,12 +399,109 @@ impl<K: Eq + Hash + Sync, V: Sync, H: BuildHasher> HashMap<K, V, H> {
         value: V,
     ) -> Result<Accessor<'a, K, V, H>, (Accessor<'a, K, V, H>, V)> {
         let (hash, partial_hash) = self.hash(&key);
+        self.insert_and_notify(key, hash, partial_hash, value, None)
+    }
+
+    /// Inserts a key-value pair into the HashMap that expires after the given time-to-live.
+    ///
+    /// An expired key-value pair is regarded as vacant, and it is erased when its cell is modified or scanned.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the HashMap was not created by with_ttl or with_clock.
+    ///
+    /// # Examples
+    /// ```
+    /// use scc::{HashMap, ManualClock};
+    /// use std::collections::hash_map::RandomState;
+    /// use std::sync::Arc;
+    /// use std::time::Duration;
+    ///
+    /// let clock = Arc::new(ManualClock::default());
+    /// let hashmap: HashMap<u64, u32, RandomState> =
+    ///     HashMap::with_clock(RandomState::new(), None, clock.clone());
+    ///
+    /// let result = hashmap.insert_with_ttl(1, 0, Duration::from_secs(10));
+    /// if let Ok(result) = result {
+    ///     assert_eq!(result.get(), (&1, &mut 0));
+    /// }
+    ///
+    /// clock.advance(Duration::from_secs(5));
+    /// assert_eq!(hashmap.read(1, |_, value| *value), Some(0));
+    ///
+    /// clock.advance(Duration::from_secs(5));
+    /// assert!(hashmap.get(1).is_none());
+    /// assert!(hashmap.insert(1, 1).is_ok());
+    /// ```
+    pub fn insert_with_ttl<'a>(
+        &'a self,
+        key: K,
+        value: V,
+        ttl: Duration,
+    ) -> Result<Accessor<'a, K, V, H>, (Accessor<'a, K, V, H>, V)> {
+        assert!(
+            self.clock.is_some(),
+            "insert_with_ttl requires a HashMap created by with_ttl or with_clock"
+        );
+        let (hash, partial_hash) = self.hash(&key);
+        self.insert_and_notify(key, hash, partial_hash, value, Some(ttl))
+    }
+
+    /// Inserts a key-value pair into the HashMap using a pre-computed hash value.
+    ///
+    /// The hash value must be the one that the BuildHasher of the HashMap produces for the key,
+    /// otherwise the key-value pair may become unreachable.
+    ///
+    /// # Examples
+    /// ```
+    /// use scc::HashMap;
+    /// use std::collections::hash_map::RandomState;
+    /// use std::hash::{BuildHasher, Hash, Hasher};
+    ///
+    /// let build_hasher = RandomState::new();
+    /// let hashmap: HashMap<u64, u32, RandomState> = HashMap::new(build_hasher.clone(), None);
+    ///
+    /// let mut hasher = build_hasher.build_hasher();
+    /// 1u64.hash(&mut hasher);
+    /// let hash = hasher.finish();
+    ///
+    /// let result = hashmap.insert_with_hash(1, hash, 0);
+    /// if let Ok(result) = result {
+    ///     assert_eq!(result.get(), (&1, &mut 0));
+    /// }
+    ///
+    /// let result = hashmap.get(1);
+    /// assert_eq!(result.unwrap().get(), (&1, &mut 0));
+    /// ```
+    pub fn insert_with_hash<'a>(
+        &'a self,
+        key: K,
+        hash: u64,
+        value: V,
+    ) -> Result<Accessor<'a, K, V, H>, (Accessor<'a, K, V, H>, V)> {
+        let (hash, partial_hash) = Self::mix(hash);
+        self.insert_and_notify(key, hash, partial_hash, value, None)
+    }
+
+    /// Inserts a key-value pair into the HashMap using the given hash value and partial hash value.
+    pub(crate) fn insert_entry<'a>(
+        &'a self,
+        key: K,
+        hash: u64,
+        partial_hash: u16,
+        value: V,
+        deadline: u64,
+    ) -> Result<Accessor<'a, K, V, H>, (Accessor<'a, K, V, H>, V)> {
         let mut resize_triggered = false;
         loop {
-            let (mut accessor, cell_index) = self.acquire(&key, hash, partial_hash);
+            let (mut accessor, array_ptr, cell_index) =
+                self.acquire(|k| k == &key, hash, partial_hash);
             if !accessor.entry_ptr.is_null() {
                 return Err((accessor, value));
             }
+
+            // expired key-value pairs must be erased before the key is inserted
+            accessor.cell_locker.purge_expired();
             if !resize_triggered
                 && accessor.cell_locker.full()
                 && cell_index < cell::ARRAY_SIZE as usize
@@ -122,6 +514,26 @@ impl<K: Eq + Hash + Sync, V: Sync, H: BuildHasher> HashMap<K, V, H> {
 
             let (sub_index, entry_array_link_ptr, entry_ptr) =
                 accessor.cell_locker.insert(key, partial_hash, value);
+
+            // the array is not dropped as long as the cell is locked
+            unsafe {
+                (*array_ptr).store_hash(
+                    cell_index,
+                    sub_index,
+                    entry_array_link_ptr,
+                    entry_ptr,
+                    hash,
+                )
+            };
+            unsafe {
+                (*array_ptr).store_deadline(
+                    cell_index,
+                    sub_index,
+                    entry_array_link_ptr,
+                    entry_ptr,
+                    deadline,
+                )
+            };
             accessor.sub_index = sub_index;
             accessor.entry_array_link_ptr = entry_array_link_ptr;
             accessor.entry_ptr = entry_ptr;
@@ -150,7 +562,9 @@ impl<K: Eq + Hash + Sync, V: Sync, H: BuildHasher> HashMap<K, V, H> {
         match self.insert(key, value) {
             Ok(result) => result,
             Err((result, value)) => {
+                result.cell_locker.mark_modified();
                 let pair_mut_ptr = result.entry_ptr as *mut (K, V);
+                unsafe { self.notify(&(*pair_mut_ptr).0, Some(&(*pair_mut_ptr).1), Some(&value)) };
                 unsafe { (*pair_mut_ptr).1 = value };
                 result
             }
@@ -179,7 +593,78 @@ impl<K: Eq + Hash + Sync, V: Sync, H: BuildHasher> HashMap<K, V, H> {
     /// ```
     pub fn get<'a>(&'a self, key: K) -> Option<Accessor<'a, K, V, H>> {
         let (hash, partial_hash) = self.hash(&key);
-        let (accessor, _) = self.acquire(&key, hash, partial_hash);
+        self.get_entry(key, hash, partial_hash)
+    }
+
+    /// Gets a mutable reference to the value associated with the key using a pre-computed hash value.
+    ///
+    /// The hash value must be the one that the BuildHasher of the HashMap produces for the key.
+    ///
+    /// # Examples
+    /// ```
+    /// use scc::HashMap;
+    /// use std::collections::hash_map::RandomState;
+    /// use std::hash::{BuildHasher, Hash, Hasher};
+    ///
+    /// let build_hasher = RandomState::new();
+    /// let hashmap: HashMap<u64, u32, RandomState> = HashMap::new(build_hasher.clone(), None);
+    ///
+    /// let mut hasher = build_hasher.build_hasher();
+    /// 1u64.hash(&mut hasher);
+    /// let hash = hasher.finish();
+    ///
+    /// let result = hashmap.get_with_hash(1, hash);
+    /// assert!(result.is_none());
+    ///
+    /// let result = hashmap.insert(1, 0);
+    /// if let Ok(result) = result {
+    ///     assert_eq!(result.get(), (&1, &mut 0));
+    /// }
+    ///
+    /// let result = hashmap.get_with_hash(1, hash);
+    /// assert_eq!(result.unwrap().get(), (&1, &mut 0));
+    /// ```
+    pub fn get_with_hash<'a>(&'a self, key: K, hash: u64) -> Option<Accessor<'a, K, V, H>> {
+        let (hash, partial_hash) = Self::mix(hash);
+        self.get_entry(key, hash, partial_hash)
+    }
+
+    /// Gets a mutable reference to the value associated with the key, or returns Deadlock if the thread is chosen as
+    /// the victim of a deadlock.
+    ///
+    /// It is equivalent to get unless the HashMap was created by with_deadlock_detection. The thread chosen as the
+    /// victim is supposed to drop the Accessors it holds before retrying.
+    ///
+    /// # Examples
+    /// ```
+    /// use scc::HashMap;
+    /// use std::collections::hash_map::RandomState;
+    /// use std::time::Duration;
+    ///
+    /// let hashmap: HashMap<u64, u32, RandomState> =
+    ///     HashMap::with_deadlock_detection(RandomState::new(), None, Duration::from_millis(1));
+    ///
+    /// assert!(hashmap.lock(1).unwrap().is_none());
+    /// assert!(hashmap.insert(1, 0).is_ok());
+    /// assert_eq!(hashmap.lock(1).unwrap().unwrap().get(), (&1, &mut 0));
+    /// ```
+    pub fn lock<'a>(&'a self, key: K) -> Result<Option<Accessor<'a, K, V, H>>, Deadlock> {
+        let (hash, partial_hash) = self.hash(&key);
+        let (accessor, _, _) = self.acquire_or_deadlock(|k| k == &key, hash, partial_hash, true)?;
+        if accessor.entry_ptr.is_null() {
+            return Ok(None);
+        }
+        Ok(Some(accessor))
+    }
+
+    /// Gets a mutable reference to the value using the given hash value and partial hash value.
+    fn get_entry<'a>(
+        &'a self,
+        key: K,
+        hash: u64,
+        partial_hash: u16,
+    ) -> Option<Accessor<'a, K, V, H>> {
+        let (accessor, _, _) = self.acquire(|k| k == &key, hash, partial_hash);
         if accessor.entry_ptr.is_null() {
             return None;
         }
@@ -211,6 +696,39 @@ impl<K: Eq + Hash + Sync, V: Sync, H: BuildHasher> HashMap<K, V, H> {
             .map_or_else(|| false, |accessor| accessor.erase())
     }
 
+    /// Removes a key-value pair using a pre-computed hash value.
+    ///
+    /// The hash value must be the one that the BuildHasher of the HashMap produces for the key.
+    ///
+    /// # Examples
+    /// ```
+    /// use scc::HashMap;
+    /// use std::collections::hash_map::RandomState;
+    /// use std::hash::{BuildHasher, Hash, Hasher};
+    ///
+    /// let build_hasher = RandomState::new();
+    /// let hashmap: HashMap<u64, u32, RandomState> = HashMap::new(build_hasher.clone(), None);
+    ///
+    /// let mut hasher = build_hasher.build_hasher();
+    /// 1u64.hash(&mut hasher);
+    /// let hash = hasher.finish();
+    ///
+    /// let result = hashmap.remove_with_hash(1, hash);
+    /// assert_eq!(result, false);
+    ///
+    /// let result = hashmap.insert(1, 0);
+    /// if let Ok(result) = result {
+    ///     assert_eq!(result.get(), (&1, &mut 0));
+    /// }
+    ///
+    /// let result = hashmap.remove_with_hash(1, hash);
+    /// assert!(result);
+    /// ```
+    pub fn remove_with_hash(&self, key: K, hash: u64) -> bool {
+        self.get_with_hash(key, hash)
+            .map_or_else(|| false, |accessor| accessor.erase())
+    }
+
     /// Reads a key-value pair.
     ///
     /// # Examples
@@ -230,38 +748,130 @@ impl<K: Eq + Hash + Sync, V: Sync, H: BuildHasher> HashMap<K, V, H> {
     /// ```
     pub fn read<U, F: FnOnce(&K, &V) -> U>(&self, key: K, f: F) -> Option<U> {
         let (hash, partial_hash) = self.hash(&key);
+        self.read_entry(|k| k == &key, hash, partial_hash, f)
+    }
+
+    /// Reads a key-value pair using a pre-computed hash value.
+    ///
+    /// The hash value must be the one that the BuildHasher of the HashMap produces for the key.
+    ///
+    /// # Examples
+    /// ```
+    /// use scc::HashMap;
+    /// use std::collections::hash_map::RandomState;
+    /// use std::hash::{BuildHasher, Hash, Hasher};
+    ///
+    /// let build_hasher = RandomState::new();
+    /// let hashmap: HashMap<u64, u32, RandomState> = HashMap::new(build_hasher.clone(), None);
+    ///
+    /// let mut hasher = build_hasher.build_hasher();
+    /// 1u64.hash(&mut hasher);
+    /// let hash = hasher.finish();
+    ///
+    /// let result = hashmap.insert_with_hash(1, hash, 0);
+    /// if let Ok(result) = result {
+    ///     assert_eq!(result.get(), (&1, &mut 0));
+    /// }
+    ///
+    /// let result = hashmap.read_with_hash(1, hash, |key, value| *value);
+    /// assert_eq!(result.unwrap(), 0);
+    ///
+    /// let result = hashmap.read(1, |key, value| *value);
+    /// assert_eq!(result.unwrap(), 0);
+    /// ```
+    pub fn read_with_hash<U, F: FnOnce(&K, &V) -> U>(&self, key: K, hash: u64, f: F) -> Option<U> {
+        let (hash, partial_hash) = Self::mix(hash);
+        self.read_entry(|k| k == &key, hash, partial_hash, f)
+    }
+
+    /// Reads a key-value pair using the given hash value and partial hash value.
+    pub(crate) fn read_entry<U, M: FnMut(&K) -> bool, F: FnOnce(&K, &V) -> U>(
+        &self,
+        mut is_match: M,
+        hash: u64,
+        partial_hash: u16,
+        f: F,
+    ) -> Option<U> {
         let guard = crossbeam_epoch::pin();
 
-        // an acquire fence is required to correctly load the contents of the array
-        let current_array = self.array.load(Acquire, &guard);
-        let current_array_ref = unsafe { current_array.deref() };
-        let old_array = current_array_ref.old_array(&guard);
-        for array_ptr in vec![old_array.as_raw(), current_array.as_raw()] {
-            if array_ptr.is_null() {
-                continue;
-            }
-            if array_ptr == old_array.as_raw() {
-                if current_array_ref.partial_rehash(&guard, |key| self.hash(key)) {
+        'retry: loop {
+            // an acquire fence is required to correctly load the contents of the array
+            let current_array = self.array.load(Acquire, &guard);
+            let current_array_ref = unsafe { current_array.deref() };
+            let old_array = current_array_ref.old_array(&guard);
+            for array_ptr in vec![old_array.as_raw(), current_array.as_raw()] {
+                if array_ptr.is_null() {
                     continue;
                 }
+                if array_ptr == old_array.as_raw() {
+                    if current_array_ref.partial_rehash(&guard, |key| self.hash(key)) {
+                        continue;
+                    }
+                }
+                let array_ref = unsafe { &(*array_ptr) };
+                let cell_index = array_ref.calculate_cell_index(hash);
+                let mut reader = self.read_cell(array_ref, cell_index);
+                reader.set_expiry(self.expiry(array_ref, cell_index));
+                if let Some(entry_ptr) = reader.search(partial_hash, &mut is_match) {
+                    let entry_ref = unsafe { &(*entry_ptr) };
+                    return Some(f(&entry_ref.0, &entry_ref.1));
+                }
+                if array_ptr == current_array.as_raw() && reader.killed() {
+                    // the array has been replaced by a new array since it was loaded
+                    continue 'retry;
+                }
             }
-            let array_ref = unsafe { &(*array_ptr) };
-            let cell_index = array_ref.calculate_cell_index(hash);
-            let reader = CellReader::lock(
-                array_ref.cell(cell_index),
-                array_ref.entry_array(cell_index),
-            );
-            if let Some(entry_ptr) = reader.search(&key, partial_hash) {
-                let entry_ref = unsafe { &(*entry_ptr) };
-                return Some(f(&entry_ref.0, &entry_ref.1));
+            return None;
+        }
+    }
+
+    /// Reads a key-value pair, and returns the version of the cell owning the key along with the result.
+    ///
+    /// The key is owned by the cell in the old array if the cell has yet to be relocated. The arrays are not dropped
+    /// while the given Guard is held, therefore the address of the array never identifies a different array.
+    pub(crate) fn read_versioned<U, F: FnOnce(&K, &V) -> U>(
+        &self,
+        key: &K,
+        f: F,
+        guard: &Guard,
+    ) -> (Option<U>, CellVersion<K, V>) {
+        let (hash, partial_hash) = self.hash(key);
+        loop {
+            // an acquire fence is required to correctly load the contents of the array
+            let current_array = self.array.load(Acquire, guard);
+            let current_array_ref = unsafe { current_array.deref() };
+            let old_array = current_array_ref.old_array(guard);
+            for &array_ptr in &[old_array.as_raw(), current_array.as_raw()] {
+                if array_ptr.is_null() {
+                    continue;
+                }
+                if array_ptr == old_array.as_raw()
+                    && current_array_ref.partial_rehash(guard, |key| self.hash(key))
+                {
+                    continue;
+                }
+                let array_ref = unsafe { &(*array_ptr) };
+                let cell_index = array_ref.calculate_cell_index(hash);
+                let mut reader = self.read_cell(array_ref, cell_index);
+                reader.set_expiry(self.expiry(array_ref, cell_index));
+                let cell_version = (array_ptr, cell_index, reader.version());
+                if let Some(entry_ptr) = reader.search(partial_hash, |k| k == key) {
+                    let entry_ref = unsafe { &(*entry_ptr) };
+                    return (Some(f(&entry_ref.0, &entry_ref.1)), cell_version);
+                }
+                if !reader.killed() {
+                    // the key can only be inserted into the cell that is not killed
+                    return (None, cell_version);
+                }
             }
+            // reaching here indicates that self.array is updated
         }
-        None
     }
 
     /// Retains the key-value pairs that satisfy the given predicate.
     ///
-    /// It returns the number of entries remaining and removed.
+    /// The predicate only gets shared references to the values, therefore every modification that retain makes is a
+    /// removal notified to the ChangeListener. It returns the number of entries remaining and removed.
     ///
     /// # Examples
     /// ```
@@ -289,10 +899,10 @@ impl<K: Eq + Hash + Sync, V: Sync, H: BuildHasher> HashMap<K, V, H> {
     /// let result = hashmap.get(2);
     /// assert!(result.is_none());
     /// ```
-    pub fn retain<F: Fn(&K, &mut V) -> bool>(&self, f: F) -> (usize, usize) {
+    pub fn retain<F: Fn(&K, &V) -> bool>(&self, f: F) -> (usize, usize) {
         let mut retained_entries = 0;
         let mut removed_entries = 0;
-        let mut scanner = self.iter();
+        let mut scanner = self.scan();
         while let Some((key, value)) = scanner.next() {
             if !f(key, value) {
                 scanner.erase_on_next = true;
@@ -344,6 +954,186 @@ impl<K: Eq + Hash + Sync, V: Sync, H: BuildHasher> HashMap<K, V, H> {
         self.retain(|_, _| false).1
     }
 
+    /// Subscribes to the modifications made to the HashMap.
+    ///
+    /// The returned Subscription receives an Event whenever the HashMap notifies the ChangeListener, refer to
+    /// HashMap::with_listener, and up to the given number of events are buffered.
+    ///
+    /// # Examples
+    /// ```
+    /// use scc::{Event, HashMap};
+    /// use std::collections::hash_map::RandomState;
+    ///
+    /// let hashmap: HashMap<u64, u32, RandomState> = HashMap::new(RandomState::new(), None);
+    ///
+    /// let subscription = hashmap.subscribe(16);
+    ///
+    /// assert!(hashmap.insert(1, 0).is_ok());
+    /// hashmap.upsert(1, 1);
+    /// assert!(hashmap.remove(1));
+    ///
+    /// assert_eq!(subscription.try_recv(), Some(Event::Insert(1, 0)));
+    /// assert_eq!(subscription.try_recv(), Some(Event::Update(1, 1)));
+    /// assert_eq!(subscription.try_recv(), Some(Event::Remove(1)));
+    /// assert!(subscription.try_recv().is_none());
+    /// ```
+    pub fn subscribe(&self, capacity: usize) -> Subscription<K, V>
+    where
+        K: Clone,
+        V: Clone,
+    {
+        self.subscribers.subscribe(capacity)
+    }
+
+    /// Replays change records.
+    ///
+    /// A record is a key and its new value, and None represents a removal. The records are applied in the given
+    /// order without notifying the ChangeListener and the Subscriptions, and it returns the number of applied
+    /// records. Inserted key-value pairs never expire; use replay_with_deadlines to restore the deadlines.
+    ///
+    /// # Examples
+    /// ```
+    /// use scc::HashMap;
+    /// use std::collections::hash_map::RandomState;
+    ///
+    /// let hashmap: HashMap<u64, u32, RandomState> = HashMap::new(RandomState::new(), None);
+    ///
+    /// let result = hashmap.replay(vec![(1, Some(0)), (2, Some(0)), (1, Some(1)), (2, None)]);
+    /// assert_eq!(result, 4);
+    ///
+    /// assert_eq!(hashmap.read(1, |_, value| *value), Some(1));
+    /// assert!(hashmap.read(2, |_, value| *value).is_none());
+    /// ```
+    pub fn replay<I: IntoIterator<Item = (K, Option<V>)>>(&self, records: I) -> usize {
+        self.replay_with_deadlines(records.into_iter().map(|(key, value)| (key, value, None)))
+    }
+
+    /// Replays change records along with the deadlines passed to ChangeListener::on_change_with_deadline.
+    ///
+    /// A record is a key, its new value, and the deadline of the key-value pair if the record inserts the key. The
+    /// deadline is restored when the record inserts the key, and a key-value pair of which the deadline has passed
+    /// is regarded as removed. Deadlines are measured by the Clock of the HashMap, therefore the Clock has to share
+    /// the origin with the Clock of the HashMap that produced the records, and they are ignored if the HashMap does
+    /// not support time-to-live.
+    ///
+    /// # Examples
+    /// ```
+    /// use scc::{HashMap, ManualClock};
+    /// use std::collections::hash_map::RandomState;
+    /// use std::sync::Arc;
+    /// use std::time::Duration;
+    ///
+    /// let clock = Arc::new(ManualClock::default());
+    /// let hashmap: HashMap<u64, u32, RandomState> =
+    ///     HashMap::with_clock(RandomState::new(), None, clock.clone());
+    ///
+    /// let records = vec![
+    ///     (1, Some(0), Some(Duration::from_secs(1))),
+    ///     (2, Some(0), Some(Duration::from_secs(2))),
+    ///     (3, Some(0), None),
+    /// ];
+    /// clock.advance(Duration::from_secs(1));
+    /// assert_eq!(hashmap.replay_with_deadlines(records), 3);
+    ///
+    /// assert!(hashmap.read(1, |_, value| *value).is_none());
+    /// assert_eq!(hashmap.read(2, |_, value| *value), Some(0));
+    /// assert_eq!(hashmap.read(3, |_, value| *value), Some(0));
+    ///
+    /// clock.advance(Duration::from_secs(1));
+    /// assert!(hashmap.read(2, |_, value| *value).is_none());
+    /// ```
+    pub fn replay_with_deadlines<I: IntoIterator<Item = (K, Option<V>, Option<Duration>)>>(
+        &self,
+        records: I,
+    ) -> usize {
+        let now = self.clock.as_ref().map(|clock| clock.now());
+        let mut num_records = 0;
+        for (key, value, deadline) in records {
+            let (hash, partial_hash) = self.hash(&key);
+            let deadline = match (now, deadline) {
+                (Some(now), Some(deadline)) => Some((now, deadline)),
+                _ => None,
+            };
+            match (value, deadline) {
+                (Some(_), Some((now, deadline))) if deadline <= now => {
+                    // the key-value pair had expired before the records were replayed
+                    if let Some(accessor) = self.get_entry(key, hash, partial_hash) {
+                        self.erase_entry(accessor);
+                    }
+                }
+                (Some(value), deadline) => {
+                    let deadline =
+                        deadline.map_or(0, |(_, deadline)| (deadline.as_nanos() as u64).max(1));
+                    if let Err((accessor, value)) =
+                        self.insert_entry(key, hash, partial_hash, value, deadline)
+                    {
+                        accessor.cell_locker.mark_modified();
+                        let pair_mut_ptr = accessor.entry_ptr as *mut (K, V);
+                        unsafe { (*pair_mut_ptr).1 = value };
+                    }
+                }
+                (None, _) => {
+                    if let Some(accessor) = self.get_entry(key, hash, partial_hash) {
+                        self.erase_entry(accessor);
+                    }
+                }
+            }
+            num_records += 1;
+        }
+        num_records
+    }
+
+    /// Erases all the expired key-value pairs.
+    ///
+    /// It locks the cells one after another, and returns the number of expired key-value pairs that it erased;
+    /// expired key-value pairs erased by other operations in the meantime are not counted.
+    ///
+    /// # Examples
+    /// ```
+    /// use scc::{HashMap, ManualClock};
+    /// use std::collections::hash_map::RandomState;
+    /// use std::sync::Arc;
+    /// use std::time::Duration;
+    ///
+    /// let clock = Arc::new(ManualClock::default());
+    /// let hashmap: HashMap<u64, u32, RandomState> =
+    ///     HashMap::with_clock(RandomState::new(), None, clock.clone());
+    ///
+    /// assert!(hashmap.insert_with_ttl(1, 0, Duration::from_secs(1)).is_ok());
+    /// assert!(hashmap.insert(2, 0).is_ok());
+    ///
+    /// clock.advance(Duration::from_secs(1));
+    /// let result = hashmap.purge_expired();
+    /// assert_eq!(result, 1);
+    /// assert_eq!(hashmap.len(|capacity| capacity), 1);
+    /// ```
+    pub fn purge_expired(&self) -> usize {
+        let mut num_expired = 0;
+        let guard = crossbeam_epoch::pin();
+        let current_array = self.array.load(Acquire, &guard);
+        let current_array_ref = unsafe { current_array.deref() };
+        let old_array = current_array_ref.old_array(&guard);
+        for array_ptr in &[old_array.as_raw(), current_array.as_raw()] {
+            if array_ptr.is_null() {
+                continue;
+            }
+            let array_ref = unsafe { &(**array_ptr) };
+            if !array_ref.track_deadline() {
+                continue;
+            }
+            for cell_index in 0..array_ref.num_cells() {
+                let mut locker = self.lock_cell(array_ref, cell_index);
+                if !locker.killed() {
+                    num_expired += locker.purge_expired();
+                }
+            }
+        }
+        if num_expired > 0 {
+            self.resize(true);
+        }
+        num_expired
+    }
+
     /// Returns an estimated size of the HashMap.
     ///
     /// It passes the capacity of the HashMap to the given function.
@@ -428,6 +1218,10 @@ impl<K: Eq + Hash + Sync, V: Sync, H: BuildHasher> HashMap<K, V, H> {
     /// let statistics = hashmap.statistics();
     /// assert_eq!(statistics.num_entries(), 1);
     /// assert_eq!(statistics.capacity(), 1024);
+    /// assert_eq!(statistics.occupancy_histogram()[1], 1);
+    /// assert_eq!(statistics.occupancy_histogram()[0], 63);
+    /// assert_eq!(statistics.link_length_histogram(), &[64]);
+    /// assert_eq!(statistics.num_partial_hash_collisions(), 0);
     /// ```
     pub fn statistics(&self) -> Statistics {
         let mut statistics = Statistics {
@@ -441,6 +1235,9 @@ impl<K: Eq + Hash + Sync, V: Sync, H: BuildHasher> HashMap<K, V, H> {
             linked_entries: 0,
             cells_having_link: 0,
             max_link_length: 0,
+            occupancy_histogram: [0; cell::ARRAY_SIZE as usize + 1],
+            link_length_histogram: Vec::new(),
+            partial_hash_collisions: 0,
         };
         let guard = crossbeam_epoch::pin();
         let current_array = self.array.load(Acquire, &guard);
@@ -458,8 +1255,18 @@ impl<K: Eq + Hash + Sync, V: Sync, H: BuildHasher> HashMap<K, V, H> {
             }
             statistics.cells += num_cells;
             for i in 0..num_cells {
+                let reader = self.read_cell(array_ref, i);
                 let (size, linked_entries) = array_ref.cell(i).size();
                 statistics.entries += size + linked_entries;
+                statistics.occupancy_histogram[size] += 1;
+                if statistics.link_length_histogram.len() <= linked_entries {
+                    statistics
+                        .link_length_histogram
+                        .resize(linked_entries + 1, 0);
+                }
+                statistics.link_length_histogram[linked_entries] += 1;
+                statistics.partial_hash_collisions += reader.partial_hash_collisions();
+                drop(reader);
                 if size == 0 {
                     statistics.empty_cells += 1;
                     consecutive_empty_cells += 1;
@@ -484,10 +1291,231 @@ impl<K: Eq + Hash + Sync, V: Sync, H: BuildHasher> HashMap<K, V, H> {
         statistics
     }
 
+    /// Returns a RawEntry that locates key-value pairs with a pre-computed hash value and a predicate.
+    ///
+    /// # Examples
+    /// ```
+    /// use scc::HashMap;
+    /// use std::collections::hash_map::RandomState;
+    /// use std::hash::{BuildHasher, Hash, Hasher};
+    ///
+    /// let build_hasher = RandomState::new();
+    /// let hashmap: HashMap<String, u32, RandomState> = HashMap::new(build_hasher.clone(), None);
+    ///
+    /// let result = hashmap.insert(String::from("one"), 1);
+    /// if let Ok(result) = result {
+    ///     assert_eq!(result.get(), (&String::from("one"), &mut 1));
+    /// }
+    ///
+    /// let mut hasher = build_hasher.build_hasher();
+    /// "one".hash(&mut hasher);
+    /// let hash = hasher.finish();
+    ///
+    /// let result = hashmap.raw_entry().from_hash(hash, |key| key == "one");
+    /// assert_eq!(result.unwrap().get(), (&String::from("one"), &mut 1));
+    /// ```
+    pub fn raw_entry<'a>(&'a self) -> RawEntry<'a, K, V, H> {
+        RawEntry { hash_map: self }
+    }
+
+    /// Locks the cells owning the given keys at once.
+    ///
+    /// The returned MultiAccessor reads, updates, inserts and removes the keys as a single atomic step. The cells are
+    /// locked in ascending order of their indexes, and the keys that are still in the old array of an in-progress
+    /// resize are relocated to the current array beforehand, therefore multiple threads calling lock_many do not
+    /// deadlock each other.
+    ///
+    /// # Examples
+    /// ```
+    /// use scc::HashMap;
+    /// use std::collections::hash_map::RandomState;
+    ///
+    /// let hashmap: HashMap<u64, u32, RandomState> = HashMap::new(RandomState::new(), None);
+    ///
+    /// assert!(hashmap.insert(1, 0).is_ok());
+    ///
+    /// let mut multi_accessor = hashmap.lock_many(&[1, 2]);
+    /// let (_, value) = multi_accessor.remove(&1).unwrap();
+    /// assert!(multi_accessor.insert(2, value).is_ok());
+    /// drop(multi_accessor);
+    ///
+    /// assert!(hashmap.read(1, |_, _| ()).is_none());
+    /// assert_eq!(hashmap.read(2, |_, value| *value), Some(0));
+    /// ```
+    pub fn lock_many<'a>(&'a self, keys: &[K]) -> MultiAccessor<'a, K, V, H> {
+        let guard = crossbeam_epoch::pin();
+        let hashes: Vec<u64> = keys.iter().map(|key| self.hash(key).0).collect();
+        loop {
+            // an acquire fence is required to correctly load the contents of the array
+            let current_array = self.array.load(Acquire, &guard);
+            // bypass the lifetime checker by not calling Shared::deref()
+            let current_array_ref = unsafe { &(*current_array.as_raw()) };
+            let old_array = current_array_ref.old_array(&guard);
+            if !old_array.is_null() {
+                if current_array_ref.partial_rehash(&guard, |key| self.hash(key)) {
+                    continue;
+                }
+
+                // the keys must not be in the old array when the cells in the current array are locked, otherwise a
+                // thread relocating them would lock the cells while holding a cell in the old array
+                let old_array_ref = unsafe { old_array.deref() };
+                for hash in hashes.iter() {
+                    let cell_index = old_array_ref.calculate_cell_index(*hash);
+                    let mut locker = CellLocker::lock(
+                        old_array_ref.cell(cell_index),
+                        old_array_ref.entry_array(cell_index),
+                    );
+                    current_array_ref.kill_cell(&mut locker, old_array_ref, cell_index, &|key| {
+                        self.hash(key)
+                    });
+                }
+            }
+
+            let mut cell_indexes: Vec<usize> = hashes
+                .iter()
+                .map(|hash| current_array_ref.calculate_cell_index(*hash))
+                .collect();
+            cell_indexes.sort_unstable();
+            cell_indexes.dedup();
+            let num_cells = cell_indexes.len();
+            let mut cell_lockers = Vec::with_capacity(num_cells);
+            for cell_index in cell_indexes {
+                let locker = self.lock_cell(current_array_ref, cell_index);
+                if locker.killed() {
+                    break;
+                }
+                cell_lockers.push((cell_index, locker));
+            }
+            if cell_lockers.len() == num_cells {
+                // once the cells are locked, the array is guaranteed to retain
+                return MultiAccessor {
+                    hash_map: self,
+                    array_ptr: current_array.as_raw(),
+                    cell_lockers,
+                    borrowed: Vec::new(),
+                };
+            }
+            // reaching here indicates that self.array is updated
+        }
+    }
+
+    /// Starts an optimistic transaction.
+    ///
+    /// The returned Transaction records the versions of the cells owning the keys it reads, and buffers updates until
+    /// it is committed. See Transaction for details.
+    ///
+    /// # Examples
+    /// ```
+    /// use scc::HashMap;
+    /// use std::collections::hash_map::RandomState;
+    ///
+    /// let hashmap: HashMap<u64, u32, RandomState> = HashMap::new(RandomState::new(), None);
+    ///
+    /// assert!(hashmap.insert(1, 1).is_ok());
+    ///
+    /// let mut transaction = hashmap.transaction();
+    /// let value = transaction.read(&1, |_, value| *value).unwrap();
+    /// transaction.upsert(2, value + 1);
+    /// assert!(transaction.commit().is_ok());
+    ///
+    /// assert_eq!(hashmap.read(2, |_, value| *value), Some(2));
+    /// ```
+    pub fn transaction<'a>(&'a self) -> Transaction<'a, K, V, H>
+    where
+        K: Clone,
+    {
+        Transaction::new(self)
+    }
+
+    /// Returns hash quality diagnostics of the given sample of keys.
+    ///
+    /// The sampled keys are distributed over the number of cells that the HashMap would allocate when created with
+    /// the number of keys as the minimum capacity, and the distribution is analyzed without modifying the HashMap.
+    /// The keys are assumed to be unique; duplicate keys are reported as partial hash collisions.
+    ///
+    /// # Examples
+    /// ```
+    /// use scc::HashMap;
+    /// use std::collections::hash_map::RandomState;
+    ///
+    /// let hashmap: HashMap<u64, u32, RandomState> = HashMap::new(RandomState::new(), None);
+    ///
+    /// let keys: Vec<u64> = (0..1024).collect();
+    /// let diagnostics = hashmap.hash_diagnostics(keys.iter());
+    /// assert_eq!(diagnostics.num_keys(), 1024);
+    /// assert_eq!(diagnostics.num_cells(), 64);
+    /// assert!(diagnostics.cell_index_skew() >= 1.0);
+    /// assert!(diagnostics.partial_hash_collision_rate() < 0.01);
+    /// ```
+    pub fn hash_diagnostics<'k, I: IntoIterator<Item = &'k K>>(&self, keys: I) -> HashDiagnostics
+    where
+        K: 'k,
+    {
+        let mut hashes: Vec<(u64, u16)> = keys.into_iter().map(|key| self.hash(key)).collect();
+        let capacity = hashes.len().max(self.minimum_capacity);
+        let lb_capacity = Array::<K, V>::calculate_lb_metadata_array_size(capacity);
+        let mut diagnostics = HashDiagnostics {
+            keys: hashes.len(),
+            cells: 1usize << lb_capacity,
+            occupied_cells: 0,
+            max_cell_load: 0,
+            partial_hash_collisions: 0,
+            linked_entries: 0,
+            max_link_arrays: 0,
+        };
+
+        // sorting the hash values groups the keys by their cell index, and linked entries only retain the upper
+        // fifteen bits of partial hash values
+        hashes.sort_unstable_by_key(|(hash, partial_hash)| {
+            (
+                Array::<K, V>::cell_index(*hash, lb_capacity),
+                *partial_hash >> 1,
+            )
+        });
+        let mut start = 0;
+        while start < hashes.len() {
+            let cell_index = Array::<K, V>::cell_index(hashes[start].0, lb_capacity);
+            let mut end = start + 1;
+            let mut run_len = 1;
+            while end < hashes.len()
+                && Array::<K, V>::cell_index(hashes[end].0, lb_capacity) == cell_index
+            {
+                if hashes[end].1 >> 1 == hashes[end - 1].1 >> 1 {
+                    run_len += 1;
+                } else {
+                    if run_len > 1 {
+                        diagnostics.partial_hash_collisions += run_len;
+                    }
+                    run_len = 1;
+                }
+                end += 1;
+            }
+            if run_len > 1 {
+                diagnostics.partial_hash_collisions += run_len;
+            }
+            let cell_load = end - start;
+            let linked_entries = cell_load.saturating_sub(cell::ARRAY_SIZE as usize);
+            let link_arrays = if linked_entries == 0 {
+                0
+            } else {
+                (linked_entries - 1) / link::ARRAY_SIZE + 1
+            };
+            diagnostics.occupied_cells += 1;
+            diagnostics.max_cell_load = diagnostics.max_cell_load.max(cell_load);
+            diagnostics.linked_entries += linked_entries;
+            diagnostics.max_link_arrays = diagnostics.max_link_arrays.max(link_arrays);
+            start = end;
+        }
+        diagnostics
+    }
+
     /// Returns a Scanner.
     ///
     /// It is guaranteed to scan all the key-value pairs pertaining in the HashMap at the moment,
     /// however the same key-value pair can be scanned more than once if the HashMap is being resized.
+    /// Since the Scanner hands out mutable references to the values, the scanned cells are regarded as modified, and
+    /// reads of open Transactions on the key-value pairs are invalidated; retain and purge_expired do not invalidate
+    /// the reads unless they erase the key-value pairs.
     ///
     /// # Examples
     /// ```
@@ -515,6 +1543,11 @@ impl<K: Eq + Hash + Sync, V: Sync, H: BuildHasher> HashMap<K, V, H> {
             if let Some(scanner) = self.pick(locker, array_ptr, cell_index) {
                 return scanner;
             }
+
+            // all the key-value pairs in the cell have expired
+            if let Some(scanner) = self.next(array_ptr, cell_index) {
+                return scanner;
+            }
         }
         Scanner {
             accessor: None,
@@ -522,15 +1555,31 @@ impl<K: Eq + Hash + Sync, V: Sync, H: BuildHasher> HashMap<K, V, H> {
             cell_index: 0,
             activated: false,
             erase_on_next: false,
+            read_only: false,
         }
     }
 
+    /// Returns a Scanner that does not regard the scanned cells as modified.
+    ///
+    /// The values must not be modified through the Scanner; the cells are only regarded as modified when key-value
+    /// pairs are erased, and therefore scanning the HashMap does not invalidate the reads of open Transactions.
+    pub(crate) fn scan<'a>(&'a self) -> Scanner<'a, K, V, H> {
+        let mut scanner = self.iter();
+        scanner.read_only = true;
+        scanner
+    }
+
     /// Returns a hash value of the given key.
-    fn hash(&self, key: &K) -> (u64, u16) {
+    pub(crate) fn hash(&self, key: &K) -> (u64, u16) {
         // generate a hash value
         let mut h = self.hasher.build_hasher();
         key.hash(&mut h);
-        let mut hash = h.finish();
+        Self::mix(h.finish())
+    }
+
+    /// Mixes the bits of the given hash value, and derives a partial hash value from it.
+    pub(crate) fn mix(hash: u64) -> (u64, u16) {
+        let mut hash = hash;
 
         // bitmix: https://mostlymangling.blogspot.com/2019/01/better-stronger-mixer-and-test-procedure.html
         hash = hash ^ (hash.rotate_right(25) ^ hash.rotate_right(50));
@@ -541,13 +1590,50 @@ impl<K: Eq + Hash + Sync, V: Sync, H: BuildHasher> HashMap<K, V, H> {
         (hash, (hash & ((1 << 16) - 1)).try_into().unwrap())
     }
 
+    /// Returns the deadline of a key-value pair inserted now with the given time-to-live.
+    fn deadline(&self, ttl: Duration) -> u64 {
+        self.clock.as_ref().map_or(0, |clock| {
+            let deadline = clock
+                .now()
+                .checked_add(ttl)
+                .map_or(u64::MAX, |deadline| deadline.as_nanos() as u64);
+            // zero represents that the key-value pair never expires
+            deadline.max(1)
+        })
+    }
+
+    /// Returns the Expiry of the cell if the HashMap supports time-to-live.
+    fn expiry<'a>(&self, array_ref: &'a Array<K, V>, cell_index: usize) -> Option<Expiry<'a>> {
+        match (self.clock.as_ref(), array_ref.deadline_array(cell_index)) {
+            (Some(clock), Some(deadline_array)) => {
+                Some(Expiry::new(deadline_array, clock.now().as_nanos() as u64))
+            }
+            _ => None,
+        }
+    }
+
     /// Acquires a cell.
-    fn acquire<'a>(
+    fn acquire<'a, F: FnMut(&K) -> bool>(
         &'a self,
-        key: &K,
+        is_match: F,
+        hash: u64,
+        partial_hash: u16,
+    ) -> AcquireResult<'a, K, V, H> {
+        match self.acquire_or_deadlock(is_match, hash, partial_hash, false) {
+            Ok(result) => result,
+            // a thread that is not abortable is never chosen as the victim of a deadlock
+            Err(Deadlock) => unreachable!(),
+        }
+    }
+
+    /// Acquires a cell, or returns Deadlock if the thread is abortable and chosen as the victim of a deadlock.
+    fn acquire_or_deadlock<'a, F: FnMut(&K) -> bool>(
+        &'a self,
+        mut is_match: F,
         hash: u64,
         partial_hash: u16,
-    ) -> (Accessor<'a, K, V, H>, usize) {
+        abortable: bool,
+    ) -> Result<AcquireResult<'a, K, V, H>, Deadlock> {
         let guard = crossbeam_epoch::pin();
 
         // it is guaranteed that the thread reads a consistent snapshot of the current and
@@ -571,10 +1657,16 @@ impl<K: Eq + Hash + Sync, V: Sync, H: BuildHasher> HashMap<K, V, H> {
                 if current_array_ref.partial_rehash(&guard, |key| self.hash(key)) {
                     continue;
                 }
-                let (mut locker, entry_array_link_ptr, entry_ptr, cell_index, sub_index) =
-                    self.search(&key, hash, partial_hash, old_array.as_raw());
+                let (mut locker, entry_array_link_ptr, entry_ptr, cell_index, sub_index) = self
+                    .search(
+                        &mut is_match,
+                        hash,
+                        partial_hash,
+                        old_array.as_raw(),
+                        abortable,
+                    )?;
                 if !entry_ptr.is_null() {
-                    return (
+                    return Ok((
                         Accessor {
                             hash_map: &self,
                             cell_locker: locker,
@@ -582,9 +1674,11 @@ impl<K: Eq + Hash + Sync, V: Sync, H: BuildHasher> HashMap<K, V, H> {
                             sub_index: sub_index,
                             entry_array_link_ptr: entry_array_link_ptr,
                             entry_ptr: entry_ptr,
+                            value_borrowed: std::cell::Cell::new(false),
                         },
+                        old_array.as_raw(),
                         cell_index,
-                    );
+                    ));
                 } else if !locker.killed() {
                     // kill the cell
                     let old_array_ref = unsafe { old_array.deref() };
@@ -593,10 +1687,15 @@ impl<K: Eq + Hash + Sync, V: Sync, H: BuildHasher> HashMap<K, V, H> {
                     });
                 }
             }
-            let (locker, entry_array_link_ptr, entry_ptr, cell_index, sub_index) =
-                self.search(&key, hash, partial_hash, current_array.as_raw());
+            let (locker, entry_array_link_ptr, entry_ptr, cell_index, sub_index) = self.search(
+                &mut is_match,
+                hash,
+                partial_hash,
+                current_array.as_raw(),
+                abortable,
+            )?;
             if !locker.killed() {
-                return (
+                return Ok((
                     Accessor {
                         hash_map: &self,
                         cell_locker: locker,
@@ -604,16 +1703,61 @@ impl<K: Eq + Hash + Sync, V: Sync, H: BuildHasher> HashMap<K, V, H> {
                         sub_index: sub_index,
                         entry_array_link_ptr: entry_array_link_ptr,
                         entry_ptr: entry_ptr,
+                        value_borrowed: std::cell::Cell::new(false),
                     },
+                    current_array.as_raw(),
                     cell_index,
-                );
+                ));
             }
             // reaching here indicates that self.array is updated
         }
     }
 
+    /// Inserts a key-value pair, and notifies the ChangeListener of the insertion.
+    fn insert_and_notify<'a>(
+        &'a self,
+        key: K,
+        hash: u64,
+        partial_hash: u16,
+        value: V,
+        ttl: Option<Duration>,
+    ) -> Result<Accessor<'a, K, V, H>, (Accessor<'a, K, V, H>, V)> {
+        let deadline = ttl.map_or(0, |ttl| self.deadline(ttl));
+        let result = self.insert_entry(key, hash, partial_hash, value, deadline);
+        if let Ok(accessor) = result.as_ref() {
+            // the key-value pair is invisible to other threads until the Accessor is dropped
+            let (key, value) = unsafe { &*accessor.entry_ptr };
+            if let Some(listener) = self.listener.as_ref() {
+                let deadline = if deadline == 0 {
+                    None
+                } else {
+                    Some(Duration::from_nanos(deadline))
+                };
+                listener.on_change_with_deadline(key, None, Some(value), deadline);
+            }
+            self.subscribers.publish(key, None, Some(value));
+        }
+        result
+    }
+
+    /// Notifies the ChangeListener and the Subscriptions of a modification.
+    pub(crate) fn notify(&self, key: &K, old_value: Option<&V>, new_value: Option<&V>) {
+        if let Some(listener) = self.listener.as_ref() {
+            listener.on_change(key, old_value, new_value);
+        }
+        self.subscribers.publish(key, old_value, new_value);
+    }
+
+    /// Erases a key-value pair owned by the accessor, and notifies the ChangeListener of the removal.
+    fn erase<'a>(&'a self, accessor: Accessor<'a, K, V, H>) {
+        let (key, value) = unsafe { &*accessor.entry_ptr };
+        self.notify(key, Some(value), None);
+        self.erase_entry(accessor);
+    }
+
     /// Erases a key-value pair owned by the accessor.
-    fn erase<'a>(&'a self, mut accessor: Accessor<'a, K, V, H>) {
+    fn erase_entry<'a>(&'a self, mut accessor: Accessor<'a, K, V, H>) {
+        accessor.value_borrowed.set(false);
         accessor.cell_locker.remove(
             true,
             accessor.sub_index,
@@ -626,40 +1770,108 @@ impl<K: Eq + Hash + Sync, V: Sync, H: BuildHasher> HashMap<K, V, H> {
         }
     }
 
+    /// Exclusively locks a cell.
+    ///
+    /// The contents of the cell are preserved for the checkpoint in progress before the cell is modified.
+    fn lock_cell<'a>(
+        &'a self,
+        array_ref: &'a Array<K, V>,
+        cell_index: usize,
+    ) -> CellLocker<'a, K, V> {
+        match self.lock_cell_or_deadlock(array_ref, cell_index, false) {
+            Ok(locker) => locker,
+            // a thread that is not abortable is never chosen as the victim of a deadlock
+            Err(Deadlock) => unreachable!(),
+        }
+    }
+
+    /// Exclusively locks a cell, or returns Deadlock if the thread is abortable and chosen as the victim of a
+    /// deadlock while waiting for the cell.
+    fn lock_cell_or_deadlock<'a>(
+        &'a self,
+        array_ref: &'a Array<K, V>,
+        cell_index: usize,
+        abortable: bool,
+    ) -> Result<CellLocker<'a, K, V>, Deadlock> {
+        let (cell, entry_array) = (
+            array_ref.cell(cell_index),
+            array_ref.entry_array(cell_index),
+        );
+        let mut locker = match self.wait_for_graph.as_ref() {
+            Some(wait_for_graph) => {
+                CellLocker::lock_tracked(cell, entry_array, wait_for_graph, abortable)?
+            }
+            None => CellLocker::lock(cell, entry_array),
+        };
+        let expiry = self.expiry(array_ref, cell_index);
+        if expiry.is_some() {
+            locker.set_expiry(expiry);
+            locker.set_expiry_listener(Some(self));
+        }
+
+        // the checkpoint is activated while all the cells are locked
+        let guard = unsafe { crossbeam_epoch::unprotected() };
+        if !self.checkpoint.load(Relaxed, guard).is_null() {
+            let guard = crossbeam_epoch::pin();
+            let checkpoint = self.checkpoint.load(Acquire, &guard);
+            if let Some(checkpoint_ref) = unsafe { checkpoint.as_ref() } {
+                checkpoint_ref.preserve(array_ref, cell_index, &mut locker);
+            }
+        }
+        Ok(locker)
+    }
+
+    /// Shared-locks a cell.
+    fn read_cell<'a>(
+        &'a self,
+        array_ref: &'a Array<K, V>,
+        cell_index: usize,
+    ) -> CellReader<'a, K, V> {
+        let (cell, entry_array) = (
+            array_ref.cell(cell_index),
+            array_ref.entry_array(cell_index),
+        );
+        match self.wait_for_graph.as_ref() {
+            Some(wait_for_graph) => CellReader::lock_tracked(cell, entry_array, wait_for_graph),
+            None => CellReader::lock(cell, entry_array),
+        }
+    }
+
     /// Searches a cell for the key.
-    fn search<'a>(
-        &self,
-        key: &K,
+    fn search<'a, F: FnMut(&K) -> bool>(
+        &'a self,
+        is_match: F,
         hash: u64,
         partial_hash: u16,
         array_ptr: *const Array<K, V>,
-    ) -> (
-        CellLocker<'a, K, V>,
-        *const EntryArrayLink<K, V>,
-        *const (K, V),
-        usize,
-        u8,
-    ) {
+        abortable: bool,
+    ) -> Result<
+        (
+            CellLocker<'a, K, V>,
+            *const EntryArrayLink<K, V>,
+            *const (K, V),
+            usize,
+            u8,
+        ),
+        Deadlock,
+    > {
         let array_ref = unsafe { &(*array_ptr) };
         let cell_index = array_ref.calculate_cell_index(hash);
-        let locker = CellLocker::lock(
-            array_ref.cell(cell_index),
-            array_ref.entry_array(cell_index),
-        );
+        let locker = self.lock_cell_or_deadlock(array_ref, cell_index, abortable)?;
         if !locker.killed() && !locker.empty() {
             if let Some((sub_index, entry_array_link_ptr, entry_ptr)) =
-                locker.search(key, partial_hash)
+                locker.search(partial_hash, is_match)
             {
-                return (
+                return Ok((
                     locker,
                     entry_array_link_ptr,
                     entry_ptr,
                     cell_index,
                     sub_index,
-                );
+                ));
             }
         }
-        (locker, std::ptr::null(), std::ptr::null(), cell_index, 0)
+        Ok((locker, std::ptr::null(), std::ptr::null(), cell_index, 0))
     }
 
     /// Returns the first valid cell.
@@ -677,10 +1889,7 @@ impl<K: Eq + Hash + Sync, V: Sync, H: BuildHasher> HashMap<K, V, H> {
                 let array_ref = unsafe { &(*array_ptr) };
                 let num_cells = array_ref.num_cells();
                 for cell_index in 0..num_cells {
-                    let locker = CellLocker::lock(
-                        array_ref.cell(cell_index),
-                        array_ref.entry_array(cell_index),
-                    );
+                    let locker = self.lock_cell(array_ref, cell_index);
                     if !locker.empty() {
                         // once a valid cell is locked, the array is guaranteed to retain
                         return (Some(locker), array_ptr, cell_index);
@@ -721,10 +1930,7 @@ impl<K: Eq + Hash + Sync, V: Sync, H: BuildHasher> HashMap<K, V, H> {
             let old_array_ref = unsafe { &(*old_array.as_raw()) };
             let num_cells = old_array_ref.num_cells();
             for cell_index in (current_index + 1)..num_cells {
-                let locker = CellLocker::lock(
-                    old_array_ref.cell(cell_index),
-                    old_array_ref.entry_array(cell_index),
-                );
+                let locker = self.lock_cell(old_array_ref, cell_index);
                 if !locker.killed() && !locker.empty() {
                     if let Some(scanner) = self.pick(locker, old_array.as_raw(), cell_index) {
                         return Some(scanner);
@@ -741,10 +1947,7 @@ impl<K: Eq + Hash + Sync, V: Sync, H: BuildHasher> HashMap<K, V, H> {
             current_index + 1
         };
         for cell_index in (start_index)..num_cells {
-            let locker = CellLocker::lock(
-                current_array_ref.cell(cell_index),
-                current_array_ref.entry_array(cell_index),
-            );
+            let locker = self.lock_cell(current_array_ref, cell_index);
             if !locker.killed() && !locker.empty() {
                 if let Some(scanner) = self.pick(locker, current_array.as_raw(), cell_index) {
                     return Some(scanner);
@@ -759,10 +1962,7 @@ impl<K: Eq + Hash + Sync, V: Sync, H: BuildHasher> HashMap<K, V, H> {
             let new_array_ref = unsafe { &(*new_array.as_raw()) };
             let num_cells = new_array_ref.num_cells();
             for cell_index in 0..num_cells {
-                let locker = CellLocker::lock(
-                    new_array_ref.cell(cell_index),
-                    new_array_ref.entry_array(cell_index),
-                );
+                let locker = self.lock_cell(new_array_ref, cell_index);
                 if !locker.killed() && !locker.empty() {
                     if let Some(scanner) = self.pick(locker, new_array.as_raw(), cell_index) {
                         return Some(scanner);
@@ -776,7 +1976,7 @@ impl<K: Eq + Hash + Sync, V: Sync, H: BuildHasher> HashMap<K, V, H> {
     /// Picks a key-value pair entry using the given CellLocker.
     fn pick<'a>(
         &'a self,
-        cell_locker: CellLocker<'a, K, V>,
+        mut cell_locker: CellLocker<'a, K, V>,
         array_ptr: *const Array<K, V>,
         cell_index: usize,
     ) -> Option<Scanner<'a, K, V, H>> {
@@ -789,11 +1989,13 @@ impl<K: Eq + Hash + Sync, V: Sync, H: BuildHasher> HashMap<K, V, H> {
                     sub_index: sub_index,
                     entry_array_link_ptr: entry_array_link_ptr,
                     entry_ptr: entry_ptr,
+                    value_borrowed: std::cell::Cell::new(false),
                 }),
                 array_ptr: array_ptr,
                 cell_index: cell_index,
                 activated: false,
                 erase_on_next: false,
+                read_only: false,
             });
         }
         None
@@ -837,33 +2039,22 @@ impl<K: Eq + Hash + Sync, V: Sync, H: BuildHasher> HashMap<K, V, H> {
                 return;
             }
 
-            // the resizing policies are as follows.
-            //  - load factor reaches 7/8: enlarge up to 64x
-            //  - load factor reaches 1/8: shrink
             let capacity = current_array_ref.capacity();
             let estimated_num_entries = self.len(|capacity| (capacity / 16).min(16384));
-            let new_capacity = if estimated_num_entries >= (capacity / 8) * 7 {
-                if capacity >= (1usize << (std::mem::size_of::<usize>() * 8 - 1)) {
-                    capacity
-                } else {
-                    (capacity.min(
-                        1usize
-                            << (std::mem::size_of::<usize>() * 8
-                                - (MAX_ENLARGE_FACTOR as usize + 1)),
-                    ) * (1 << MAX_ENLARGE_FACTOR as usize))
-                        .min(estimated_num_entries.next_power_of_two() * 2)
-                }
-            } else if estimated_num_entries <= capacity / 8 {
-                estimated_num_entries
-                    .next_power_of_two()
-                    .max(self.minimum_capacity)
-            } else {
-                capacity
-            };
+            let new_capacity = Array::<K, V>::calculate_new_capacity(
+                capacity,
+                estimated_num_entries,
+                self.minimum_capacity,
+            );
 
             // Array::new may not be able to allocate the requested number of cells
             if new_capacity != capacity {
-                let new_array = Array::<K, V>::new(new_capacity, Atomic::from(current_array));
+                let new_array = Array::<K, V>::new(
+                    new_capacity,
+                    current_array_ref.cache_hash(),
+                    current_array_ref.track_deadline(),
+                    Atomic::from(current_array),
+                );
                 if (!shrink && new_array.capacity() > capacity)
                     || (shrink && new_array.capacity() == new_capacity)
                 {
@@ -876,6 +2067,13 @@ impl<K: Eq + Hash + Sync, V: Sync, H: BuildHasher> HashMap<K, V, H> {
     }
 }
 
+/// Expired key-value pairs erased by the HashMap are notified as removals.
+impl<K: Eq + Hash + Sync, V: Sync, H: BuildHasher> ExpiryListener<K, V> for HashMap<K, V, H> {
+    fn on_expire(&self, key: &K, value: &V) {
+        self.notify(key, Some(value), None);
+    }
+}
+
 impl<K: Eq + Hash + Sync, V: Sync, H: BuildHasher> Drop for HashMap<K, V, H> {
     fn drop(&mut self) {
         self.clear();
@@ -886,7 +2084,8 @@ impl<K: Eq + Hash + Sync, V: Sync, H: BuildHasher> Drop for HashMap<K, V, H> {
 ///
 /// It is !Send, thus disallowing other threads to have references to it.
 /// It acquires an exclusive lock on the cell managing the key.
-/// Instantiating multiple Accessor of Scanner instances in a thread poses a possibility of deadlock.
+/// Instantiating multiple Accessor of Scanner instances in a thread poses a possibility of deadlock, refer to
+/// HashMap::with_deadlock_detection.
 pub struct Accessor<'a, K: Eq + Hash + Sync, V: Sync, H: BuildHasher> {
     hash_map: &'a HashMap<K, V, H>,
     cell_locker: CellLocker<'a, K, V>,
@@ -894,11 +2093,16 @@ pub struct Accessor<'a, K: Eq + Hash + Sync, V: Sync, H: BuildHasher> {
     sub_index: u8,
     entry_array_link_ptr: *const EntryArrayLink<K, V>,
     entry_ptr: *const (K, V),
+    /// Whether a mutable reference to the value has been handed out since the last notification.
+    value_borrowed: std::cell::Cell<bool>,
 }
 
 impl<'a, K: Eq + Hash + Sync, V: Sync, H: BuildHasher> Accessor<'a, K, V, H> {
     /// Returns a reference to the key-value pair.
     ///
+    /// The ChangeListener and the Subscriptions are notified of an update of the value when the Accessor is dropped,
+    /// and the notification carries the value at the time as both the old and the new value.
+    ///
     /// # Examples
     /// ```
     /// use scc::HashMap;
@@ -919,6 +2123,8 @@ impl<'a, K: Eq + Hash + Sync, V: Sync, H: BuildHasher> Accessor<'a, K, V, H> {
     /// assert_eq!(result.unwrap().get(), (&1, &mut 2));
     /// ```
     pub fn get(&'a self) -> (&'a K, &'a mut V) {
+        self.cell_locker.mark_modified();
+        self.value_borrowed.set(true);
         unsafe {
             let key_ptr = &(*self.entry_ptr).0 as *const K;
             let value_ptr = &(*self.entry_ptr).1 as *const V;
@@ -952,6 +2158,234 @@ impl<'a, K: Eq + Hash + Sync, V: Sync, H: BuildHasher> Accessor<'a, K, V, H> {
         self.hash_map.erase(self);
         true
     }
+
+    /// Notifies the ChangeListener of an update of the value if a mutable reference to it has been handed out.
+    fn notify_borrowed(&self) {
+        if self.value_borrowed.replace(false) {
+            let (key, value) = unsafe { &*self.entry_ptr };
+            self.hash_map.notify(key, Some(value), Some(value));
+        }
+    }
+}
+
+impl<'a, K: Eq + Hash + Sync, V: Sync, H: BuildHasher> Drop for Accessor<'a, K, V, H> {
+    fn drop(&mut self) {
+        self.notify_borrowed();
+    }
+}
+
+/// MultiAccessor owns the cells managing a set of keys in the HashMap.
+///
+/// It is !Send, thus disallowing other threads to have references to it.
+/// It acquires exclusive locks on the cells managing the keys given to HashMap::lock_many.
+/// Instantiating an Accessor or Scanner while holding a MultiAccessor in a thread poses a possibility of deadlock.
+pub struct MultiAccessor<'a, K: Eq + Hash + Sync, V: Sync, H: BuildHasher> {
+    hash_map: &'a HashMap<K, V, H>,
+    array_ptr: *const Array<K, V>,
+    /// The locked cells sorted by their indexes.
+    cell_lockers: Vec<(usize, CellLocker<'a, K, V>)>,
+    /// The key-value pairs of which the values have been mutably borrowed.
+    borrowed: Vec<*const (K, V)>,
+}
+
+impl<'a, K: Eq + Hash + Sync, V: Sync, H: BuildHasher> MultiAccessor<'a, K, V, H> {
+    /// Returns a reference to the value of the key.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the key was not given to lock_many.
+    ///
+    /// # Examples
+    /// ```
+    /// use scc::HashMap;
+    /// use std::collections::hash_map::RandomState;
+    ///
+    /// let hashmap: HashMap<u64, u32, RandomState> = HashMap::new(RandomState::new(), None);
+    ///
+    /// assert!(hashmap.insert(1, 0).is_ok());
+    ///
+    /// let multi_accessor = hashmap.lock_many(&[1, 2]);
+    /// assert_eq!(multi_accessor.read(&1), Some(&0));
+    /// assert!(multi_accessor.read(&2).is_none());
+    /// ```
+    pub fn read(&self, key: &K) -> Option<&V> {
+        let (_, partial_hash, position) = self.locate(key);
+        self.cell_lockers[position]
+            .1
+            .search(partial_hash, |k| k == key)
+            .map(|(_, _, entry_ptr)| unsafe { &(*entry_ptr).1 })
+    }
+
+    /// Returns a mutable reference to the value of the key.
+    ///
+    /// The ChangeListener and the Subscriptions are notified of an update of the value when the MultiAccessor is
+    /// dropped.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the key was not given to lock_many.
+    ///
+    /// # Examples
+    /// ```
+    /// use scc::HashMap;
+    /// use std::collections::hash_map::RandomState;
+    ///
+    /// let hashmap: HashMap<u64, u32, RandomState> = HashMap::new(RandomState::new(), None);
+    ///
+    /// assert!(hashmap.insert(1, 1).is_ok());
+    /// assert!(hashmap.insert(2, 0).is_ok());
+    ///
+    /// let mut multi_accessor = hashmap.lock_many(&[1, 2]);
+    /// *multi_accessor.get_mut(&1).unwrap() -= 1;
+    /// *multi_accessor.get_mut(&2).unwrap() += 1;
+    /// drop(multi_accessor);
+    ///
+    /// assert_eq!(hashmap.read(1, |_, value| *value), Some(0));
+    /// assert_eq!(hashmap.read(2, |_, value| *value), Some(1));
+    /// ```
+    pub fn get_mut(&mut self, key: &K) -> Option<&mut V> {
+        let (_, partial_hash, position) = self.locate(key);
+        let cell_locker = &self.cell_lockers[position].1;
+        let borrowed = &mut self.borrowed;
+        cell_locker
+            .search(partial_hash, |k| k == key)
+            .map(|(_, _, entry_ptr)| {
+                cell_locker.mark_modified();
+                if !borrowed.contains(&entry_ptr) {
+                    borrowed.push(entry_ptr);
+                }
+                unsafe { &mut (*(entry_ptr as *mut (K, V))).1 }
+            })
+    }
+
+    /// Inserts a key-value pair.
+    ///
+    /// It returns the given key-value pair if the key exists.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the key was not given to lock_many.
+    ///
+    /// # Examples
+    /// ```
+    /// use scc::HashMap;
+    /// use std::collections::hash_map::RandomState;
+    ///
+    /// let hashmap: HashMap<u64, u32, RandomState> = HashMap::new(RandomState::new(), None);
+    ///
+    /// let mut multi_accessor = hashmap.lock_many(&[1]);
+    /// assert!(multi_accessor.insert(1, 0).is_ok());
+    /// assert_eq!(multi_accessor.insert(1, 1), Err((1, 1)));
+    /// ```
+    pub fn insert(&mut self, key: K, value: V) -> Result<(), (K, V)> {
+        let (hash, partial_hash, position) = self.locate(&key);
+        let (cell_index, cell_locker) = &mut self.cell_lockers[position];
+        if cell_locker.search(partial_hash, |k| *k == key).is_some() {
+            return Err((key, value));
+        }
+
+        // expired key-value pairs must be erased before the key is inserted
+        cell_locker.purge_expired();
+        let (sub_index, entry_array_link_ptr, entry_ptr) =
+            cell_locker.insert(key, partial_hash, value);
+        let (key, value) = unsafe { &*entry_ptr };
+        self.hash_map.notify(key, None, Some(value));
+
+        // the array is not dropped as long as the cell is locked
+        let array_ref = unsafe { &(*self.array_ptr) };
+        array_ref.store_hash(
+            *cell_index,
+            sub_index,
+            entry_array_link_ptr,
+            entry_ptr,
+            hash,
+        );
+        array_ref.store_deadline(*cell_index, sub_index, entry_array_link_ptr, entry_ptr, 0);
+        Ok(())
+    }
+
+    /// Removes the key, and returns the key-value pair.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the key was not given to lock_many.
+    ///
+    /// # Examples
+    /// ```
+    /// use scc::HashMap;
+    /// use std::collections::hash_map::RandomState;
+    ///
+    /// let hashmap: HashMap<u64, u32, RandomState> = HashMap::new(RandomState::new(), None);
+    ///
+    /// assert!(hashmap.insert(1, 0).is_ok());
+    ///
+    /// let mut multi_accessor = hashmap.lock_many(&[1]);
+    /// assert_eq!(multi_accessor.remove(&1), Some((1, 0)));
+    /// assert!(multi_accessor.remove(&1).is_none());
+    /// ```
+    pub fn remove(&mut self, key: &K) -> Option<(K, V)> {
+        let (_, partial_hash, position) = self.locate(key);
+        let hash_map = self.hash_map;
+        let cell_locker = &mut self.cell_lockers[position].1;
+        let borrowed = &mut self.borrowed;
+        cell_locker.search(partial_hash, |k| k == key).map(
+            |(sub_index, entry_array_link_ptr, entry_ptr)| {
+                borrowed.retain(|borrowed_ptr| *borrowed_ptr != entry_ptr);
+                let (key, value) = unsafe { &*entry_ptr };
+                hash_map.notify(key, Some(value), None);
+
+                // the entry has to be moved out before the linked entry array is deallocated
+                let entry = unsafe { std::ptr::read(entry_ptr) };
+                cell_locker.remove(false, sub_index, entry_array_link_ptr, entry_ptr);
+                entry
+            },
+        )
+    }
+
+    /// Inserts or updates a key-value pair, and notifies the ChangeListener of the modification.
+    pub(crate) fn upsert(&mut self, key: K, value: V) {
+        let (_, partial_hash, position) = self.locate(&key);
+        let cell_locker = &self.cell_lockers[position].1;
+        if let Some((_, _, entry_ptr)) = cell_locker.search(partial_hash, |k| *k == key) {
+            cell_locker.mark_modified();
+            let pair_mut_ptr = entry_ptr as *mut (K, V);
+            unsafe {
+                self.hash_map
+                    .notify(&(*pair_mut_ptr).0, Some(&(*pair_mut_ptr).1), Some(&value));
+                (*pair_mut_ptr).1 = value;
+            }
+        } else {
+            // the key does not exist, therefore the insertion succeeds
+            let _ = self.insert(key, value);
+        }
+    }
+
+    /// Returns the current version of the locked cell owning the key.
+    pub(crate) fn cell_version(&self, key: &K) -> CellVersion<K, V> {
+        let (_, _, position) = self.locate(key);
+        let (cell_index, cell_locker) = &self.cell_lockers[position];
+        (self.array_ptr, *cell_index, cell_locker.version())
+    }
+
+    /// Returns the hash value, partial hash value, and the position of the locked cell of the key.
+    fn locate(&self, key: &K) -> (u64, u16, usize) {
+        let (hash, partial_hash) = self.hash_map.hash(key);
+        let cell_index = unsafe { (*self.array_ptr).calculate_cell_index(hash) };
+        let position = self
+            .cell_lockers
+            .binary_search_by_key(&cell_index, |(index, _)| *index)
+            .expect("the key was not given to lock_many");
+        (hash, partial_hash, position)
+    }
+}
+
+impl<'a, K: Eq + Hash + Sync, V: Sync, H: BuildHasher> Drop for MultiAccessor<'a, K, V, H> {
+    fn drop(&mut self) {
+        for entry_ptr in self.borrowed.drain(..) {
+            let (key, value) = unsafe { &*entry_ptr };
+            self.hash_map.notify(key, Some(value), Some(value));
+        }
+    }
 }
 
 /// Scanner implements Iterator.
@@ -965,6 +2399,7 @@ pub struct Scanner<'a, K: Eq + Hash + Sync, V: Sync, H: BuildHasher> {
     cell_index: usize,
     activated: bool,
     erase_on_next: bool,
+    read_only: bool,
 }
 
 impl<'a, K: Eq + Hash + Sync, V: Sync, H: BuildHasher> Iterator for Scanner<'a, K, V, H> {
@@ -976,6 +2411,13 @@ impl<'a, K: Eq + Hash + Sync, V: Sync, H: BuildHasher> Iterator for Scanner<'a,
             let erase = self.erase_on_next;
             if erase {
                 self.erase_on_next = false;
+                if let Some(accessor) = self.accessor.as_ref() {
+                    accessor.value_borrowed.set(false);
+                    let (key, value) = unsafe { &*accessor.entry_ptr };
+                    accessor.hash_map.notify(key, Some(value), None);
+                }
+            } else if let Some(accessor) = self.accessor.as_ref() {
+                accessor.notify_borrowed();
             }
             if let Some((next_sub_index, next_entry_array_link_ptr, next_entry_ptr)) =
                 self.accessor.as_mut().map_or_else(
@@ -1019,6 +2461,10 @@ impl<'a, K: Eq + Hash + Sync, V: Sync, H: BuildHasher> Iterator for Scanner<'a,
             }
         }
         if let Some(accessor) = &self.accessor {
+            if !self.read_only {
+                accessor.cell_locker.mark_modified();
+                accessor.value_borrowed.set(true);
+            }
             unsafe {
                 let key_ptr = &(*accessor.entry_ptr).0 as *const K;
                 let value_ptr = &(*accessor.entry_ptr).1 as *const V;
@@ -1030,7 +2476,95 @@ impl<'a, K: Eq + Hash + Sync, V: Sync, H: BuildHasher> Iterator for Scanner<'a,
     }
 }
 
+/// RawEntry locates a key-value pair without materializing the key.
+///
+/// The hash value passed to a RawEntry must be the one that the BuildHasher of the HashMap
+/// produces for the key, and the predicate must only match the key.
+pub struct RawEntry<'a, K: Eq + Hash + Sync, V: Sync, H: BuildHasher> {
+    hash_map: &'a HashMap<K, V, H>,
+}
+
+impl<'a, K: Eq + Hash + Sync, V: Sync, H: BuildHasher> RawEntry<'a, K, V, H> {
+    /// Gets an Accessor to the key-value pair of which the key satisfies the predicate.
+    ///
+    /// # Examples
+    /// ```
+    /// use scc::HashMap;
+    /// use std::collections::hash_map::RandomState;
+    /// use std::hash::{BuildHasher, Hash, Hasher};
+    ///
+    /// let build_hasher = RandomState::new();
+    /// let hashmap: HashMap<(u64, u64), u32, RandomState> = HashMap::new(build_hasher.clone(), None);
+    ///
+    /// let result = hashmap.insert((1, 2), 0);
+    /// if let Ok(result) = result {
+    ///     assert_eq!(result.get(), (&(1, 2), &mut 0));
+    /// }
+    ///
+    /// let mut hasher = build_hasher.build_hasher();
+    /// (1u64, 2u64).hash(&mut hasher);
+    /// let hash = hasher.finish();
+    ///
+    /// let result = hashmap.raw_entry().from_hash(hash, |key| key.0 == 1 && key.1 == 2);
+    /// if let Some(result) = result {
+    ///     (*result.get().1) = 1;
+    /// }
+    ///
+    /// let result = hashmap.raw_entry().from_hash(hash, |key| key.0 == 1 && key.1 == 3);
+    /// assert!(result.is_none());
+    ///
+    /// let result = hashmap.get((1, 2));
+    /// assert_eq!(result.unwrap().get(), (&(1, 2), &mut 1));
+    /// ```
+    pub fn from_hash<F: FnMut(&K) -> bool>(
+        self,
+        hash: u64,
+        is_match: F,
+    ) -> Option<Accessor<'a, K, V, H>> {
+        let (hash, partial_hash) = HashMap::<K, V, H>::mix(hash);
+        let (accessor, _, _) = self.hash_map.acquire(is_match, hash, partial_hash);
+        if accessor.entry_ptr.is_null() {
+            return None;
+        }
+        Some(accessor)
+    }
+
+    /// Reads the key-value pair of which the key satisfies the predicate.
+    ///
+    /// # Examples
+    /// ```
+    /// use scc::HashMap;
+    /// use std::collections::hash_map::RandomState;
+    /// use std::hash::{BuildHasher, Hash, Hasher};
+    ///
+    /// let build_hasher = RandomState::new();
+    /// let hashmap: HashMap<String, u32, RandomState> = HashMap::new(build_hasher.clone(), None);
+    ///
+    /// let result = hashmap.insert(String::from("one"), 1);
+    /// if let Ok(result) = result {
+    ///     assert_eq!(result.get(), (&String::from("one"), &mut 1));
+    /// }
+    ///
+    /// let mut hasher = build_hasher.build_hasher();
+    /// "one".hash(&mut hasher);
+    /// let hash = hasher.finish();
+    ///
+    /// let result = hashmap.raw_entry().read_from_hash(hash, |key| key == "one", |key, value| *value);
+    /// assert_eq!(result.unwrap(), 1);
+    /// ```
+    pub fn read_from_hash<U, M: FnMut(&K) -> bool, F: FnOnce(&K, &V) -> U>(
+        self,
+        hash: u64,
+        is_match: M,
+        f: F,
+    ) -> Option<U> {
+        let (hash, partial_hash) = HashMap::<K, V, H>::mix(hash);
+        self.hash_map.read_entry(is_match, hash, partial_hash, f)
+    }
+}
+
 /// Statistics
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct Statistics {
     capacity: usize,
     effective_capacity: usize,
@@ -1042,6 +2576,9 @@ pub struct Statistics {
     linked_entries: usize,
     cells_having_link: usize,
     max_link_length: usize,
+    occupancy_histogram: [usize; cell::ARRAY_SIZE as usize + 1],
+    link_length_histogram: Vec<usize>,
+    partial_hash_collisions: usize,
 }
 
 impl Statistics {
@@ -1075,13 +2612,25 @@ impl Statistics {
     pub fn max_link_length(&self) -> usize {
         self.max_link_length
     }
+    /// Returns the number of cells indexed by the number of entries in the cell array, 0..=16.
+    pub fn occupancy_histogram(&self) -> &[usize] {
+        &self.occupancy_histogram
+    }
+    /// Returns the number of cells indexed by the number of linked entries.
+    pub fn link_length_histogram(&self) -> &[usize] {
+        &self.link_length_histogram
+    }
+    /// Returns the number of entries sharing a partial hash value with another entry in the same cell.
+    pub fn num_partial_hash_collisions(&self) -> usize {
+        self.partial_hash_collisions
+    }
 }
 
 impl fmt::Display for Statistics {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
         write!(
             f,
-            "capacity: {}, effective_capacity: {}, cells: {}, killed_entries: {}, empty_cells: {}, max_consecutive_empty_cells: {}, entries: {}, linked_entries: {}, cells_having_link: {}, max_link_length: {}",
+            "capacity: {}, effective_capacity: {}, cells: {}, killed_entries: {}, empty_cells: {}, max_consecutive_empty_cells: {}, entries: {}, linked_entries: {}, cells_having_link: {}, max_link_length: {}, occupancy_histogram: {:?}, link_length_histogram: {:?}, partial_hash_collisions: {}",
             self.capacity,
             self.effective_capacity,
             self.cells,
@@ -1091,7 +2640,86 @@ impl fmt::Display for Statistics {
             self.entries,
             self.linked_entries,
             self.cells_having_link,
-            self.max_link_length
+            self.max_link_length,
+            self.occupancy_histogram,
+            self.link_length_histogram,
+            self.partial_hash_collisions
+        )
+    }
+}
+
+/// HashDiagnostics describes how well the hasher of a HashMap distributes a sample of keys.
+pub struct HashDiagnostics {
+    keys: usize,
+    cells: usize,
+    occupied_cells: usize,
+    max_cell_load: usize,
+    partial_hash_collisions: usize,
+    linked_entries: usize,
+    max_link_arrays: usize,
+}
+
+impl HashDiagnostics {
+    pub fn num_keys(&self) -> usize {
+        self.keys
+    }
+    pub fn num_cells(&self) -> usize {
+        self.cells
+    }
+    pub fn num_occupied_cells(&self) -> usize {
+        self.occupied_cells
+    }
+    pub fn max_cell_load(&self) -> usize {
+        self.max_cell_load
+    }
+    /// Returns the ratio of the maximum number of keys in a cell to the expected number of keys in a cell.
+    ///
+    /// A uniform hasher yields a value close to 1.0 when the expected number of keys in a cell is large.
+    pub fn cell_index_skew(&self) -> f64 {
+        if self.keys == 0 {
+            return 0.0;
+        }
+        self.max_cell_load as f64 / (self.keys as f64 / self.cells as f64)
+    }
+    pub fn num_partial_hash_collisions(&self) -> usize {
+        self.partial_hash_collisions
+    }
+    /// Returns the ratio of keys sharing a partial hash value with another key in the same cell.
+    ///
+    /// Partial hash values differing only in the least significant bit are regarded as equal since linked entries do
+    /// not retain the bit.
+    pub fn partial_hash_collision_rate(&self) -> f64 {
+        if self.keys == 0 {
+            return 0.0;
+        }
+        self.partial_hash_collisions as f64 / self.keys as f64
+    }
+    /// Returns the predicted number of entries stored in linked entry arrays.
+    pub fn predicted_linked_entries(&self) -> usize {
+        self.linked_entries
+    }
+    /// Returns the predicted maximum number of linked entry arrays in a cell.
+    ///
+    /// Unlike Statistics::max_link_length that counts linked entries, it counts entry arrays.
+    pub fn predicted_max_link_arrays(&self) -> usize {
+        self.max_link_arrays
+    }
+}
+
+impl fmt::Display for HashDiagnostics {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "keys: {}, cells: {}, occupied_cells: {}, max_cell_load: {}, cell_index_skew: {:.3}, partial_hash_collisions: {}, partial_hash_collision_rate: {:.6}, predicted_linked_entries: {}, predicted_max_link_arrays: {}",
+            self.keys,
+            self.cells,
+            self.occupied_cells,
+            self.max_cell_load,
+            self.cell_index_skew(),
+            self.partial_hash_collisions,
+            self.partial_hash_collision_rate(),
+            self.linked_entries,
+            self.max_link_arrays
         )
     }
 }
@@ -1,25 +1,37 @@
 extern crate libc;
 
-use super::cell::{Cell, CellLocker, EntryArray, ARRAY_SIZE};
+use super::cell::{Cell, CellLocker, DeadlineArray, EntryArray, ARRAY_SIZE};
+use super::link::EntryArrayLink;
 use crossbeam_epoch::{Atomic, Guard, Shared};
 use std::convert::TryInto;
 use std::mem::MaybeUninit;
-use std::sync::atomic::AtomicUsize;
+use std::ops::Range;
+use std::ptr;
 use std::sync::atomic::Ordering::{Acquire, Relaxed, Release};
+use std::sync::atomic::{AtomicU64, AtomicUsize};
 
 pub const MAX_ENLARGE_FACTOR: u8 = 6;
 
+/// HashArray stores the hash values of the key-value pairs in an EntryArray.
+pub type HashArray = [AtomicU64; ARRAY_SIZE as usize];
+
 pub struct Array<K: Eq, V> {
     cell_array: Option<Box<Cell<K, V>>>,
     entry_array: Option<Box<EntryArray<K, V>>>,
+    hash_array: Option<Box<HashArray>>,
+    deadline_array: Option<Box<DeadlineArray>>,
     lb_capacity: u8,
-    rehashing: AtomicUsize,
-    rehashed: AtomicUsize,
+    rehashing: Rehashing,
     old_array: Atomic<Array<K, V>>,
 }
 
 impl<K: Eq, V> Array<K, V> {
-    pub fn new(capacity: usize, old_array: Atomic<Array<K, V>>) -> Array<K, V> {
+    pub fn new(
+        capacity: usize,
+        cache_hash: bool,
+        track_deadline: bool,
+        old_array: Atomic<Array<K, V>>,
+    ) -> Array<K, V> {
         let mut lb_capacity = Self::calculate_lb_metadata_array_size(capacity);
         loop {
             let cell_capacity = 1usize << lb_capacity;
@@ -64,12 +76,67 @@ impl<K: Eq, V> Array<K, V> {
                 None
             };
 
+            // calloc zeroes the allocated heap memory region
+            let hash_array_ptr: *mut HashArray = if cache_hash {
+                unsafe {
+                    libc::calloc(cell_capacity, std::mem::size_of::<HashArray>()) as *mut HashArray
+                }
+            } else {
+                ptr::null_mut()
+            };
+
+            // memory allocation failure: retry with a smaller capacity value
+            if cache_hash && hash_array_ptr.is_null() {
+                unsafe { libc::free(entry_array_ptr as *mut libc::c_void) };
+                unsafe { libc::free(cell_array_ptr as *mut libc::c_void) };
+                if lb_capacity > 2 {
+                    lb_capacity -= 1;
+                }
+                continue;
+            }
+
+            let hash_array_boxed = if !hash_array_ptr.is_null() {
+                Some(unsafe { Box::from_raw(hash_array_ptr) })
+            } else {
+                None
+            };
+
+            // calloc zeroes the allocated heap memory region
+            let deadline_array_ptr: *mut DeadlineArray = if track_deadline {
+                unsafe {
+                    libc::calloc(cell_capacity, std::mem::size_of::<DeadlineArray>())
+                        as *mut DeadlineArray
+                }
+            } else {
+                ptr::null_mut()
+            };
+
+            // memory allocation failure: retry with a smaller capacity value
+            if track_deadline && deadline_array_ptr.is_null() {
+                if let Some(hash_array_box) = hash_array_boxed {
+                    unsafe { libc::free(Box::into_raw(hash_array_box) as *mut libc::c_void) };
+                }
+                unsafe { libc::free(entry_array_ptr as *mut libc::c_void) };
+                unsafe { libc::free(cell_array_ptr as *mut libc::c_void) };
+                if lb_capacity > 2 {
+                    lb_capacity -= 1;
+                }
+                continue;
+            }
+
+            let deadline_array_boxed = if !deadline_array_ptr.is_null() {
+                Some(unsafe { Box::from_raw(deadline_array_ptr) })
+            } else {
+                None
+            };
+
             return Array {
                 cell_array: cell_array_boxed,
                 entry_array: entry_array_boxed,
+                hash_array: hash_array_boxed,
+                deadline_array: deadline_array_boxed,
                 lb_capacity: lb_capacity,
-                rehashing: AtomicUsize::new(0),
-                rehashed: AtomicUsize::new(0),
+                rehashing: Rehashing::new(),
                 old_array: old_array,
             };
         }
@@ -85,6 +152,111 @@ impl<K: Eq, V> Array<K, V> {
         unsafe { &(*(array_ptr.add(index))) }
     }
 
+    pub fn hash_array(&self, index: usize) -> Option<&HashArray> {
+        self.hash_array.as_ref().map(|hash_array| {
+            let array_ptr = &(**hash_array) as *const HashArray;
+            unsafe { &(*(array_ptr.add(index))) }
+        })
+    }
+
+    pub fn cache_hash(&self) -> bool {
+        self.hash_array.is_some()
+    }
+
+    /// Loads the cached hash value of a key-value pair.
+    ///
+    /// The cell must be locked, and it returns None if hash values are not cached.
+    pub fn load_hash(
+        &self,
+        index: usize,
+        sub_index: u8,
+        entry_array_link_ptr: *const EntryArrayLink<K, V>,
+        entry_ptr: *const (K, V),
+    ) -> Option<u64> {
+        if sub_index != u8::MAX {
+            self.hash_array(index)
+                .map(|hash_array| hash_array[sub_index as usize].load(Relaxed))
+        } else if self.cache_hash() {
+            Some(unsafe { (*entry_array_link_ptr).hash(entry_ptr) })
+        } else {
+            None
+        }
+    }
+
+    /// Stores the hash value of a key-value pair if hash values are cached.
+    ///
+    /// The cell must be locked.
+    pub fn store_hash(
+        &self,
+        index: usize,
+        sub_index: u8,
+        entry_array_link_ptr: *const EntryArrayLink<K, V>,
+        entry_ptr: *const (K, V),
+        hash: u64,
+    ) {
+        if sub_index != u8::MAX {
+            if let Some(hash_array) = self.hash_array(index) {
+                hash_array[sub_index as usize].store(hash, Relaxed);
+            }
+        } else if self.cache_hash() {
+            let entry_array_link_mut_ptr = entry_array_link_ptr as *mut EntryArrayLink<K, V>;
+            unsafe { (*entry_array_link_mut_ptr).set_hash(entry_ptr, hash) };
+        }
+    }
+
+    pub fn deadline_array(&self, index: usize) -> Option<&DeadlineArray> {
+        self.deadline_array.as_ref().map(|deadline_array| {
+            let array_ptr = &(**deadline_array) as *const DeadlineArray;
+            unsafe { &(*(array_ptr.add(index))) }
+        })
+    }
+
+    pub fn track_deadline(&self) -> bool {
+        self.deadline_array.is_some()
+    }
+
+    /// Loads the deadline of a key-value pair.
+    ///
+    /// The cell must be locked.
+    pub fn load_deadline(
+        &self,
+        index: usize,
+        sub_index: u8,
+        entry_array_link_ptr: *const EntryArrayLink<K, V>,
+        entry_ptr: *const (K, V),
+    ) -> u64 {
+        if sub_index != u8::MAX {
+            self.deadline_array(index).map_or(0, |deadline_array| {
+                deadline_array[sub_index as usize].load(Relaxed)
+            })
+        } else if self.track_deadline() {
+            unsafe { (*entry_array_link_ptr).deadline(entry_ptr) }
+        } else {
+            0
+        }
+    }
+
+    /// Stores the deadline of a key-value pair.
+    ///
+    /// The cell must be locked.
+    pub fn store_deadline(
+        &self,
+        index: usize,
+        sub_index: u8,
+        entry_array_link_ptr: *const EntryArrayLink<K, V>,
+        entry_ptr: *const (K, V),
+        deadline: u64,
+    ) {
+        if sub_index != u8::MAX {
+            if let Some(deadline_array) = self.deadline_array(index) {
+                deadline_array[sub_index as usize].store(deadline, Relaxed);
+            }
+        } else if self.track_deadline() {
+            let entry_array_link_mut_ptr = entry_array_link_ptr as *mut EntryArrayLink<K, V>;
+            unsafe { (*entry_array_link_mut_ptr).set_deadline(entry_ptr, deadline) };
+        }
+    }
+
     pub fn num_cells(&self) -> usize {
         1usize << self.lb_capacity
     }
@@ -98,7 +270,11 @@ impl<K: Eq, V> Array<K, V> {
     }
 
     pub fn calculate_cell_index(&self, hash: u64) -> usize {
-        (hash >> (64 - self.lb_capacity)).try_into().unwrap()
+        Self::cell_index(hash, self.lb_capacity)
+    }
+
+    pub fn cell_index(hash: u64, lb_capacity: u8) -> usize {
+        (hash >> (64 - lb_capacity)).try_into().unwrap()
     }
 
     pub fn calculate_lb_metadata_array_size(capacity: usize) -> u8 {
@@ -117,6 +293,49 @@ impl<K: Eq, V> Array<K, V> {
         lb_capacity.try_into().unwrap()
     }
 
+    /// Calculates the capacity of a new array according to the resizing policy.
+    ///
+    /// The resizing policies are as follows.
+    ///  - load factor reaches 7/8: enlarge up to 64x
+    ///  - load factor reaches 1/8: shrink
+    pub fn calculate_new_capacity(
+        capacity: usize,
+        estimated_num_entries: usize,
+        minimum_capacity: usize,
+    ) -> usize {
+        if estimated_num_entries >= (capacity / 8) * 7 {
+            if capacity >= (1usize << (std::mem::size_of::<usize>() * 8 - 1)) {
+                capacity
+            } else {
+                (capacity.min(
+                    1usize
+                        << (std::mem::size_of::<usize>() * 8 - (MAX_ENLARGE_FACTOR as usize + 1)),
+                ) * (1 << MAX_ENLARGE_FACTOR as usize))
+                    .min(estimated_num_entries.next_power_of_two() * 2)
+            }
+        } else if estimated_num_entries <= capacity / 8 {
+            estimated_num_entries
+                .next_power_of_two()
+                .max(minimum_capacity)
+        } else {
+            capacity
+        }
+    }
+
+    /// Returns the index of the first cell receiving the entries of the old cell, and the number of such cells.
+    pub fn relocation_target(
+        old_lb_capacity: u8,
+        lb_capacity: u8,
+        old_cell_index: usize,
+    ) -> (usize, usize) {
+        if old_lb_capacity > lb_capacity {
+            (old_cell_index >> (old_lb_capacity - lb_capacity), 1)
+        } else {
+            let ratio = 1usize << (lb_capacity - old_lb_capacity);
+            (old_cell_index * ratio, ratio)
+        }
+    }
+
     pub fn kill_cell<F: Fn(&K) -> (u64, u16)>(
         &self,
         cell_locker: &mut CellLocker<K, V>,
@@ -131,18 +350,9 @@ impl<K: Eq, V> Array<K, V> {
             return;
         }
 
-        let shrink = old_array.lb_capacity > self.lb_capacity;
-        let ratio = if shrink {
-            1usize << (old_array.lb_capacity - self.lb_capacity)
-        } else {
-            1usize << (self.lb_capacity - old_array.lb_capacity)
-        };
-        let target_cell_index = if shrink {
-            old_cell_index / ratio
-        } else {
-            old_cell_index * ratio
-        };
-        debug_assert!(ratio <= (1 << MAX_ENLARGE_FACTOR as usize));
+        let (target_cell_index, num_target_cells) =
+            Self::relocation_target(old_array.lb_capacity, self.lb_capacity, old_cell_index);
+        debug_assert!(num_target_cells <= (1 << MAX_ENLARGE_FACTOR as usize));
 
         let mut target_cells: [Option<CellLocker<K, V>>; 1 << MAX_ENLARGE_FACTOR as usize] = [
             None, None, None, None, None, None, None, None, None, None, None, None, None, None,
@@ -153,15 +363,24 @@ impl<K: Eq, V> Array<K, V> {
         ];
         let mut current = cell_locker.first();
         while let Some((sub_index, entry_array_link_ptr, entry_ptr)) = current {
+            let deadline =
+                old_array.load_deadline(old_cell_index, sub_index, entry_array_link_ptr, entry_ptr);
+            let cached_hash =
+                old_array.load_hash(old_cell_index, sub_index, entry_array_link_ptr, entry_ptr);
             let entry_mut_ptr = entry_ptr as *mut MaybeUninit<(K, V)>;
             let entry = unsafe { std::ptr::replace(entry_mut_ptr, MaybeUninit::uninit()) };
             let (key, value) = unsafe { entry.assume_init() };
-            let (hash, partial_hash) = hasher(&key);
+
+            // cached hash values spare the cost of hashing the key
+            let (hash, partial_hash) = match cached_hash {
+                Some(hash) => (hash, (hash & ((1 << 16) - 1)) as u16),
+                None => hasher(&key),
+            };
             let new_cell_index = self.calculate_cell_index(hash);
 
             debug_assert!(
-                (!shrink && (new_cell_index - target_cell_index) < ratio)
-                    || (shrink && new_cell_index == old_cell_index / ratio)
+                new_cell_index >= target_cell_index
+                    && new_cell_index - target_cell_index < num_target_cells
             );
 
             for i in 0..=(new_cell_index - target_cell_index) {
@@ -172,9 +391,24 @@ impl<K: Eq, V> Array<K, V> {
                     ));
                 }
             }
-            target_cells[new_cell_index - target_cell_index]
-                .as_mut()
-                .map(|cell_locker| cell_locker.insert(key, partial_hash, value));
+            if let Some(cell_locker) = target_cells[new_cell_index - target_cell_index].as_mut() {
+                let (new_sub_index, new_entry_array_link_ptr, new_entry_ptr) =
+                    cell_locker.insert(key, partial_hash, value);
+                self.store_hash(
+                    new_cell_index,
+                    new_sub_index,
+                    new_entry_array_link_ptr,
+                    new_entry_ptr,
+                    hash,
+                );
+                self.store_deadline(
+                    new_cell_index,
+                    new_sub_index,
+                    new_entry_array_link_ptr,
+                    new_entry_ptr,
+                    deadline,
+                );
+            }
 
             current = cell_locker.next(true, false, sub_index, entry_array_link_ptr, entry_ptr);
         }
@@ -189,23 +423,12 @@ impl<K: Eq, V> Array<K, V> {
 
         let old_array_ref = unsafe { old_array.deref() };
         let old_array_size = old_array_ref.num_cells();
-        let mut current = self.rehashing.load(Relaxed);
-        loop {
-            if current >= old_array_size {
-                return false;
-            }
-            match self.rehashing.compare_exchange(
-                current,
-                current + ARRAY_SIZE as usize,
-                Acquire,
-                Relaxed,
-            ) {
-                Ok(_) => break,
-                Err(result) => current = result,
-            }
-        }
-
-        for old_cell_index in current..(current + ARRAY_SIZE as usize).min(old_array_size) {
+        let old_cell_range = if let Some(old_cell_range) = self.rehashing.claim(old_array_size) {
+            old_cell_range
+        } else {
+            return false;
+        };
+        for old_cell_index in old_cell_range {
             let old_cell_array_ptr =
                 &(**old_array_ref.cell_array.as_ref().unwrap()) as *const Cell<K, V>;
             let old_cell_ref = unsafe { &(*(old_cell_array_ptr.add(old_cell_index))) };
@@ -219,8 +442,7 @@ impl<K: Eq, V> Array<K, V> {
             self.kill_cell(&mut old_cell, old_array_ref, old_cell_index, &hasher);
         }
 
-        let completed = self.rehashed.fetch_add(ARRAY_SIZE as usize, Release) + ARRAY_SIZE as usize;
-        if old_array_size <= completed {
+        if self.rehashing.complete(old_array_size) {
             let old_array = self.old_array.swap(Shared::null(), Relaxed, guard);
             if !old_array.is_null() {
                 unsafe { guard.defer_destroy(old_array) };
@@ -238,6 +460,14 @@ impl<K: Eq, V> Drop for Array<K, V> {
             let entry_array_ptr = Box::into_raw(entry_array_box);
             unsafe { libc::free(entry_array_ptr as *mut libc::c_void) };
         });
+        if let Some(hash_array_box) = self.hash_array.take() {
+            let hash_array_ptr = Box::into_raw(hash_array_box);
+            unsafe { libc::free(hash_array_ptr as *mut libc::c_void) };
+        }
+        if let Some(deadline_array_box) = self.deadline_array.take() {
+            let deadline_array_ptr = Box::into_raw(deadline_array_box);
+            unsafe { libc::free(deadline_array_ptr as *mut libc::c_void) };
+        }
         let cell_array = self.cell_array.take();
         cell_array.map(|cell_array_box| {
             let cell_array_ptr = Box::into_raw(cell_array_box);
@@ -246,6 +476,50 @@ impl<K: Eq, V> Drop for Array<K, V> {
     }
 }
 
+/// Rehashing distributes the cells of an old array among the threads relocating them.
+pub struct Rehashing {
+    /// The number of cells claimed by relocating threads.
+    rehashing: AtomicUsize,
+    /// The number of cells relocated.
+    rehashed: AtomicUsize,
+}
+
+impl Rehashing {
+    pub fn new() -> Rehashing {
+        Rehashing {
+            rehashing: AtomicUsize::new(0),
+            rehashed: AtomicUsize::new(0),
+        }
+    }
+
+    /// Claims the next ARRAY_SIZE cells to relocate.
+    ///
+    /// It returns None if all the cells have been claimed.
+    pub fn claim(&self, old_array_size: usize) -> Option<Range<usize>> {
+        let mut current = self.rehashing.load(Relaxed);
+        loop {
+            if current >= old_array_size {
+                return None;
+            }
+            match self.rehashing.compare_exchange(
+                current,
+                current + ARRAY_SIZE as usize,
+                Acquire,
+                Relaxed,
+            ) {
+                Ok(_) => return Some(current..(current + ARRAY_SIZE as usize).min(old_array_size)),
+                Err(result) => current = result,
+            }
+        }
+    }
+
+    /// Marks the claimed cells relocated, and returns true if all the cells have been relocated.
+    pub fn complete(&self, old_array_size: usize) -> bool {
+        let completed = self.rehashed.fetch_add(ARRAY_SIZE as usize, Release) + ARRAY_SIZE as usize;
+        old_array_size <= completed
+    }
+}
+
 #[cfg(test)]
 mod test {
     use super::*;
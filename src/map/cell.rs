@@ -1,10 +1,13 @@
+use super::super::lock_table::Deadlock;
 use super::link::{EntryArrayLink, LinkType};
+use super::wait_for_graph::WaitForGraph;
 use std::convert::TryInto;
 use std::mem::MaybeUninit;
 use std::ptr;
 use std::sync::atomic::Ordering::{Acquire, Relaxed, Release};
-use std::sync::atomic::{AtomicPtr, AtomicU32};
-use std::sync::{Condvar, Mutex};
+use std::sync::atomic::{AtomicBool, AtomicPtr, AtomicU16, AtomicU32, AtomicU64, AtomicUsize};
+use std::sync::{Arc, Condvar, Mutex};
+use std::time::Duration;
 
 pub const ARRAY_SIZE: u8 = 16;
 const KILLED_FLAG: u32 = 1u32 << 31;
@@ -18,12 +21,80 @@ const OCCUPANCY_BIT: u32 = 1;
 
 pub type EntryArray<K, V> = [MaybeUninit<(K, V)>; ARRAY_SIZE as usize];
 
+/// DeadlineArray stores the deadlines of the key-value pairs in an EntryArray.
+///
+/// Zero represents that the corresponding key-value pair never expires.
+pub type DeadlineArray = [AtomicU64; ARRAY_SIZE as usize];
+
+/// Expiry consists of the deadlines of the key-value pairs in a cell and the current time.
+#[derive(Clone, Copy)]
+pub struct Expiry<'a> {
+    deadline_array: &'a DeadlineArray,
+    now: u64,
+}
+
+impl<'a> Expiry<'a> {
+    pub fn new(deadline_array: &'a DeadlineArray, now: u64) -> Expiry<'a> {
+        Expiry {
+            deadline_array,
+            now,
+        }
+    }
+
+    fn expired(&self, sub_index: u8) -> bool {
+        let deadline = self.deadline_array[sub_index as usize].load(Relaxed);
+        deadline != 0 && deadline <= self.now
+    }
+}
+
+/// ExpiryListener is notified of the expired key-value pairs that a CellLocker erases.
+pub trait ExpiryListener<K, V> {
+    fn on_expire(&self, key: &K, value: &V);
+}
+
+/// WeighFn calculates the weight of a key-value pair.
+pub type WeighFn<K, V> = dyn Fn(&K, &V) -> usize + Send + Sync;
+
+/// Weigher consists of the cost function of key-value pairs and the total weight of a container.
+pub struct Weigher<'a, K, V> {
+    weigh: &'a WeighFn<K, V>,
+    total: &'a AtomicUsize,
+}
+
+impl<'a, K, V> Weigher<'a, K, V> {
+    pub fn new(weigh: &'a WeighFn<K, V>, total: &'a AtomicUsize) -> Weigher<'a, K, V> {
+        Weigher { weigh, total }
+    }
+
+    fn add(&self, key: &K, value: &V) {
+        self.total.fetch_add((self.weigh)(key, value), Relaxed);
+    }
+
+    fn subtract(&self, key: &K, value: &V) {
+        self.total.fetch_sub((self.weigh)(key, value), Relaxed);
+    }
+}
+
+impl<'a, K, V> Clone for Weigher<'a, K, V> {
+    fn clone(&self) -> Self {
+        *self
+    }
+}
+
+impl<'a, K, V> Copy for Weigher<'a, K, V> {}
+
 pub struct Cell<K: Eq, V> {
     partial_hash_array: [u16; ARRAY_SIZE as usize],
     metadata: AtomicU32,
+    /// The reference bits of the key-value pairs in the cell array for the CLOCK eviction policy.
+    reference_bits: AtomicU16,
+    /// The position in the cell array where the next CLOCK eviction starts.
+    clock_hand: u8,
     wait_queue: AtomicPtr<WaitQueueEntry>,
     link: LinkType<K, V>,
-    linked_entries: usize,
+    linked_entries: u32,
+    /// The version of the cell that is incremented whenever the exclusive lock on a modified cell is released.
+    version: u32,
 }
 
 impl<K: Eq, V> Cell<K, V> {
@@ -34,21 +105,59 @@ impl<K: Eq, V> Cell<K, V> {
     pub fn size(&self) -> (usize, usize) {
         (
             (self.metadata.load(Relaxed) & OCCUPANCY_MASK).count_ones() as usize,
-            self.linked_entries,
+            self.linked_entries as usize,
         )
     }
 
+    /// Returns the address of the cell that identifies it in a WaitForGraph.
+    fn address(&self) -> usize {
+        self as *const Cell<K, V> as usize
+    }
+
     fn wait<T, F: FnOnce() -> Option<T>>(&self, f: F) -> Option<T> {
-        // insert the condvar into the wait queue
-        let mut condvar = WaitQueueEntry::new(self.wait_queue.load(Relaxed));
-        let condvar_ptr: *mut WaitQueueEntry = &mut condvar;
+        let condvar = WaitQueueEntry::new(ptr::null_mut());
+        let locked = self.enqueue(&condvar as *const WaitQueueEntry as *mut WaitQueueEntry, f);
+        condvar.wait();
+        locked
+    }
+
+    /// Waits like wait while the WaitForGraph records the thread as waiting for the cell.
+    ///
+    /// The wait queue entry is shared with the wait queue, therefore a thread woken up with a Deadlock error leaves
+    /// without waiting for the cell to be released.
+    fn wait_tracked<T, F: FnOnce() -> Option<T>>(
+        &self,
+        f: F,
+        wait_for_graph: &WaitForGraph,
+        abortable: bool,
+    ) -> Result<Option<T>, Deadlock> {
+        let condvar = Arc::new(WaitQueueEntry::shared());
+        // the wait queue owns a reference that is released when the entry is signaled
+        let locked = self.enqueue(Arc::into_raw(condvar.clone()) as *mut WaitQueueEntry, f);
+        if locked.is_some() {
+            return Ok(locked);
+        }
+        wait_for_graph.wait(self.address(), condvar, abortable)?;
+        Ok(None)
+    }
+
+    /// Inserts the condvar into the wait queue, and tries to lock the cell again.
+    fn enqueue<T, F: FnOnce() -> Option<T>>(
+        &self,
+        condvar_ptr: *mut WaitQueueEntry,
+        f: F,
+    ) -> Option<T> {
+        let condvar = unsafe { &*condvar_ptr };
+        condvar.next.store(self.wait_queue.load(Relaxed), Relaxed);
 
         // insert itself into the wait queue
-        while let Err(result) =
-            self.wait_queue
-                .compare_exchange(condvar.next, condvar_ptr, Release, Relaxed)
-        {
-            condvar.next = result;
+        while let Err(result) = self.wait_queue.compare_exchange(
+            condvar.next.load(Relaxed),
+            condvar_ptr,
+            Release,
+            Relaxed,
+        ) {
+            condvar.next.store(result, Relaxed);
         }
 
         // 'Relaxed' is sufficient, because this thread reading the flag state as 'set'
@@ -69,7 +178,6 @@ impl<K: Eq, V> Cell<K, V> {
         if locked.is_some() {
             self.wakeup();
         }
-        condvar.wait();
         locked
     }
 
@@ -86,30 +194,38 @@ impl<K: Eq, V> Cell<K, V> {
         }
 
         while condvar_ptr != ptr::null_mut() {
-            let next_ptr = unsafe { (*condvar_ptr).next };
+            let next_ptr = unsafe { (*condvar_ptr).next.load(Relaxed) };
+            let shared = unsafe { (*condvar_ptr).shared };
             unsafe {
                 (*condvar_ptr).signal();
             };
+            if shared {
+                // releases the reference owned by the wait queue
+                drop(unsafe { Arc::from_raw(condvar_ptr as *const WaitQueueEntry) });
+            }
             condvar_ptr = next_ptr;
         }
     }
 
-    fn search(
+    fn search<F: FnMut(&K) -> bool>(
         &self,
         metadata: u32,
-        key: &K,
         partial_hash: u16,
+        mut is_match: F,
         entry_array: &EntryArray<K, V>,
+        expiry: Option<Expiry>,
     ) -> Option<(u8, *const EntryArrayLink<K, V>, *const (K, V))> {
         let occupancy_metadata = metadata & OCCUPANCY_MASK;
+        let expired = |index: u8| matches!(expiry, Some(expiry) if expiry.expired(index));
 
         // start with the preferred index
         let preferred_index = (partial_hash % (ARRAY_SIZE as u16)).try_into().unwrap();
         if (occupancy_metadata & (OCCUPANCY_BIT << preferred_index)) != 0
             && self.partial_hash_array[preferred_index as usize] == partial_hash
+            && !expired(preferred_index)
         {
             let entry_ptr = entry_array[preferred_index as usize].as_ptr();
-            if unsafe { &(*entry_ptr) }.0 == *key {
+            if is_match(&unsafe { &(*entry_ptr) }.0) {
                 return Some((preferred_index, ptr::null(), entry_ptr));
             }
         }
@@ -124,9 +240,10 @@ impl<K: Eq, V> Cell<K, V> {
             if i != preferred_index
                 && (metadata & occupancy_bit) != 0
                 && self.partial_hash_array[i as usize] == partial_hash
+                && !expired(i)
             {
                 let entry_ptr = entry_array[i as usize].as_ptr();
-                if unsafe { &(*entry_ptr) }.0 == *key {
+                if is_match(&unsafe { &(*entry_ptr) }.0) {
                     return Some((i, ptr::null(), entry_ptr));
                 }
             }
@@ -135,7 +252,9 @@ impl<K: Eq, V> Cell<K, V> {
         // traverse the link
         let mut link_ref = &self.link;
         while let Some(link) = link_ref.as_ref() {
-            if let Some(result) = link.search_entry(key, partial_hash) {
+            if let Some(result) =
+                link.search_entry(partial_hash, &mut is_match, expiry.map(|expiry| expiry.now))
+            {
                 return Some((u8::MAX, result.0, result.1));
             }
             link_ref = &link.link_ref();
@@ -149,10 +268,13 @@ impl<K: Eq, V> Default for Cell<K, V> {
     fn default() -> Self {
         Cell {
             metadata: AtomicU32::new(0),
+            reference_bits: AtomicU16::new(0),
+            clock_hand: 0,
             wait_queue: AtomicPtr::new(ptr::null_mut()),
             partial_hash_array: [0; ARRAY_SIZE as usize],
             link: None,
             linked_entries: 0,
+            version: 0,
         }
     }
 }
@@ -162,6 +284,13 @@ pub struct CellLocker<'a, K: Eq, V> {
     cell: &'a Cell<K, V>,
     entry_array: &'a EntryArray<K, V>,
     metadata: u32,
+    expiry: Option<Expiry<'a>>,
+    expiry_listener: Option<&'a dyn ExpiryListener<K, V>>,
+    weigher: Option<Weigher<'a, K, V>>,
+    /// Whether key-value pairs in the cell have been inserted, removed, or mutably accessed.
+    modified: AtomicBool,
+    /// The WaitForGraph recording the thread as the holder of the cell.
+    wait_for_graph: Option<&'a WaitForGraph>,
 }
 
 impl<'a, K: Eq, V> CellLocker<'a, K, V> {
@@ -177,6 +306,34 @@ impl<'a, K: Eq, V> CellLocker<'a, K, V> {
         }
     }
 
+    /// Create a new CellLocker instance with the cell exclusively locked, and record the thread as the holder of the
+    /// cell in the WaitForGraph.
+    ///
+    /// The WaitForGraph records the thread as waiting for the cell while it waits, and it returns Deadlock if the
+    /// thread is abortable and chosen as the victim of a deadlock.
+    pub fn lock_tracked(
+        cell: &'a Cell<K, V>,
+        entry_array: &'a EntryArray<K, V>,
+        wait_for_graph: &'a WaitForGraph,
+        abortable: bool,
+    ) -> Result<CellLocker<'a, K, V>, Deadlock> {
+        let mut locker = loop {
+            if let Some(result) = Self::try_lock(cell, entry_array) {
+                break result;
+            }
+            if let Some(result) = cell.wait_tracked(
+                || Self::try_lock(cell, entry_array),
+                wait_for_graph,
+                abortable,
+            )? {
+                break result;
+            }
+        };
+        wait_for_graph.hold(cell.address());
+        locker.wait_for_graph = Some(wait_for_graph);
+        Ok(locker)
+    }
+
     /// Create a new CellLocker instance if the cell is exclusively locked.
     fn try_lock(
         cell: &'a Cell<K, V>,
@@ -196,6 +353,11 @@ impl<'a, K: Eq, V> CellLocker<'a, K, V> {
                         cell: cell,
                         entry_array: entry_array,
                         metadata: result | XLOCK,
+                        expiry: None,
+                        expiry_listener: None,
+                        weigher: None,
+                        modified: AtomicBool::new(false),
+                        wait_for_graph: None,
                     });
                 }
                 Err(result) => {
@@ -208,6 +370,34 @@ impl<'a, K: Eq, V> CellLocker<'a, K, V> {
         }
     }
 
+    /// Makes the CellLocker regard key-value pairs of which the deadline has passed as vacant.
+    pub fn set_expiry(&mut self, expiry: Option<Expiry<'a>>) {
+        self.expiry = expiry;
+    }
+
+    pub fn expiry(&self) -> Option<Expiry<'a>> {
+        self.expiry
+    }
+
+    /// Makes the CellLocker notify the listener of the expired key-value pairs before erasing them.
+    pub fn set_expiry_listener(&mut self, expiry_listener: Option<&'a dyn ExpiryListener<K, V>>) {
+        self.expiry_listener = expiry_listener;
+    }
+
+    /// Makes the CellLocker keep the total weight up to date when inserting or removing key-value pairs.
+    ///
+    /// It is only used by HashCache, since values in a HashMap can be modified through mutable references.
+    pub fn set_weigher(&mut self, weigher: Option<Weigher<'a, K, V>>) {
+        self.weigher = weigher;
+    }
+
+    /// Makes the CellLocker increment the version of the cell when it is dropped.
+    ///
+    /// It has to be called before a mutable reference to a value in the cell is handed out.
+    pub fn mark_modified(&self) {
+        self.modified.store(true, Relaxed);
+    }
+
     pub fn occupied(&self, index: u8) -> bool {
         (self.metadata & (OCCUPANCY_BIT << index)) != 0
     }
@@ -216,6 +406,7 @@ impl<'a, K: Eq, V> CellLocker<'a, K, V> {
         (self.metadata & OCCUPANCY_MASK) == OCCUPANCY_MASK
     }
 
+    /// Returns the next key-value pair, and erases expired key-value pairs on the way.
     pub fn next(
         &mut self,
         erase_current: bool,
@@ -223,6 +414,31 @@ impl<'a, K: Eq, V> CellLocker<'a, K, V> {
         sub_index: u8,
         entry_array_link_ptr: *const EntryArrayLink<K, V>,
         entry_ptr: *const (K, V),
+    ) -> Option<(u8, *const EntryArrayLink<K, V>, *const (K, V))> {
+        let mut current = self.advance(
+            erase_current,
+            drop_entry,
+            sub_index,
+            entry_array_link_ptr,
+            entry_ptr,
+        );
+        while let Some((sub_index, entry_array_link_ptr, entry_ptr)) = current {
+            if !self.expired(sub_index, entry_array_link_ptr, entry_ptr) {
+                break;
+            }
+            self.expire(entry_ptr);
+            current = self.advance(true, true, sub_index, entry_array_link_ptr, entry_ptr);
+        }
+        current
+    }
+
+    fn advance(
+        &mut self,
+        erase_current: bool,
+        drop_entry: bool,
+        sub_index: u8,
+        entry_array_link_ptr: *const EntryArrayLink<K, V>,
+        entry_ptr: *const (K, V),
     ) -> Option<(u8, *const EntryArrayLink<K, V>, *const (K, V))> {
         if !entry_array_link_ptr.is_null() {
             // traverse the link
@@ -258,13 +474,18 @@ impl<'a, K: Eq, V> CellLocker<'a, K, V> {
         None
     }
 
-    pub fn search(
+    pub fn search<F: FnMut(&K) -> bool>(
         &self,
-        key: &K,
         partial_hash: u16,
+        is_match: F,
     ) -> Option<(u8, *const EntryArrayLink<K, V>, *const (K, V))> {
-        self.cell
-            .search(self.metadata, key, partial_hash, self.entry_array)
+        self.cell.search(
+            self.metadata,
+            partial_hash,
+            is_match,
+            self.entry_array,
+            self.expiry,
+        )
     }
 
     pub fn insert(
@@ -273,6 +494,10 @@ impl<'a, K: Eq, V> CellLocker<'a, K, V> {
         partial_hash: u16,
         value: V,
     ) -> (u8, *const EntryArrayLink<K, V>, *const (K, V)) {
+        *self.modified.get_mut() = true;
+        if let Some(weigher) = self.weigher.as_ref() {
+            weigher.add(&key, &value);
+        }
         let preferred_index = (partial_hash % (ARRAY_SIZE as u16)).try_into().unwrap();
         if !self.occupied(preferred_index) {
             self.metadata = self.metadata | (OCCUPANCY_BIT << preferred_index);
@@ -337,8 +562,20 @@ impl<'a, K: Eq, V> CellLocker<'a, K, V> {
         entry_array_link_ptr: *const EntryArrayLink<K, V>,
         key_value_pair_ptr: *const (K, V),
     ) {
+        *self.modified.get_mut() = true;
+        if let Some(weigher) = self.weigher.as_ref() {
+            let entry_ref = if sub_index == u8::MAX {
+                unsafe { &(*key_value_pair_ptr) }
+            } else {
+                unsafe { &(*self.entry_array[sub_index as usize].as_ptr()) }
+            };
+            weigher.subtract(&entry_ref.0, &entry_ref.1);
+        }
         if sub_index != u8::MAX {
             self.metadata = self.metadata & (!(OCCUPANCY_BIT << sub_index));
+            self.cell
+                .reference_bits
+                .fetch_and(!(1u16 << sub_index), Relaxed);
             if drop_entry {
                 unsafe {
                     std::ptr::drop_in_place(
@@ -370,7 +607,62 @@ impl<'a, K: Eq, V> CellLocker<'a, K, V> {
         }
     }
 
-    pub fn first(&self) -> Option<(u8, *const EntryArrayLink<K, V>, *const (K, V))> {
+    /// Evicts a key-value pair in the cell array according to the CLOCK policy.
+    ///
+    /// Key-value pairs that have been referenced since the last sweep are given a second chance.
+    pub fn evict(&mut self) -> Option<(K, V)> {
+        if (self.metadata & OCCUPANCY_MASK) == 0 {
+            return None;
+        }
+        let mut clock_hand = self.cell.clock_hand;
+        loop {
+            let sub_index = clock_hand % ARRAY_SIZE;
+            clock_hand = (sub_index + 1) % ARRAY_SIZE;
+            if !self.occupied(sub_index) {
+                continue;
+            }
+            let reference_bit = 1u16 << sub_index;
+            if self.cell.reference_bits.fetch_and(!reference_bit, Relaxed) & reference_bit != 0 {
+                continue;
+            }
+            self.cell_mut_ref().clock_hand = clock_hand;
+            self.remove(false, sub_index, ptr::null(), ptr::null());
+            return Some(unsafe { self.entry_array[sub_index as usize].as_ptr().read() });
+        }
+    }
+
+    /// Returns the first key-value pair, and erases expired key-value pairs on the way.
+    pub fn first(&mut self) -> Option<(u8, *const EntryArrayLink<K, V>, *const (K, V))> {
+        match self.first_entry() {
+            Some((sub_index, entry_array_link_ptr, entry_ptr))
+                if self.expired(sub_index, entry_array_link_ptr, entry_ptr) =>
+            {
+                self.expire(entry_ptr);
+                self.next(true, true, sub_index, entry_array_link_ptr, entry_ptr)
+            }
+            first => first,
+        }
+    }
+
+    /// Erases all the expired key-value pairs in the cell, and returns the number of erased key-value pairs.
+    pub fn purge_expired(&mut self) -> usize {
+        if self.expiry.is_none() {
+            return 0;
+        }
+        let num_entries = self.num_entries();
+        let mut current = self.first();
+        while let Some((sub_index, entry_array_link_ptr, entry_ptr)) = current {
+            current = self.next(false, false, sub_index, entry_array_link_ptr, entry_ptr);
+        }
+        // only expired key-value pairs are erased on the way
+        num_entries - self.num_entries()
+    }
+
+    fn num_entries(&self) -> usize {
+        (self.metadata & OCCUPANCY_MASK).count_ones() as usize + self.cell.linked_entries as usize
+    }
+
+    fn first_entry(&self) -> Option<(u8, *const EntryArrayLink<K, V>, *const (K, V))> {
         self.cell.link.as_ref().map_or_else(
             || {
                 let start_index: u8 = self.metadata.trailing_zeros().try_into().unwrap();
@@ -403,6 +695,31 @@ impl<'a, K: Eq, V> CellLocker<'a, K, V> {
         self.metadata & KILLED_FLAG == KILLED_FLAG
     }
 
+    pub fn version(&self) -> u32 {
+        self.cell.version
+    }
+
+    fn expired(
+        &self,
+        sub_index: u8,
+        entry_array_link_ptr: *const EntryArrayLink<K, V>,
+        entry_ptr: *const (K, V),
+    ) -> bool {
+        match self.expiry {
+            Some(expiry) if sub_index != u8::MAX => expiry.expired(sub_index),
+            Some(expiry) => unsafe { (*entry_array_link_ptr).expired(entry_ptr, expiry.now) },
+            None => false,
+        }
+    }
+
+    /// Notifies the listener of the expired key-value pair about to be erased.
+    fn expire(&self, entry_ptr: *const (K, V)) {
+        if let Some(expiry_listener) = self.expiry_listener {
+            let (key, value) = unsafe { &*entry_ptr };
+            expiry_listener.on_expire(key, value);
+        }
+    }
+
     fn cell_mut_ref(&mut self) -> &mut Cell<K, V> {
         let cell_ptr = self.cell as *const Cell<K, V>;
         let cell_mut_ptr = cell_ptr as *mut Cell<K, V>;
@@ -418,6 +735,17 @@ impl<'a, K: Eq, V> CellLocker<'a, K, V> {
 
 impl<'a, K: Eq, V> Drop for CellLocker<'a, K, V> {
     fn drop(&mut self) {
+        if let Some(wait_for_graph) = self.wait_for_graph {
+            // the record is erased before another thread acquires the lock and records itself
+            wait_for_graph.release(self.cell.address());
+        }
+
+        if *self.modified.get_mut() {
+            // versions of unmodified cells are kept intact so that readers do not observe false conflicts
+            let cell = self.cell_mut_ref();
+            cell.version = cell.version.wrapping_add(1);
+        }
+
         // a Release fence is required to publish the changes
         let mut current = self.cell.metadata.load(Relaxed);
         loop {
@@ -445,6 +773,7 @@ pub struct CellReader<'a, K: Eq, V> {
     cell: &'a Cell<K, V>,
     entry_array: &'a EntryArray<K, V>,
     metadata: u32,
+    expiry: Option<Expiry<'a>>,
 }
 
 impl<'a, K: Eq, V> CellReader<'a, K, V> {
@@ -460,6 +789,28 @@ impl<'a, K: Eq, V> CellReader<'a, K, V> {
         }
     }
 
+    /// Create a new CellReader instance with the cell shared locked while the WaitForGraph records the thread as
+    /// waiting for the cell.
+    ///
+    /// Shared locks are not recorded in the WaitForGraph, since a reader does not wait for another cell.
+    pub fn lock_tracked(
+        cell: &'a Cell<K, V>,
+        entry_array: &'a EntryArray<K, V>,
+        wait_for_graph: &WaitForGraph,
+    ) -> CellReader<'a, K, V> {
+        loop {
+            if let Some(result) = Self::try_lock(cell, entry_array) {
+                return result;
+            }
+            // a thread that is not abortable is never woken up with a Deadlock error
+            if let Ok(Some(result)) =
+                cell.wait_tracked(|| Self::try_lock(cell, entry_array), wait_for_graph, false)
+            {
+                return result;
+            }
+        }
+    }
+
     /// Create a new CellReader instance if the cell is shared locked.
     fn try_lock(
         cell: &'a Cell<K, V>,
@@ -481,6 +832,7 @@ impl<'a, K: Eq, V> CellReader<'a, K, V> {
                         cell: cell,
                         entry_array: entry_array,
                         metadata: result,
+                        expiry: None,
                     })
                 }
                 Err(result) => {
@@ -493,12 +845,115 @@ impl<'a, K: Eq, V> CellReader<'a, K, V> {
         }
     }
 
-    pub fn search(&self, key: &K, partial_hash: u16) -> Option<*const (K, V)> {
+    pub fn search<F: FnMut(&K) -> bool>(
+        &self,
+        partial_hash: u16,
+        is_match: F,
+    ) -> Option<*const (K, V)> {
         self.cell
-            .search(self.metadata, key, partial_hash, self.entry_array)
+            .search(
+                self.metadata,
+                partial_hash,
+                is_match,
+                self.entry_array,
+                self.expiry,
+            )
             .as_ref()
             .map(|result| result.2)
     }
+
+    /// Makes the CellReader regard key-value pairs of which the deadline has passed as vacant.
+    pub fn set_expiry(&mut self, expiry: Option<Expiry<'a>>) {
+        self.expiry = expiry;
+    }
+
+    pub fn killed(&self) -> bool {
+        self.metadata & KILLED_FLAG == KILLED_FLAG
+    }
+
+    /// Returns the version of the cell, which does not change while the CellReader is held.
+    pub fn version(&self) -> u32 {
+        self.cell.version
+    }
+
+    /// Searches for a key-value pair, and sets the reference bit of it if found in the cell array.
+    pub fn search_and_reference<F: FnMut(&K) -> bool>(
+        &self,
+        partial_hash: u16,
+        is_match: F,
+    ) -> Option<*const (K, V)> {
+        self.cell
+            .search(
+                self.metadata,
+                partial_hash,
+                is_match,
+                self.entry_array,
+                self.expiry,
+            )
+            .map(|(sub_index, _, entry_ptr)| {
+                if sub_index != u8::MAX {
+                    let reference_bit = 1u16 << sub_index;
+                    // avoids writing to the cell if the bit is already set
+                    if self.cell.reference_bits.load(Relaxed) & reference_bit == 0 {
+                        self.cell.reference_bits.fetch_or(reference_bit, Relaxed);
+                    }
+                }
+                entry_ptr
+            })
+    }
+
+    /// Returns the number of entries sharing a partial hash value with another entry in the cell.
+    ///
+    /// Linked entries store partial hash values with the least significant bit set, therefore a linked entry is
+    /// compared with the other entries without the least significant bit, and cell array entries are compared
+    /// as they are.
+    pub fn partial_hash_collisions(&self) -> usize {
+        // (partial_hash >> 1, the least significant bit if the entry is in the cell array)
+        let mut partial_hashes = Vec::with_capacity(ARRAY_SIZE as usize);
+        for i in 0..ARRAY_SIZE {
+            if (self.metadata & (OCCUPANCY_BIT << i)) != 0 {
+                let partial_hash = self.cell.partial_hash_array[i as usize];
+                partial_hashes.push((partial_hash >> 1, Some(partial_hash & 1)));
+            }
+        }
+        let mut link_ref = &self.cell.link;
+        while let Some(link) = link_ref.as_ref() {
+            partial_hashes.extend(
+                link.partial_hash_array()
+                    .iter()
+                    .filter(|v| **v != 0)
+                    .map(|v| (*v >> 1, None)),
+            );
+            link_ref = link.link_ref();
+        }
+        partial_hashes.sort_unstable();
+
+        let mut collisions = 0;
+        let mut run_start = 0;
+        while run_start < partial_hashes.len() {
+            let upper_bits = partial_hashes[run_start].0;
+            let run_len = partial_hashes[run_start..]
+                .iter()
+                .take_while(|v| v.0 == upper_bits)
+                .count();
+            let run = &partial_hashes[run_start..run_start + run_len];
+            if run.iter().any(|v| v.1.is_none()) {
+                // a linked entry matches every entry in the run
+                if run_len > 1 {
+                    collisions += run_len;
+                }
+            } else {
+                for bit in 0..2 {
+                    let bit_run_len = run.iter().filter(|v| v.1 == Some(bit)).count();
+                    if bit_run_len > 1 {
+                        collisions += bit_run_len;
+                    }
+                }
+            }
+            run_start += run_len;
+        }
+        collisions
+    }
 }
 
 impl<'a, K: Eq, V> Drop for CellReader<'a, K, V> {
@@ -526,35 +981,61 @@ impl<'a, K: Eq, V> Drop for CellReader<'a, K, V> {
     }
 }
 
-struct WaitQueueEntry {
+/// WaitQueueEntry is a one-shot signal that a waiting thread blocks on.
+pub struct WaitQueueEntry {
     mutex: Mutex<bool>,
     condvar: Condvar,
-    next: *mut WaitQueueEntry,
+    next: AtomicPtr<WaitQueueEntry>,
+    /// Set if the entry is an Arc of which the wait queue owns a reference.
+    shared: bool,
 }
 
 impl WaitQueueEntry {
-    fn new(wait_queue: *mut WaitQueueEntry) -> WaitQueueEntry {
+    pub fn new(wait_queue: *mut WaitQueueEntry) -> WaitQueueEntry {
         WaitQueueEntry {
             mutex: Mutex::new(false),
             condvar: Condvar::new(),
-            next: wait_queue,
+            next: AtomicPtr::new(wait_queue),
+            shared: false,
+        }
+    }
+
+    fn shared() -> WaitQueueEntry {
+        WaitQueueEntry {
+            shared: true,
+            ..WaitQueueEntry::new(ptr::null_mut())
         }
     }
 
-    fn wait(&self) {
+    pub fn wait(&self) {
         let mut completed = self.mutex.lock().unwrap();
         while !*completed {
             completed = self.condvar.wait(completed).unwrap();
         }
     }
 
-    fn signal(&self) {
+    /// Waits for the signal until the timeout elapses, and returns true if signaled.
+    pub fn wait_timeout(&self, timeout: Duration) -> bool {
+        let completed = self.mutex.lock().unwrap();
+        let (completed, _) = self
+            .condvar
+            .wait_timeout_while(completed, timeout, |completed| !*completed)
+            .unwrap();
+        *completed
+    }
+
+    pub fn signal(&self) {
         let mut completed = self.mutex.lock().unwrap();
         *completed = true;
         self.condvar.notify_one();
     }
 }
 
+// the link to the next entry is only accessed by the thread that inserts the entry into a wait queue or the thread
+// that takes the whole wait queue, and the rest of the state is protected by the mutex
+unsafe impl Send for WaitQueueEntry {}
+unsafe impl Sync for WaitQueueEntry {}
+
 #[cfg(test)]
 mod test {
     use super::*;
@@ -632,7 +1113,7 @@ mod test {
             let mut xlocker = CellLocker::lock(&*cell, &*entry_array);
             let result = xlocker.first();
             assert!(result.is_some());
-            let result = xlocker.search(&tid, tid.try_into().unwrap());
+            let result = xlocker.search(tid.try_into().unwrap(), |key| *key == tid);
             assert!(result.is_some());
             if let Some((sub_index, entry_array_link_ptr, entry_ptr)) = result {
                 assert_eq!(unsafe { *entry_ptr }, (tid.try_into().unwrap(), tid));
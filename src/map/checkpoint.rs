@@ -0,0 +1,413 @@
+use super::array::Array;
+use super::cell::{CellLocker, Expiry};
+use super::HashMap;
+use crossbeam_epoch::{Owned, Shared};
+use std::convert::TryInto;
+use std::hash::{BuildHasher, Hash};
+use std::io::{self, BufReader, Read, Write};
+use std::sync::atomic::AtomicBool;
+use std::sync::atomic::Ordering::{Acquire, Relaxed, Release};
+use std::sync::Mutex;
+use std::thread;
+
+/// The magic number at the beginning of a checkpoint.
+const MAGIC: &[u8; 4] = b"SCCK";
+
+/// The version of the checkpoint format.
+const VERSION: u8 = 1;
+
+/// The minimum number of bytes written to the writer at once.
+const CHUNK_SIZE: usize = 4096;
+
+/// The maximum size of an encoded key-value pair accepted by HashMap::restore.
+const MAX_RECORD_SIZE: u64 = 1 << 30;
+
+/// The maximum capacity that HashMap::restore allocates in advance.
+///
+/// The capacity recorded in a checkpoint is not trusted, and the HashMap grows beyond it as key-value pairs are
+/// inserted.
+const MAX_RESTORED_CAPACITY: usize = 1 << 20;
+
+/// CheckpointState is shared by the thread taking a checkpoint and the threads modifying the HashMap.
+///
+/// The cells of the array are dumped exactly once, either by the checkpoint thread sweeping the array, or by a thread
+/// that is about to modify a cell that has yet to be dumped.
+pub struct CheckpointState<K, V> {
+    /// The address of the array being dumped.
+    array_address: usize,
+    /// dumped[i] is set when the contents of the i-th cell have been encoded.
+    dumped: Vec<AtomicBool>,
+    /// The time when the checkpoint was activated, against which the deadlines of key-value pairs are evaluated.
+    now: Option<u64>,
+    encoder: fn(&K, &V, &mut Vec<u8>),
+    /// The encoded records of the cells preserved by modifying threads, and the number of them.
+    preserved: Mutex<(Vec<u8>, usize)>,
+}
+
+impl<K: Eq, V> CheckpointState<K, V> {
+    /// Preserves the contents of the locked cell if the cell has yet to be dumped.
+    pub fn preserve<'a>(
+        &self,
+        array_ref: &'a Array<K, V>,
+        cell_index: usize,
+        locker: &mut CellLocker<'a, K, V>,
+    ) {
+        if array_ref as *const Array<K, V> as usize != self.array_address
+            || self.dumped[cell_index].swap(true, Relaxed)
+        {
+            return;
+        }
+        let mut preserved = self.preserved.lock().unwrap();
+        let (buffer, num_records) = &mut *preserved;
+        let expiry = locker.expiry();
+        locker.set_expiry(self.expiry(array_ref, cell_index));
+        *num_records += encode_cell(self.encoder, locker, buffer);
+        locker.set_expiry(expiry);
+    }
+
+    /// Returns the deadlines of the key-value pairs in the cell evaluated at the time of the activation.
+    fn expiry<'a>(&self, array_ref: &'a Array<K, V>, cell_index: usize) -> Option<Expiry<'a>> {
+        match (self.now, array_ref.deadline_array(cell_index)) {
+            (Some(now), Some(deadline_array)) => Some(Expiry::new(deadline_array, now)),
+            _ => None,
+        }
+    }
+}
+
+impl<K: Eq + Hash + Sync, V: Sync, H: BuildHasher> HashMap<K, V, H> {
+    /// Writes a consistent snapshot of the HashMap to the writer without blocking other threads.
+    ///
+    /// The checkpoint reflects the state of the HashMap at a single point in time. All the cells are locked at once to
+    /// activate the checkpoint, and then the cells are dumped one after another while other threads keep modifying
+    /// the HashMap; a thread about to modify a cell that has yet to be dumped encodes the contents of the cell before
+    /// the modification. Each key-value pair is encoded by the given encoder, and the snapshot is protected by a
+    /// checksum. It returns the number of key-value pairs written.
+    ///
+    /// Since every cell is locked during the activation, all the other threads accessing the HashMap are briefly
+    /// blocked, and the pause grows with the capacity of the HashMap. Key-value pairs of which the deadline had passed
+    /// at the activation are excluded even if they have yet to be erased, and those expiring afterwards are included.
+    ///
+    /// Resizing is suspended during the checkpoint, and time-to-live and hash caching settings are not recorded.
+    /// Taking a checkpoint while holding an Accessor, Scanner or MultiAccessor in the thread poses a possibility of
+    /// deadlock.
+    ///
+    /// # Errors
+    /// It returns the error returned by the writer, and the HashMap is not affected by the failure.
+    ///
+    /// # Examples
+    /// ```
+    /// use scc::HashMap;
+    /// use std::collections::hash_map::RandomState;
+    ///
+    /// let hashmap: HashMap<u64, u32, RandomState> = HashMap::new(RandomState::new(), None);
+    ///
+    /// assert!(hashmap.insert(1, 0).is_ok());
+    ///
+    /// let mut checkpoint = Vec::new();
+    /// let result = hashmap.checkpoint(&mut checkpoint, |key, value, buffer| {
+    ///     buffer.extend_from_slice(&key.to_le_bytes());
+    ///     buffer.extend_from_slice(&value.to_le_bytes());
+    /// });
+    /// assert_eq!(result.unwrap(), 1);
+    /// ```
+    pub fn checkpoint<W: Write>(
+        &self,
+        mut writer: W,
+        encoder: fn(&K, &V, &mut Vec<u8>),
+    ) -> io::Result<usize> {
+        // the array must not be replaced until the checkpoint is complete
+        while self.resize_mutex.swap(true, Acquire) {
+            thread::yield_now();
+        }
+
+        let guard = crossbeam_epoch::pin();
+        let current_array = self.array.load(Acquire, &guard);
+        let current_array_ref = unsafe { current_array.deref() };
+        while !current_array_ref.partial_rehash(&guard, |key| self.hash(key)) {
+            thread::yield_now();
+        }
+
+        // locking all the cells at once makes a consistent cut
+        let num_cells = current_array_ref.num_cells();
+        let mut lockers = Vec::with_capacity(num_cells);
+        for cell_index in 0..num_cells {
+            lockers.push(CellLocker::lock(
+                current_array_ref.cell(cell_index),
+                current_array_ref.entry_array(cell_index),
+            ));
+        }
+        let state = Owned::new(CheckpointState {
+            array_address: current_array.as_raw() as usize,
+            dumped: (0..num_cells).map(|_| AtomicBool::new(false)).collect(),
+            now: self
+                .clock
+                .as_ref()
+                .map(|clock| clock.now().as_nanos() as u64),
+            encoder,
+            preserved: Mutex::new((Vec::new(), 0)),
+        })
+        .into_shared(&guard);
+        self.checkpoint.store(state, Release);
+        drop(lockers);
+
+        let state_ref = unsafe { state.deref() };
+        let result = self.dump(current_array_ref, state_ref, &mut writer);
+
+        self.checkpoint.store(Shared::null(), Release);
+        unsafe { guard.defer_destroy(state) };
+        self.resize_mutex.store(false, Release);
+        result
+    }
+
+    /// Restores a HashMap from a checkpoint.
+    ///
+    /// Each key-value pair is decoded by the given decoder. The array is allocated in advance according to the
+    /// capacity recorded in the checkpoint up to a fixed limit, and the minimum capacity is not affected by it.
+    ///
+    /// # Errors
+    /// It returns the error returned by the reader, or an error of the InvalidData kind if the checkpoint is
+    /// malformed, an encoded key-value pair is larger than a gigabyte, the checksum does not match, the decoder fails,
+    /// or a key appears more than once.
+    ///
+    /// # Examples
+    /// ```
+    /// use scc::HashMap;
+    /// use std::collections::hash_map::RandomState;
+    /// use std::convert::TryInto;
+    ///
+    /// let hashmap: HashMap<u64, u32, RandomState> = HashMap::new(RandomState::new(), None);
+    ///
+    /// assert!(hashmap.insert(1, 0).is_ok());
+    ///
+    /// let mut checkpoint = Vec::new();
+    /// let result = hashmap.checkpoint(&mut checkpoint, |key, value, buffer| {
+    ///     buffer.extend_from_slice(&key.to_le_bytes());
+    ///     buffer.extend_from_slice(&value.to_le_bytes());
+    /// });
+    /// assert!(result.is_ok());
+    ///
+    /// let restored: HashMap<u64, u32, RandomState> =
+    ///     HashMap::restore(RandomState::new(), checkpoint.as_slice(), |record| {
+    ///         let key = u64::from_le_bytes(record.get(0..8)?.try_into().ok()?);
+    ///         let value = u32::from_le_bytes(record.get(8..12)?.try_into().ok()?);
+    ///         Some((key, value))
+    ///     })
+    ///     .unwrap();
+    /// assert_eq!(restored.read(1, |_, value| *value), Some(0));
+    /// ```
+    pub fn restore<R: Read>(
+        hasher: H,
+        reader: R,
+        decoder: fn(&[u8]) -> Option<(K, V)>,
+    ) -> io::Result<HashMap<K, V, H>> {
+        let mut reader = ChecksumReader {
+            reader: BufReader::new(reader),
+            crc32: Crc32::new(),
+        };
+        let mut magic = [0u8; 4];
+        reader.read_exact(&mut magic)?;
+        if &magic != MAGIC {
+            return Err(invalid_data("not a checkpoint"));
+        }
+        let mut version = [0u8; 1];
+        reader.read_exact(&mut version)?;
+        if version[0] != VERSION {
+            return Err(invalid_data("unsupported checkpoint version"));
+        }
+        let capacity: usize = match reader.read_varint()?.try_into() {
+            Ok(capacity) if usize::is_power_of_two(capacity) => capacity,
+            _ => return Err(invalid_data("invalid capacity")),
+        };
+
+        let hashmap = Self::presized(hasher, capacity.min(MAX_RESTORED_CAPACITY));
+        let mut num_entries = 0u64;
+        let mut record = Vec::new();
+        loop {
+            let len = reader.read_varint()?;
+            if len == 0 {
+                break;
+            } else if len - 1 > MAX_RECORD_SIZE {
+                return Err(invalid_data("record too large"));
+            }
+            reader.read_record(len as usize - 1, &mut record)?;
+            let (key, value) = decoder(&record).ok_or_else(|| invalid_data("undecodable entry"))?;
+            if hashmap.insert(key, value).is_err() {
+                return Err(invalid_data("duplicate key"));
+            }
+            num_entries += 1;
+        }
+        if reader.read_varint()? != num_entries {
+            return Err(invalid_data("number of entries mismatch"));
+        }
+        let expected = reader.crc32.value();
+        let mut checksum = [0u8; 4];
+        reader.reader.read_exact(&mut checksum)?;
+        if u32::from_le_bytes(checksum) != expected {
+            return Err(invalid_data("checksum mismatch"));
+        }
+        Ok(hashmap)
+    }
+
+    /// Dumps the cells that have yet to be dumped, and then the preserved cells.
+    fn dump<W: Write>(
+        &self,
+        array_ref: &Array<K, V>,
+        state_ref: &CheckpointState<K, V>,
+        writer: &mut W,
+    ) -> io::Result<usize> {
+        let mut crc32 = Crc32::new();
+        let mut buffer = Vec::with_capacity(CHUNK_SIZE * 2);
+        buffer.extend_from_slice(MAGIC);
+        buffer.push(VERSION);
+        write_varint(array_ref.capacity() as u64, &mut buffer);
+
+        let mut num_entries = 0;
+        for cell_index in 0..array_ref.num_cells() {
+            let mut locker = CellLocker::lock(
+                array_ref.cell(cell_index),
+                array_ref.entry_array(cell_index),
+            );
+            locker.set_expiry(state_ref.expiry(array_ref, cell_index));
+            if !state_ref.dumped[cell_index].swap(true, Relaxed) {
+                num_entries += encode_cell(state_ref.encoder, &mut locker, &mut buffer);
+            }
+            drop(locker);
+            if buffer.len() >= CHUNK_SIZE {
+                crc32.update(&buffer);
+                writer.write_all(&buffer)?;
+                buffer.clear();
+            }
+        }
+
+        // all the cells are dumped, therefore the preserved records do not grow any further
+        {
+            let preserved = state_ref.preserved.lock().unwrap();
+            buffer.extend_from_slice(&preserved.0);
+            num_entries += preserved.1;
+        }
+        write_varint(0, &mut buffer);
+        write_varint(num_entries as u64, &mut buffer);
+        crc32.update(&buffer);
+        buffer.extend_from_slice(&crc32.value().to_le_bytes());
+        writer.write_all(&buffer)?;
+        writer.flush()?;
+        Ok(num_entries)
+    }
+}
+
+/// Encodes the key-value pairs in the locked cell, and returns the number of them.
+fn encode_cell<K: Eq, V>(
+    encoder: fn(&K, &V, &mut Vec<u8>),
+    locker: &mut CellLocker<K, V>,
+    buffer: &mut Vec<u8>,
+) -> usize {
+    let mut num_records = 0;
+    let mut payload = Vec::new();
+    let mut current = locker.first();
+    while let Some((sub_index, entry_array_link_ptr, entry_ptr)) = current {
+        let (key, value) = unsafe { &*entry_ptr };
+        payload.clear();
+        encoder(key, value, &mut payload);
+        write_varint(payload.len() as u64 + 1, buffer);
+        buffer.extend_from_slice(&payload);
+        num_records += 1;
+        current = locker.next(false, false, sub_index, entry_array_link_ptr, entry_ptr);
+    }
+    num_records
+}
+
+/// Writes an unsigned LEB128 integer.
+fn write_varint(mut value: u64, buffer: &mut Vec<u8>) {
+    while value >= 0x80 {
+        buffer.push((value as u8) | 0x80);
+        value >>= 7;
+    }
+    buffer.push(value as u8);
+}
+
+fn invalid_data(message: &str) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, message)
+}
+
+/// ChecksumReader computes the checksum of the bytes read.
+struct ChecksumReader<R: Read> {
+    reader: R,
+    crc32: Crc32,
+}
+
+impl<R: Read> ChecksumReader<R> {
+    fn read_exact(&mut self, buf: &mut [u8]) -> io::Result<()> {
+        self.reader.read_exact(buf)?;
+        self.crc32.update(buf);
+        Ok(())
+    }
+
+    /// Reads a record of the given length.
+    ///
+    /// The buffer grows as bytes arrive so that a corrupted length does not allocate memory in advance.
+    fn read_record(&mut self, len: usize, record: &mut Vec<u8>) -> io::Result<()> {
+        record.clear();
+        while record.len() < len {
+            let offset = record.len();
+            record.resize(len.min(offset + CHUNK_SIZE), 0);
+            self.read_exact(&mut record[offset..])?;
+        }
+        Ok(())
+    }
+
+    /// Reads an unsigned LEB128 integer.
+    fn read_varint(&mut self) -> io::Result<u64> {
+        let mut value = 0u64;
+        for shift in (0..64).step_by(7) {
+            let mut byte = [0u8; 1];
+            self.read_exact(&mut byte)?;
+            let bits = u64::from(byte[0] & 0x7f);
+            if shift == 63 && bits > 1 {
+                break;
+            }
+            value |= bits << shift;
+            if byte[0] & 0x80 == 0 {
+                return Ok(value);
+            }
+        }
+        Err(invalid_data("invalid integer"))
+    }
+}
+
+/// Crc32 computes the IEEE CRC-32 checksum.
+struct Crc32 {
+    table: [u32; 256],
+    crc: u32,
+}
+
+impl Crc32 {
+    fn new() -> Crc32 {
+        let mut table = [0u32; 256];
+        for (i, entry) in table.iter_mut().enumerate() {
+            let mut crc = i as u32;
+            for _ in 0..8 {
+                crc = if crc & 1 == 1 {
+                    (crc >> 1) ^ 0xedb8_8320
+                } else {
+                    crc >> 1
+                };
+            }
+            *entry = crc;
+        }
+        Crc32 {
+            table,
+            crc: u32::MAX,
+        }
+    }
+
+    fn update(&mut self, bytes: &[u8]) {
+        for byte in bytes {
+            self.crc =
+                (self.crc >> 8) ^ self.table[((self.crc ^ u32::from(*byte)) & 0xff) as usize];
+        }
+    }
+
+    fn value(&self) -> u32 {
+        !self.crc
+    }
+}
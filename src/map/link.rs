@@ -9,6 +9,15 @@ pub struct EntryArrayLink<K: Eq, V> {
     ///
     /// Zero represents a state where the corresponding entry is vacant.
     partial_hash_array: [u16; ARRAY_SIZE],
+    /// The array of deadlines of the entries
+    ///
+    /// Zero represents that the corresponding entry never expires. It is allocated when an entry that expires is
+    /// stored in the link.
+    deadline_array: Option<Box<[u64; ARRAY_SIZE]>>,
+    /// The array of hash values of the entries
+    ///
+    /// It is allocated when a hash value is stored in the link, which only happens if the Array caches hash values.
+    hash_array: Option<Box<[u64; ARRAY_SIZE]>>,
     entry_array: [MaybeUninit<(K, V)>; ARRAY_SIZE],
     link: LinkType<K, V>,
 }
@@ -17,11 +26,17 @@ impl<K: Eq, V> EntryArrayLink<K, V> {
     pub fn new(link: LinkType<K, V>) -> EntryArrayLink<K, V> {
         EntryArrayLink {
             partial_hash_array: [0; ARRAY_SIZE],
+            deadline_array: None,
+            hash_array: None,
             entry_array: unsafe { MaybeUninit::uninit().assume_init() },
             link: link,
         }
     }
 
+    pub fn partial_hash_array(&self) -> &[u16; ARRAY_SIZE] {
+        &self.partial_hash_array
+    }
+
     pub fn link_ref(&self) -> &LinkType<K, V> {
         &self.link
     }
@@ -70,14 +85,16 @@ impl<K: Eq, V> EntryArrayLink<K, V> {
             .map_or_else(|| None, |link| (*link).first_entry())
     }
 
-    pub fn search_entry(
+    /// Searches for an entry that has not expired at the given time.
+    pub fn search_entry<F: FnMut(&K) -> bool>(
         &self,
-        key: &K,
         partial_hash: u16,
+        is_match: &mut F,
+        now: Option<u64>,
     ) -> Option<(*const EntryArrayLink<K, V>, *const (K, V))> {
         for (i, v) in self.partial_hash_array.iter().enumerate() {
-            if *v == (partial_hash | 1) {
-                if unsafe { &(*self.entry_array[i].as_ptr()).0 } == key {
+            if *v == (partial_hash | 1) && !matches!(now, Some(now) if self.expired_at(i, now)) {
+                if is_match(unsafe { &(*self.entry_array[i].as_ptr()).0 }) {
                     return Some((
                         self as *const EntryArrayLink<K, V>,
                         self.entry_array[i].as_ptr(),
@@ -97,6 +114,9 @@ impl<K: Eq, V> EntryArrayLink<K, V> {
         for i in 0..ARRAY_SIZE {
             if self.partial_hash_array[i] == 0 {
                 self.partial_hash_array[i] = partial_hash | 1;
+                if let Some(deadline_array) = self.deadline_array.as_mut() {
+                    deadline_array[i] = 0;
+                }
                 unsafe { self.entry_array[i].as_mut_ptr().write((key, value)) };
                 return Ok((
                     self as *const EntryArrayLink<K, V>,
@@ -130,6 +150,55 @@ impl<K: Eq, V> EntryArrayLink<K, V> {
         vacant
     }
 
+    pub fn deadline(&self, key_value_pair_ptr: *const (K, V)) -> u64 {
+        match (
+            self.deadline_array.as_ref(),
+            self.index_of(key_value_pair_ptr),
+        ) {
+            (Some(deadline_array), Some(index)) => deadline_array[index],
+            _ => 0,
+        }
+    }
+
+    pub fn set_deadline(&mut self, key_value_pair_ptr: *const (K, V), deadline: u64) {
+        if let Some(index) = self.index_of(key_value_pair_ptr) {
+            if let Some(deadline_array) = self.deadline_array.as_mut() {
+                deadline_array[index] = deadline;
+            } else if deadline != 0 {
+                let mut deadline_array = Box::new([0; ARRAY_SIZE]);
+                deadline_array[index] = deadline;
+                self.deadline_array = Some(deadline_array);
+            }
+        }
+    }
+
+    pub fn hash(&self, key_value_pair_ptr: *const (K, V)) -> u64 {
+        match (self.hash_array.as_ref(), self.index_of(key_value_pair_ptr)) {
+            (Some(hash_array), Some(index)) => hash_array[index],
+            _ => 0,
+        }
+    }
+
+    pub fn set_hash(&mut self, key_value_pair_ptr: *const (K, V), hash: u64) {
+        if let Some(index) = self.index_of(key_value_pair_ptr) {
+            self.hash_array
+                .get_or_insert_with(|| Box::new([0; ARRAY_SIZE]))[index] = hash;
+        }
+    }
+
+    pub fn expired(&self, key_value_pair_ptr: *const (K, V), now: u64) -> bool {
+        matches!(self.index_of(key_value_pair_ptr), Some(index) if self.expired_at(index, now))
+    }
+
+    fn expired_at(&self, index: usize, now: u64) -> bool {
+        matches!(self.deadline_array.as_ref(), Some(deadline_array)
+            if deadline_array[index] != 0 && deadline_array[index] <= now)
+    }
+
+    fn index_of(&self, key_value_pair_ptr: *const (K, V)) -> Option<usize> {
+        (0..ARRAY_SIZE).find(|i| self.entry_array[*i].as_ptr() == key_value_pair_ptr)
+    }
+
     pub fn remove_self(
         &mut self,
         entry_array_link_ptr: *const EntryArrayLink<K, V>,
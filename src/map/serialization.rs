@@ -0,0 +1,72 @@
+use super::HashMap;
+use serde::de::{Deserialize, Deserializer, MapAccess, Visitor};
+use serde::ser::{Serialize, SerializeMap, Serializer};
+use std::fmt;
+use std::hash::{BuildHasher, Hash};
+use std::marker::PhantomData;
+
+/// HashMap is serialized as a map.
+///
+/// The key-value pairs are serialized while the cells owning them are locked one after another, therefore
+/// modifications made by other threads during serialization may or may not be reflected, and the length of the map
+/// passed to the serializer may not match the number of serialized key-value pairs.
+impl<K: Eq + Hash + Serialize + Sync, V: Serialize + Sync, H: BuildHasher> Serialize
+    for HashMap<K, V, H>
+{
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let mut map = serializer.serialize_map(Some(self.len(|capacity| capacity)))?;
+        for (key, value) in self.scan() {
+            map.serialize_entry(key, value)?;
+        }
+        map.end()
+    }
+}
+
+/// HashMap is deserialized from a map.
+///
+/// The array is allocated in advance according to the length of the map if known, and the minimum capacity is not
+/// affected by the length.
+impl<'de, K, V, H> Deserialize<'de> for HashMap<K, V, H>
+where
+    K: Deserialize<'de> + Eq + Hash + Sync,
+    V: Deserialize<'de> + Sync,
+    H: BuildHasher + Default,
+{
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        deserializer.deserialize_map(HashMapVisitor {
+            _phantom: PhantomData,
+        })
+    }
+}
+
+struct HashMapVisitor<K, V, H> {
+    _phantom: PhantomData<(K, V, H)>,
+}
+
+impl<'de, K, V, H> Visitor<'de> for HashMapVisitor<K, V, H>
+where
+    K: Deserialize<'de> + Eq + Hash + Sync,
+    V: Deserialize<'de> + Sync,
+    H: BuildHasher + Default,
+{
+    type Value = HashMap<K, V, H>;
+
+    fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        formatter.write_str("a map")
+    }
+
+    fn visit_map<M: MapAccess<'de>>(self, mut access: M) -> Result<Self::Value, M::Error> {
+        // the HashMap is enlarged when the load factor reaches 7/8
+        let capacity = access
+            .size_hint()
+            .map_or(0, |len| len.saturating_add(len / 7 + 1));
+        let hashmap = HashMap::presized(H::default(), capacity);
+        while let Some((key, value)) = access.next_entry()? {
+            if let Err((accessor, value)) = hashmap.insert(key, value) {
+                // the latter value of a duplicate key prevails
+                *accessor.get().1 = value;
+            }
+        }
+        Ok(hashmap)
+    }
+}
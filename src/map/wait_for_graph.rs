@@ -0,0 +1,128 @@
+use super::super::lock_table::Deadlock;
+use super::cell::WaitQueueEntry;
+use std::collections::HashMap;
+use std::sync::atomic::AtomicU64;
+use std::sync::atomic::Ordering::Relaxed;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+/// WaitForGraph records the threads exclusively holding cells and the threads waiting for cells, and detects
+/// deadlocks among them.
+///
+/// A waiting thread is only blocked by the thread exclusively holding the cell it is waiting for, therefore each
+/// thread has at most one outgoing edge, and a cycle is found by following the edges from a waiting thread.
+pub struct WaitForGraph {
+    interval: Duration,
+    state: Mutex<State>,
+}
+
+#[derive(Default)]
+struct State {
+    /// The thread exclusively holding each cell, keyed by the address of the cell.
+    holders: HashMap<usize, u64>,
+    /// The waiting threads.
+    waiters: HashMap<u64, Waiter>,
+}
+
+struct Waiter {
+    /// The address of the cell that the thread is waiting for.
+    cell: usize,
+    /// Whether the thread can be woken up with a Deadlock error.
+    abortable: bool,
+    /// Set when the thread is chosen as the victim of a deadlock.
+    aborted: bool,
+    signal: Arc<WaitQueueEntry>,
+}
+
+impl WaitForGraph {
+    pub fn new(interval: Duration) -> WaitForGraph {
+        WaitForGraph {
+            interval,
+            state: Mutex::new(State::default()),
+        }
+    }
+
+    /// Records the current thread as the exclusive holder of the cell.
+    pub fn hold(&self, cell: usize) {
+        self.state
+            .lock()
+            .unwrap()
+            .holders
+            .insert(cell, current_thread_id());
+    }
+
+    /// Erases the record of the cell; it has to be called before the exclusive lock is released.
+    pub fn release(&self, cell: usize) {
+        self.state.lock().unwrap().holders.remove(&cell);
+    }
+
+    /// Waits for the signal while the current thread is recorded as waiting for the cell.
+    ///
+    /// The thread periodically searches the wait-for graph for a cycle that it belongs to, and the youngest abortable
+    /// thread in the cycle, the one with the largest identifier, is woken up with a Deadlock error.
+    pub fn wait(
+        &self,
+        cell: usize,
+        signal: Arc<WaitQueueEntry>,
+        abortable: bool,
+    ) -> Result<(), Deadlock> {
+        let thread_id = current_thread_id();
+        self.state.lock().unwrap().waiters.insert(
+            thread_id,
+            Waiter {
+                cell,
+                abortable,
+                aborted: false,
+                signal: signal.clone(),
+            },
+        );
+        while !signal.wait_timeout(self.interval) {
+            let mut state = self.state.lock().unwrap();
+            if let Some(victim) = state.detect(thread_id) {
+                if let Some(waiter) = state.waiters.get_mut(&victim) {
+                    waiter.aborted = true;
+                    waiter.signal.signal();
+                }
+            }
+        }
+        let waiter = self.state.lock().unwrap().waiters.remove(&thread_id);
+        if matches!(waiter, Some(Waiter { aborted: true, .. })) {
+            return Err(Deadlock);
+        }
+        Ok(())
+    }
+}
+
+impl State {
+    /// Follows the edges from the thread, and returns the youngest abortable thread if the thread is in a cycle.
+    fn detect(&self, thread_id: u64) -> Option<u64> {
+        let mut cycle = vec![thread_id];
+        let mut current = thread_id;
+        loop {
+            let cell = self.waiters.get(&current)?.cell;
+            let holder = *self.holders.get(&cell)?;
+            if holder == thread_id {
+                break;
+            }
+            if cycle.contains(&holder) {
+                // the threads in the cycle search for it by themselves
+                return None;
+            }
+            cycle.push(holder);
+            current = holder;
+        }
+        cycle
+            .into_iter()
+            .filter(|id| matches!(self.waiters.get(id), Some(waiter) if waiter.abortable))
+            .max()
+    }
+}
+
+/// Returns the identifier of the current thread, which increases in the order the threads first call it.
+fn current_thread_id() -> u64 {
+    static NEXT_THREAD_ID: AtomicU64 = AtomicU64::new(1);
+    thread_local! {
+        static THREAD_ID: u64 = NEXT_THREAD_ID.fetch_add(1, Relaxed);
+    }
+    THREAD_ID.with(|thread_id| *thread_id)
+}
@@ -0,0 +1,953 @@
+use super::map::array::Array;
+use super::map::HashMap;
+use std::cell::UnsafeCell;
+use std::error::Error;
+use std::fmt;
+use std::fs::{self, File, OpenOptions};
+use std::hash::{BuildHasher, Hash, Hasher};
+use std::io;
+use std::marker::PhantomData;
+use std::mem::{size_of, MaybeUninit};
+use std::os::unix::io::AsRawFd;
+use std::path::{Path, PathBuf};
+use std::ptr;
+use std::sync::atomic::Ordering::{Acquire, Relaxed, Release};
+use std::sync::atomic::{AtomicBool, AtomicU32};
+use std::sync::{RwLock, RwLockWriteGuard};
+
+/// The magic number at the beginning of a PersistentMap file.
+const MAGIC: [u8; 8] = *b"SCCPMAP\0";
+
+/// The version of the file format.
+const VERSION: u32 = 1;
+
+/// The cells start at the second page of the file, and the first page is dedicated to the header.
+const HEADER_SIZE: usize = 4096;
+
+/// The number of slots in a cell.
+const ARRAY_SIZE: usize = 16;
+
+/// A cell accepts up to ARRAY_SIZE - 1 entries, and the vacant slot receives the new value of an updated key.
+const MAX_ENTRIES: u32 = ARRAY_SIZE as u32 - 1;
+
+/// The maximum number of locks protecting cells from concurrent modifications.
+const MAX_LOCKS: usize = 1024;
+
+/// The file grows if a new key would be stored this many or more cells away from the cell its hash value designates.
+const MAX_PROBE_DISTANCE: usize = 8;
+
+/// The header states whether the file has been modified since it was last flushed.
+const CLEAN: u32 = 0;
+const DIRTY: u32 = 1;
+
+/// Pod is a plain-old-data type that can be stored in a file, and restored by copying the bytes.
+///
+/// # Safety
+///
+/// Any bit pattern of the size of the type must be a valid value of the type, and the type must not own or refer to
+/// memory; integers, floating point numbers, and arrays of them are Pod.
+pub unsafe trait Pod: Copy + Send + Sync + 'static {}
+
+unsafe impl Pod for u8 {}
+unsafe impl Pod for u16 {}
+unsafe impl Pod for u32 {}
+unsafe impl Pod for u64 {}
+unsafe impl Pod for u128 {}
+unsafe impl Pod for usize {}
+unsafe impl Pod for i8 {}
+unsafe impl Pod for i16 {}
+unsafe impl Pod for i32 {}
+unsafe impl Pod for i64 {}
+unsafe impl Pod for i128 {}
+unsafe impl Pod for isize {}
+unsafe impl Pod for f32 {}
+unsafe impl Pod for f64 {}
+unsafe impl<T: Pod, const N: usize> Pod for [T; N] {}
+
+/// The cell storing a key and a cell having a vacant slot for the key, refer to Mapping::probe.
+type ProbeResult = (Option<(usize, usize)>, Option<usize>);
+
+/// Full is returned when a PersistentMap has no vacant slot for a new key, and the file cannot be grown.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub struct Full;
+
+impl fmt::Display for Full {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "the persistent map has no vacant slot for a new key and cannot grow"
+        )
+    }
+}
+
+impl Error for Full {}
+
+/// The header of a PersistentMap file.
+#[repr(C)]
+struct Header {
+    magic: [u8; 8],
+    version: u32,
+    state: AtomicU32,
+    lb_capacity: u32,
+    key_size: u32,
+    value_size: u32,
+    cell_size: u32,
+    /// A hash value generated by the BuildHasher, refer to hash_check.
+    hash_check: u64,
+}
+
+/// A cell of a PersistentMap file.
+///
+/// The occupancy bitmap is the only word that makes an entry visible or invisible, therefore every modification is
+/// completed by a single aligned four-byte store after the slot contents are written.
+#[repr(C)]
+struct Cell<K, V> {
+    occupied: AtomicU32,
+    /// Set when a key that belongs to the cell or a preceding cell was stored in a following cell.
+    overflowed: AtomicU32,
+    partial_hashes: UnsafeCell<[u16; ARRAY_SIZE]>,
+    keys: UnsafeCell<[MaybeUninit<K>; ARRAY_SIZE]>,
+    values: UnsafeCell<[MaybeUninit<V>; ARRAY_SIZE]>,
+}
+
+impl<K: Pod + Eq, V: Pod> Cell<K, V> {
+    fn num_entries(&self) -> u32 {
+        self.occupied.load(Relaxed).count_ones()
+    }
+
+    /// Returns true if the cell accepts a new key, keeping a slot vacant for updates.
+    fn accepts(&self) -> bool {
+        self.num_entries() < MAX_ENTRIES
+    }
+
+    fn search(&self, key: &K, partial_hash: u16) -> Option<usize> {
+        let occupied = self.occupied.load(Acquire);
+        (0..ARRAY_SIZE).find(|&slot| {
+            occupied & (1 << slot) != 0
+                && self.partial_hash(slot) == partial_hash
+                && unsafe { (*self.keys.get())[slot].assume_init_ref() } == key
+        })
+    }
+
+    /// Returns a vacant slot, or None if the occupancy bitmap in the file is corrupted.
+    fn vacant_slot(&self) -> Option<usize> {
+        let occupied = self.occupied.load(Relaxed);
+        if occupied.count_ones() > MAX_ENTRIES {
+            return None;
+        }
+        Some((!occupied).trailing_zeros() as usize)
+    }
+
+    fn partial_hash(&self, slot: usize) -> u16 {
+        unsafe { (*self.partial_hashes.get())[slot] }
+    }
+
+    fn entry(&self, slot: usize) -> (&K, &V) {
+        unsafe {
+            (
+                (*self.keys.get())[slot].assume_init_ref(),
+                (*self.values.get())[slot].assume_init_ref(),
+            )
+        }
+    }
+
+    /// Writes the entry into a vacant slot without making it visible.
+    fn write(&self, slot: usize, key: K, value: V, partial_hash: u16) {
+        unsafe {
+            (*self.partial_hashes.get())[slot] = partial_hash;
+            (*self.keys.get())[slot] = MaybeUninit::new(key);
+            (*self.values.get())[slot] = MaybeUninit::new(value);
+        }
+    }
+}
+
+/// The memory-mapped cells of a PersistentMap file, and the locks protecting them.
+struct Mapping<K, V> {
+    _file: File,
+    ptr: *mut u8,
+    len: usize,
+    lb_capacity: u8,
+    /// locks[cell_index % locks.len()] protects the cell from concurrent modifications.
+    locks: Vec<RwLock<()>>,
+    _phantom: PhantomData<(K, V)>,
+}
+
+impl<K: Pod + Eq, V: Pod> Mapping<K, V> {
+    /// Maps the file into memory.
+    fn map(file: File, len: usize, lb_capacity: u8) -> io::Result<Mapping<K, V>> {
+        let ptr = unsafe {
+            libc::mmap(
+                ptr::null_mut(),
+                len,
+                libc::PROT_READ | libc::PROT_WRITE,
+                libc::MAP_SHARED,
+                file.as_raw_fd(),
+                0,
+            )
+        };
+        if ptr == libc::MAP_FAILED {
+            return Err(io::Error::last_os_error());
+        }
+        Ok(Mapping {
+            _file: file,
+            ptr: ptr as *mut u8,
+            len,
+            lb_capacity,
+            locks: Self::new_locks(lb_capacity),
+            _phantom: PhantomData,
+        })
+    }
+
+    /// Creates a file of 2^lb_capacity empty cells at the path, and maps it into memory.
+    ///
+    /// The header is written and the file is synchronized before it is returned.
+    fn create(path: &Path, lb_capacity: u8, hash_check: u64) -> io::Result<Mapping<K, V>> {
+        let len = 1usize
+            .checked_shl(u32::from(lb_capacity))
+            .and_then(|num_cells| num_cells.checked_mul(size_of::<Cell<K, V>>()))
+            .and_then(|len| len.checked_add(HEADER_SIZE))
+            .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidInput, "capacity overflow"))?;
+        let file = OpenOptions::new()
+            .read(true)
+            .write(true)
+            .create(true)
+            .truncate(true)
+            .open(path)?;
+        // the file is filled with zeros that represent empty cells
+        file.set_len(len as u64)?;
+        let mapping = Self::map(file, len, lb_capacity)?;
+        let header = Header {
+            magic: MAGIC,
+            version: VERSION,
+            state: AtomicU32::new(CLEAN),
+            lb_capacity: u32::from(lb_capacity),
+            key_size: size_of::<K>() as u32,
+            value_size: size_of::<V>() as u32,
+            cell_size: size_of::<Cell<K, V>>() as u32,
+            hash_check,
+        };
+        unsafe { ptr::write(mapping.ptr as *mut Header, header) };
+        mapping.sync(0, len)?;
+        Ok(mapping)
+    }
+
+    fn new_locks(lb_capacity: u8) -> Vec<RwLock<()>> {
+        (0..(1usize << lb_capacity).min(MAX_LOCKS))
+            .map(|_| RwLock::new(()))
+            .collect()
+    }
+
+    /// Synchronously writes the given range of the file to the storage device.
+    fn sync(&self, offset: usize, len: usize) -> io::Result<()> {
+        if unsafe {
+            libc::msync(
+                self.ptr.add(offset) as *mut libc::c_void,
+                len,
+                libc::MS_SYNC,
+            )
+        } != 0
+        {
+            return Err(io::Error::last_os_error());
+        }
+        Ok(())
+    }
+
+    /// Exclusively locks the cells that a modification of the key may access, and probes them for the key.
+    ///
+    /// The cells from the one the hash value designates to the farthest cell the probe returns are locked, therefore
+    /// the result stays valid until the locks are released.
+    fn lock_and_probe(
+        &self,
+        key: &K,
+        hash: u64,
+        partial_hash: u16,
+    ) -> (ProbeResult, Vec<RwLockWriteGuard<'_, ()>>) {
+        let mut extent = 0;
+        loop {
+            let locks = self.lock_run(self.cell_index_at(hash, 0), extent);
+            let (found, vacant) = self.probe(key, hash, partial_hash);
+            let required = found.map(|(distance, _)| distance).max(vacant).unwrap_or(0);
+            if required <= extent {
+                return ((found, vacant), locks);
+            }
+            // cells beyond the locked ones may have changed in the meantime
+            extent = required;
+        }
+    }
+
+    /// Exclusively locks the cells in the run starting at the given cell and spanning extent following cells.
+    ///
+    /// The locks are acquired in ascending order of their indexes to avoid deadlocks.
+    fn lock_run(&self, start_index: usize, extent: usize) -> Vec<RwLockWriteGuard<'_, ()>> {
+        let mut lock_indexes: Vec<usize> = (0..=extent.min(self.num_cells() - 1))
+            .map(|distance| ((start_index + distance) & (self.num_cells() - 1)) % self.locks.len())
+            .collect();
+        lock_indexes.sort_unstable();
+        lock_indexes.dedup();
+        lock_indexes
+            .into_iter()
+            .map(|lock_index| self.locks[lock_index].write().unwrap())
+            .collect()
+    }
+
+    /// Clears the overflowed flags of the cells preceding the given empty cell if no key overflows past them.
+    ///
+    /// The caller must hold the flush lock.
+    fn shrink_run(&self, cell_index: usize) {
+        let preceding_index =
+            |distance: usize| (cell_index + self.num_cells() - distance) & (self.num_cells() - 1);
+        let mut extent = 0;
+        while extent + 1 < self.num_cells() {
+            let cell = self.cell(preceding_index(extent + 1));
+            if cell.overflowed.load(Relaxed) == 0 {
+                break;
+            }
+            extent += 1;
+            if cell.num_entries() != 0 {
+                break;
+            }
+        }
+        if extent == 0 {
+            return;
+        }
+
+        // the flags are cleared backwards as long as the cells following the flagged one are empty
+        let _locks = self.lock_run(preceding_index(extent), extent);
+        let cell = self.cell(cell_index);
+        if cell.num_entries() != 0 || cell.overflowed.load(Relaxed) != 0 {
+            return;
+        }
+        for distance in 1..=extent {
+            let cell = self.cell(preceding_index(distance));
+            if cell.overflowed.load(Relaxed) == 0 {
+                break;
+            }
+            cell.overflowed.store(0, Release);
+            if cell.num_entries() != 0 {
+                break;
+            }
+        }
+    }
+
+    /// Searches the cells for the key, and a cell having a vacant slot for the key.
+    ///
+    /// It returns the distance of the cell storing the key from the cell the hash value designates and the slot of
+    /// the key if found, and the distance to the first cell having a vacant slot.
+    fn probe(&self, key: &K, hash: u64, partial_hash: u16) -> ProbeResult {
+        let mut vacant = None;
+        let mut key_absent = false;
+        for distance in 0..self.num_cells() {
+            let cell = self.cell(self.cell_index_at(hash, distance));
+            if !key_absent {
+                if let Some(slot) = cell.search(key, partial_hash) {
+                    return (Some((distance, slot)), vacant);
+                }
+            }
+            if vacant.is_none() && cell.accepts() {
+                vacant = Some(distance);
+            }
+            key_absent |= cell.overflowed.load(Relaxed) == 0;
+            if key_absent && vacant.is_some() {
+                break;
+            }
+        }
+        (None, vacant)
+    }
+
+    /// Stores a new key-value pair in the cell at the given distance from the cell the hash value designates.
+    ///
+    /// The caller must hold the locks on the cells up to the distance.
+    fn store(&self, hash: u64, distance: usize, key: K, value: V, partial_hash: u16) {
+        for passed in 0..distance {
+            self.cell(self.cell_index_at(hash, passed))
+                .overflowed
+                .store(1, Release);
+        }
+        let cell = self.cell(self.cell_index_at(hash, distance));
+        // the cell accepts a new key, therefore the occupancy bitmap has a vacant slot
+        let vacant_slot = cell.vacant_slot().unwrap();
+        cell.write(vacant_slot, key, value, partial_hash);
+        let occupied = cell.occupied.load(Relaxed);
+        cell.occupied.store(occupied | (1 << vacant_slot), Release);
+    }
+
+    /// Returns the index of the cell at the given distance from the cell the hash value designates.
+    fn cell_index_at(&self, hash: u64, distance: usize) -> usize {
+        (Array::<K, V>::cell_index(hash, self.lb_capacity) + distance) & (self.num_cells() - 1)
+    }
+
+    fn header(&self) -> &Header {
+        unsafe { &*(self.ptr as *const Header) }
+    }
+
+    fn cell(&self, cell_index: usize) -> &Cell<K, V> {
+        debug_assert!(cell_index < self.num_cells());
+        unsafe { &*(self.ptr.add(HEADER_SIZE) as *const Cell<K, V>).add(cell_index) }
+    }
+
+    fn lock(&self, cell_index: usize) -> &RwLock<()> {
+        &self.locks[cell_index % self.locks.len()]
+    }
+
+    fn num_cells(&self) -> usize {
+        1usize << self.lb_capacity
+    }
+}
+
+impl<K, V> Drop for Mapping<K, V> {
+    fn drop(&mut self) {
+        unsafe { libc::munmap(self.ptr as *mut libc::c_void, self.len) };
+    }
+}
+
+/// A concurrent hash map of which the cells live in a memory-mapped file.
+///
+/// scc::PersistentMap stores Pod keys and values in a file laid out like the array of an scc::HashMap: a header
+/// recording the format version and lb_capacity, followed by 2^lb_capacity cells of sixteen slots. Opening an
+/// existing file maps it into memory without reading the entries, thus the operating system pages in only the cells
+/// being accessed, and the table may exceed the size of the physical memory. The cells are not those of the Array of
+/// an scc::HashMap, since their linked lists of entry arrays are heap pointers that cannot survive a restart.
+///
+/// A key is stored in the cell its hash value designates, or the closest following cell having a vacant slot if the
+/// cell is full. A modification exclusively locks the cells from the one the hash value designates to the one being
+/// modified, therefore modifications of keys in disjoint cells proceed in parallel, and readers are blocked only by
+/// a modification of the cell they are reading.
+///
+/// The file grows when a new key would be stored MAX_PROBE_DISTANCE or more cells away from the cell its hash value
+/// designates, or when no cell has a vacant slot: the entries are rehashed into a new file of twice as many cells
+/// next to the original one, and the new file is synchronized, renamed over the original one, and mapped into memory
+/// in place of it. Every other operation on the PersistentMap waits until the growth completes.
+///
+/// The file is not portable: it is written in the native byte order and the in-memory layout of the key and value
+/// types, and the BuildHasher must produce the same hash values whenever the file is opened, which RandomState does
+/// not. Opening a file with a different key type, value type, or BuildHasher fails with an error of the InvalidData
+/// kind.
+///
+/// Crash consistency.
+/// * A modification is made visible by a single aligned store to the occupancy bitmap of a cell, therefore a crash
+///   of the process never exposes a partially written entry, and the file reflects every completed modification.
+/// * An update writes the new value to the vacant slot that every cell keeps, and then swaps the two slots in the
+///   occupancy bitmap, therefore a value is never torn.
+/// * A growth replaces the file by renaming a completely written and synchronized file over it, therefore a crash
+///   during a growth leaves either the original file or the grown one at the path.
+/// * A crash of the operating system only preserves the modifications made before the last flush, and
+///   PersistentMap::was_flushed tells whether the file was flushed after the last modification.
+pub struct PersistentMap<K: Pod + Eq + Hash, V: Pod, H: BuildHasher> {
+    path: PathBuf,
+    /// Operations share the lock, and a growth exclusively acquires it to replace the mapping.
+    mapping: RwLock<Mapping<K, V>>,
+    hasher: H,
+    /// Set when the header has been marked dirty since the file was last flushed.
+    dirty: AtomicBool,
+    /// Modifications share the lock, and flush exclusively acquires it.
+    flush_lock: RwLock<()>,
+    was_flushed: bool,
+}
+
+unsafe impl<K: Pod + Eq + Hash, V: Pod, H: BuildHasher + Send> Send for PersistentMap<K, V, H> {}
+unsafe impl<K: Pod + Eq + Hash, V: Pod, H: BuildHasher + Sync> Sync for PersistentMap<K, V, H> {}
+
+impl<K: Pod + Eq + Hash, V: Pod, H: BuildHasher> PersistentMap<K, V, H> {
+    /// Creates a PersistentMap file that accommodates at least the given number of key-value pairs before growing.
+    ///
+    /// An existing file at the path is overwritten.
+    ///
+    /// # Examples
+    /// ```
+    /// use scc::PersistentMap;
+    /// use std::collections::hash_map::DefaultHasher;
+    /// use std::hash::BuildHasherDefault;
+    ///
+    /// let path = std::env::temp_dir().join(format!("scc-create-{}.map", std::process::id()));
+    /// let persistentmap: PersistentMap<u64, u32, BuildHasherDefault<DefaultHasher>> =
+    ///     PersistentMap::create(&path, Default::default(), 1000).unwrap();
+    ///
+    /// assert!(persistentmap.capacity() >= 1000);
+    /// assert_eq!(persistentmap.len(), 0);
+    ///
+    /// drop(persistentmap);
+    /// std::fs::remove_file(&path).unwrap();
+    /// ```
+    pub fn create<P: AsRef<Path>>(
+        path: P,
+        hasher: H,
+        capacity: usize,
+    ) -> io::Result<PersistentMap<K, V, H>> {
+        let capacity = capacity.min(usize::MAX / 2);
+        let required_cells = if capacity == 0 {
+            1
+        } else {
+            ((capacity - 1) / MAX_ENTRIES as usize + 1).next_power_of_two()
+        };
+        let lb_capacity = (required_cells.trailing_zeros() as u8).max(1);
+        let path = path.as_ref().to_path_buf();
+        let hash_check = Self::hash_check(&hasher);
+        let mapping = Mapping::create(&path, lb_capacity, hash_check)?;
+        Ok(PersistentMap {
+            path,
+            mapping: RwLock::new(mapping),
+            hasher,
+            dirty: AtomicBool::new(false),
+            flush_lock: RwLock::new(()),
+            was_flushed: true,
+        })
+    }
+
+    /// Opens an existing PersistentMap file.
+    ///
+    /// # Errors
+    /// It returns the error returned by the operating system, or an error of the InvalidData kind if the file is not
+    /// a PersistentMap file of the format version, key type, value type and BuildHasher.
+    ///
+    /// # Examples
+    /// ```
+    /// use scc::PersistentMap;
+    /// use std::collections::hash_map::DefaultHasher;
+    /// use std::hash::BuildHasherDefault;
+    ///
+    /// let path = std::env::temp_dir().join(format!("scc-open-{}.map", std::process::id()));
+    /// let persistentmap: PersistentMap<u64, u32, BuildHasherDefault<DefaultHasher>> =
+    ///     PersistentMap::create(&path, Default::default(), 1000).unwrap();
+    ///
+    /// assert_eq!(persistentmap.insert(1, 0), Ok(true));
+    /// drop(persistentmap);
+    ///
+    /// let persistentmap: PersistentMap<u64, u32, BuildHasherDefault<DefaultHasher>> =
+    ///     PersistentMap::open(&path, Default::default()).unwrap();
+    /// assert!(persistentmap.was_flushed());
+    /// assert_eq!(persistentmap.read(1, |_, value| *value), Some(0));
+    ///
+    /// drop(persistentmap);
+    /// std::fs::remove_file(&path).unwrap();
+    /// ```
+    pub fn open<P: AsRef<Path>>(path: P, hasher: H) -> io::Result<PersistentMap<K, V, H>> {
+        let path = path.as_ref().to_path_buf();
+        let file = OpenOptions::new().read(true).write(true).open(&path)?;
+        let len = file.metadata()?.len() as usize;
+        if len < HEADER_SIZE {
+            return Err(invalid_data("not a persistent map file"));
+        }
+        let mut mapping = Mapping::<K, V>::map(file, len, 1)?;
+        let header = mapping.header();
+        if header.magic != MAGIC {
+            return Err(invalid_data("not a persistent map file"));
+        }
+        if header.version != VERSION {
+            return Err(invalid_data("unsupported format version"));
+        }
+        if header.key_size != size_of::<K>() as u32
+            || header.value_size != size_of::<V>() as u32
+            || header.cell_size != size_of::<Cell<K, V>>() as u32
+        {
+            return Err(invalid_data("key or value type mismatch"));
+        }
+        if header.lb_capacity == 0
+            || header.lb_capacity >= usize::BITS
+            || (1usize << header.lb_capacity).checked_mul(size_of::<Cell<K, V>>())
+                != Some(len - HEADER_SIZE)
+        {
+            return Err(invalid_data("invalid capacity"));
+        }
+        if header.hash_check != Self::hash_check(&hasher) {
+            return Err(invalid_data("BuildHasher mismatch"));
+        }
+        let (lb_capacity, state) = (header.lb_capacity as u8, header.state.load(Relaxed));
+        mapping.lb_capacity = lb_capacity;
+        mapping.locks = Mapping::<K, V>::new_locks(lb_capacity);
+        Ok(PersistentMap {
+            path,
+            mapping: RwLock::new(mapping),
+            hasher,
+            // the header stays dirty until the file is flushed
+            dirty: AtomicBool::new(state != CLEAN),
+            flush_lock: RwLock::new(()),
+            was_flushed: state == CLEAN,
+        })
+    }
+
+    /// Inserts a key-value pair.
+    ///
+    /// It returns false if the key exists.
+    ///
+    /// # Errors
+    /// It returns Full if no cell has a vacant slot for the key, and the file cannot be grown.
+    ///
+    /// # Examples
+    /// ```
+    /// use scc::PersistentMap;
+    /// use std::collections::hash_map::DefaultHasher;
+    /// use std::hash::BuildHasherDefault;
+    ///
+    /// let path = std::env::temp_dir().join(format!("scc-insert-{}.map", std::process::id()));
+    /// let persistentmap: PersistentMap<u64, u32, BuildHasherDefault<DefaultHasher>> =
+    ///     PersistentMap::create(&path, Default::default(), 16).unwrap();
+    ///
+    /// assert_eq!(persistentmap.insert(1, 0), Ok(true));
+    /// assert_eq!(persistentmap.insert(1, 1), Ok(false));
+    ///
+    /// let capacity = persistentmap.capacity() as u64;
+    /// for key in 2..=capacity + 1 {
+    ///     assert_eq!(persistentmap.insert(key, 0), Ok(true));
+    /// }
+    /// assert!(persistentmap.capacity() > capacity as usize);
+    ///
+    /// drop(persistentmap);
+    /// std::fs::remove_file(&path).unwrap();
+    /// ```
+    pub fn insert(&self, key: K, value: V) -> Result<bool, Full> {
+        let (hash, partial_hash) = self.hash(&key);
+        self.modify(&key, hash, partial_hash, |mapping, (found, vacant)| {
+            if found.is_some() {
+                return Ok(false);
+            }
+            let distance = vacant.ok_or(Full)?;
+            self.mark_dirty(mapping);
+            mapping.store(hash, distance, key, value, partial_hash);
+            Ok(true)
+        })
+    }
+
+    /// Upserts a key-value pair, and returns the previous value of the key.
+    ///
+    /// # Errors
+    /// It returns Full if the key does not exist, no cell has a vacant slot for the key, and the file cannot be
+    /// grown, or if the cell storing the key has no vacant slot for the new value, which only happens if the file was
+    /// corrupted.
+    ///
+    /// # Examples
+    /// ```
+    /// use scc::PersistentMap;
+    /// use std::collections::hash_map::DefaultHasher;
+    /// use std::hash::BuildHasherDefault;
+    ///
+    /// let path = std::env::temp_dir().join(format!("scc-upsert-{}.map", std::process::id()));
+    /// let persistentmap: PersistentMap<u64, u32, BuildHasherDefault<DefaultHasher>> =
+    ///     PersistentMap::create(&path, Default::default(), 1000).unwrap();
+    ///
+    /// assert_eq!(persistentmap.upsert(1, 0), Ok(None));
+    /// assert_eq!(persistentmap.upsert(1, 1), Ok(Some(0)));
+    /// assert_eq!(persistentmap.read(1, |_, value| *value), Some(1));
+    ///
+    /// drop(persistentmap);
+    /// std::fs::remove_file(&path).unwrap();
+    /// ```
+    pub fn upsert(&self, key: K, value: V) -> Result<Option<V>, Full> {
+        let (hash, partial_hash) = self.hash(&key);
+        self.modify(&key, hash, partial_hash, |mapping, (found, vacant)| {
+            if let Some((distance, slot)) = found {
+                let cell = mapping.cell(mapping.cell_index_at(hash, distance));
+                let vacant_slot = cell.vacant_slot().ok_or(Full)?;
+                self.mark_dirty(mapping);
+                let old_value = *cell.entry(slot).1;
+                cell.write(vacant_slot, key, value, partial_hash);
+                let occupied = cell.occupied.load(Relaxed);
+                cell.occupied
+                    .store((occupied & !(1 << slot)) | (1 << vacant_slot), Release);
+                return Ok(Some(old_value));
+            }
+            let distance = vacant.ok_or(Full)?;
+            self.mark_dirty(mapping);
+            mapping.store(hash, distance, key, value, partial_hash);
+            Ok(None)
+        })
+    }
+
+    /// Removes a key-value pair.
+    ///
+    /// # Examples
+    /// ```
+    /// use scc::PersistentMap;
+    /// use std::collections::hash_map::DefaultHasher;
+    /// use std::hash::BuildHasherDefault;
+    ///
+    /// let path = std::env::temp_dir().join(format!("scc-remove-{}.map", std::process::id()));
+    /// let persistentmap: PersistentMap<u64, u32, BuildHasherDefault<DefaultHasher>> =
+    ///     PersistentMap::create(&path, Default::default(), 1000).unwrap();
+    ///
+    /// assert!(!persistentmap.remove(1));
+    /// assert_eq!(persistentmap.insert(1, 0), Ok(true));
+    /// assert!(persistentmap.remove(1));
+    ///
+    /// drop(persistentmap);
+    /// std::fs::remove_file(&path).unwrap();
+    /// ```
+    pub fn remove(&self, key: K) -> bool {
+        let mapping = self.mapping.read().unwrap();
+        let _flush_lock = self.flush_lock.read().unwrap();
+        let (hash, partial_hash) = self.hash(&key);
+        let ((found, _), locks) = mapping.lock_and_probe(&key, hash, partial_hash);
+        if let Some((distance, slot)) = found {
+            self.mark_dirty(&mapping);
+            let cell_index = mapping.cell_index_at(hash, distance);
+            let cell = mapping.cell(cell_index);
+            let occupied = cell.occupied.load(Relaxed);
+            cell.occupied.store(occupied & !(1 << slot), Release);
+            drop(locks);
+            if cell.num_entries() == 0 {
+                mapping.shrink_run(cell_index);
+            }
+            return true;
+        }
+        false
+    }
+
+    /// Reads a key-value pair.
+    ///
+    /// # Examples
+    /// ```
+    /// use scc::PersistentMap;
+    /// use std::collections::hash_map::DefaultHasher;
+    /// use std::hash::BuildHasherDefault;
+    ///
+    /// let path = std::env::temp_dir().join(format!("scc-read-{}.map", std::process::id()));
+    /// let persistentmap: PersistentMap<u64, u32, BuildHasherDefault<DefaultHasher>> =
+    ///     PersistentMap::create(&path, Default::default(), 1000).unwrap();
+    ///
+    /// assert_eq!(persistentmap.insert(1, 0), Ok(true));
+    /// assert_eq!(persistentmap.read(1, |_, value| *value), Some(0));
+    /// assert!(persistentmap.read(2, |_, value| *value).is_none());
+    ///
+    /// drop(persistentmap);
+    /// std::fs::remove_file(&path).unwrap();
+    /// ```
+    pub fn read<U, F: FnOnce(&K, &V) -> U>(&self, key: K, f: F) -> Option<U> {
+        let mapping = self.mapping.read().unwrap();
+        let (hash, partial_hash) = self.hash(&key);
+        let mut cell_index = mapping.cell_index_at(hash, 0);
+        for _ in 0..mapping.num_cells() {
+            let _lock = mapping.lock(cell_index).read().unwrap();
+            let cell = mapping.cell(cell_index);
+            if let Some(slot) = cell.search(&key, partial_hash) {
+                let (key, value) = cell.entry(slot);
+                return Some(f(key, value));
+            }
+            if cell.overflowed.load(Acquire) == 0 {
+                break;
+            }
+            cell_index = (cell_index + 1) & (mapping.num_cells() - 1);
+        }
+        None
+    }
+
+    /// Returns the number of key-value pairs.
+    ///
+    /// It scans the whole file.
+    ///
+    /// # Examples
+    /// ```
+    /// use scc::PersistentMap;
+    /// use std::collections::hash_map::DefaultHasher;
+    /// use std::hash::BuildHasherDefault;
+    ///
+    /// let path = std::env::temp_dir().join(format!("scc-len-{}.map", std::process::id()));
+    /// let persistentmap: PersistentMap<u64, u32, BuildHasherDefault<DefaultHasher>> =
+    ///     PersistentMap::create(&path, Default::default(), 1000).unwrap();
+    ///
+    /// assert_eq!(persistentmap.insert(1, 0), Ok(true));
+    /// assert_eq!(persistentmap.len(), 1);
+    ///
+    /// drop(persistentmap);
+    /// std::fs::remove_file(&path).unwrap();
+    /// ```
+    pub fn len(&self) -> usize {
+        let mapping = self.mapping.read().unwrap();
+        (0..mapping.num_cells())
+            .map(|cell_index| mapping.cell(cell_index).num_entries() as usize)
+            .sum()
+    }
+
+    /// Returns true if the PersistentMap is empty.
+    ///
+    /// It scans the whole file.
+    pub fn is_empty(&self) -> bool {
+        let mapping = self.mapping.read().unwrap();
+        (0..mapping.num_cells()).all(|cell_index| mapping.cell(cell_index).num_entries() == 0)
+    }
+
+    /// Returns the number of key-value pairs the PersistentMap can accommodate without growing the file.
+    pub fn capacity(&self) -> usize {
+        self.mapping.read().unwrap().num_cells() * MAX_ENTRIES as usize
+    }
+
+    /// Writes the modifications to the storage device.
+    ///
+    /// # Errors
+    /// It returns the error returned by the operating system.
+    ///
+    /// # Examples
+    /// ```
+    /// use scc::PersistentMap;
+    /// use std::collections::hash_map::DefaultHasher;
+    /// use std::hash::BuildHasherDefault;
+    ///
+    /// let path = std::env::temp_dir().join(format!("scc-flush-{}.map", std::process::id()));
+    /// let persistentmap: PersistentMap<u64, u32, BuildHasherDefault<DefaultHasher>> =
+    ///     PersistentMap::create(&path, Default::default(), 1000).unwrap();
+    ///
+    /// assert_eq!(persistentmap.insert(1, 0), Ok(true));
+    /// assert!(persistentmap.flush().is_ok());
+    ///
+    /// drop(persistentmap);
+    /// std::fs::remove_file(&path).unwrap();
+    /// ```
+    pub fn flush(&self) -> io::Result<()> {
+        let mapping = self.mapping.read().unwrap();
+        let _flush_lock = self.flush_lock.write().unwrap();
+        if self.dirty.load(Relaxed) {
+            // the header is marked clean only after all the cells are written
+            mapping.sync(HEADER_SIZE, mapping.len - HEADER_SIZE)?;
+            mapping.header().state.store(CLEAN, Relaxed);
+            mapping.sync(0, HEADER_SIZE)?;
+            self.dirty.store(false, Relaxed);
+        }
+        Ok(())
+    }
+
+    /// Returns true if the file had been flushed after it was last modified when it was opened.
+    ///
+    /// It returns false if the process that last modified the file crashed before flushing it; the file reflects
+    /// every completed modification unless the operating system crashed as well.
+    pub fn was_flushed(&self) -> bool {
+        self.was_flushed
+    }
+
+    /// Locks the cells that a modification of the key may access, and passes the probe result to the closure.
+    ///
+    /// The file is grown before the closure is invoked if the key does not exist, and the closest cell having a
+    /// vacant slot is MAX_PROBE_DISTANCE or more cells away; if the file cannot be grown, the closure is invoked with
+    /// the probe result as it is.
+    fn modify<R, F: FnOnce(&Mapping<K, V>, ProbeResult) -> Result<R, Full>>(
+        &self,
+        key: &K,
+        hash: u64,
+        partial_hash: u16,
+        f: F,
+    ) -> Result<R, Full> {
+        let mut grow_failed = false;
+        loop {
+            let mapping = self.mapping.read().unwrap();
+            let flush_lock = self.flush_lock.read().unwrap();
+            let ((found, vacant), locks) = mapping.lock_and_probe(key, hash, partial_hash);
+            if grow_failed
+                || found.is_some()
+                || matches!(vacant, Some(distance) if distance < MAX_PROBE_DISTANCE)
+            {
+                return f(&mapping, (found, vacant));
+            }
+            let lb_capacity = mapping.lb_capacity;
+            drop(locks);
+            drop(flush_lock);
+            drop(mapping);
+            grow_failed = self.grow(lb_capacity).is_err();
+        }
+    }
+
+    /// Doubles the number of cells unless another thread has already grown the file since it observed lb_capacity.
+    ///
+    /// The entries are rehashed into a new file next to the original one, and the new file replaces the original one
+    /// after it is synchronized.
+    fn grow(&self, lb_capacity: u8) -> io::Result<()> {
+        let mut mapping = self.mapping.write().unwrap();
+        if mapping.lb_capacity != lb_capacity {
+            return Ok(());
+        }
+        if u32::from(lb_capacity) + 1 >= usize::BITS {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                "capacity overflow",
+            ));
+        }
+        let mut new_path = self.path.clone().into_os_string();
+        new_path.push(".grow");
+        let new_path = PathBuf::from(new_path);
+        let result = self.rehash(&mapping, &new_path).and_then(|new_mapping| {
+            fs::rename(&new_path, &self.path)?;
+            sync_parent(&self.path)?;
+            Ok(new_mapping)
+        });
+        match result {
+            Ok(new_mapping) => {
+                *mapping = new_mapping;
+                // every entry was written to the storage device before the new file replaced the original one
+                self.dirty.store(false, Relaxed);
+                Ok(())
+            }
+            Err(error) => {
+                let _ = fs::remove_file(&new_path);
+                Err(error)
+            }
+        }
+    }
+
+    /// Creates a file of twice as many cells at the path, and copies the entries into it.
+    ///
+    /// The caller must exclusively hold the mapping.
+    fn rehash(&self, mapping: &Mapping<K, V>, path: &Path) -> io::Result<Mapping<K, V>> {
+        let new_mapping = Mapping::<K, V>::create(
+            path,
+            mapping.lb_capacity + 1,
+            Self::hash_check(&self.hasher),
+        )?;
+        for cell_index in 0..mapping.num_cells() {
+            let cell = mapping.cell(cell_index);
+            let occupied = cell.occupied.load(Relaxed);
+            if occupied.count_ones() > MAX_ENTRIES {
+                return Err(invalid_data("corrupted occupancy bitmap"));
+            }
+            for slot in (0..ARRAY_SIZE).filter(|slot| occupied & (1 << slot) != 0) {
+                let (key, value) = cell.entry(slot);
+                let (hash, partial_hash) = self.hash(key);
+                let (_, vacant) = new_mapping.probe(key, hash, partial_hash);
+                let distance = vacant.ok_or_else(|| invalid_data("corrupted occupancy bitmap"))?;
+                new_mapping.store(hash, distance, *key, *value, partial_hash);
+            }
+        }
+        new_mapping.sync(HEADER_SIZE, new_mapping.len - HEADER_SIZE)?;
+        Ok(new_mapping)
+    }
+
+    /// Marks the header dirty before the first modification since the file was last flushed.
+    ///
+    /// The caller must hold the flush lock.
+    fn mark_dirty(&self, mapping: &Mapping<K, V>) {
+        if !self.dirty.load(Acquire) {
+            // concurrent modifications may mark the header at the same time, which is harmless
+            mapping.header().state.store(DIRTY, Relaxed);
+            // a failure is reported by the next flush
+            let _ = mapping.sync(0, HEADER_SIZE);
+            self.dirty.store(true, Release);
+        }
+    }
+
+    fn hash(&self, key: &K) -> (u64, u16) {
+        let mut h = self.hasher.build_hasher();
+        key.hash(&mut h);
+        HashMap::<K, V, H>::mix(h.finish())
+    }
+
+    /// Returns a hash value that tells whether the file was created with an equivalent BuildHasher.
+    fn hash_check(hasher: &H) -> u64 {
+        let mut h = hasher.build_hasher();
+        MAGIC.hash(&mut h);
+        h.finish()
+    }
+}
+
+impl<K: Pod + Eq + Hash, V: Pod, H: BuildHasher> Drop for PersistentMap<K, V, H> {
+    fn drop(&mut self) {
+        // an unflushed modification is still written to the file by the operating system
+        let _ = self.flush();
+    }
+}
+
+fn invalid_data(message: &str) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, message)
+}
+
+/// Synchronizes the directory containing the path so that a rename of the file survives a crash.
+fn sync_parent(path: &Path) -> io::Result<()> {
+    match path.parent() {
+        Some(parent) if !parent.as_os_str().is_empty() => File::open(parent)?.sync_all(),
+        _ => File::open(".")?.sync_all(),
+    }
+}
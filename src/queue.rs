@@ -0,0 +1,312 @@
+extern crate crossbeam_epoch;
+
+use crossbeam_epoch::{Atomic, Owned, Shared};
+use std::cell::UnsafeCell;
+use std::mem::MaybeUninit;
+use std::ptr;
+use std::sync::atomic::AtomicUsize;
+use std::sync::atomic::Ordering::{AcqRel, Acquire, Relaxed, Release};
+use std::thread;
+
+/// The flag of the state of an Entry representing that the value has been taken.
+const TAKEN: usize = !(usize::MAX >> 1);
+
+/// A lock-free concurrent FIFO queue implementation.
+///
+/// scc::Queue is a Michael-Scott queue whose nodes are reclaimed by the epoch-based reclamation technique provided by
+/// the crossbeam_epoch crate. A popped value is moved out of the node, and pop waits for the peek closures that
+/// started reading the value before it was popped.
+pub struct Queue<T> {
+    head: Atomic<Node<T>>,
+    tail: Atomic<Node<T>>,
+    len: AtomicUsize,
+}
+
+impl<T> Queue<T> {
+    /// Creates an empty Queue instance.
+    ///
+    /// # Examples
+    /// ```
+    /// use scc::Queue;
+    ///
+    /// let queue: Queue<u64> = Queue::new();
+    ///
+    /// let result = queue.pop();
+    /// assert!(result.is_none());
+    /// ```
+    pub fn new() -> Queue<T> {
+        let guard = unsafe { crossbeam_epoch::unprotected() };
+        let sentinel = Owned::new(Node::new(Entry::empty())).into_shared(guard);
+        Queue {
+            head: Atomic::from(sentinel),
+            tail: Atomic::from(sentinel),
+            len: AtomicUsize::new(0),
+        }
+    }
+
+    /// Pushes a value into the tail of the Queue.
+    ///
+    /// # Examples
+    /// ```
+    /// use scc::Queue;
+    ///
+    /// let queue: Queue<u64> = Queue::new();
+    ///
+    /// queue.push(1);
+    /// assert_eq!(queue.len(), 1);
+    /// ```
+    pub fn push(&self, value: T) {
+        let guard = crossbeam_epoch::pin();
+        let new_node = Owned::new(Node::new(Entry::new(value))).into_shared(&guard);
+        loop {
+            let tail = self.tail.load(Acquire, &guard);
+            let tail_ref = unsafe { tail.deref() };
+            let next = tail_ref.next.load(Acquire, &guard);
+            if !next.is_null() {
+                // helps the other thread move the tail forward
+                let _ = self
+                    .tail
+                    .compare_exchange(tail, next, Release, Relaxed, &guard);
+                continue;
+            }
+            if tail_ref
+                .next
+                .compare_exchange(Shared::null(), new_node, Release, Relaxed, &guard)
+                .is_ok()
+            {
+                let _ = self
+                    .tail
+                    .compare_exchange(tail, new_node, Release, Relaxed, &guard);
+                self.len.fetch_add(1, Relaxed);
+                return;
+            }
+        }
+    }
+
+    /// Pops the value at the head of the Queue.
+    ///
+    /// # Examples
+    /// ```
+    /// use scc::Queue;
+    ///
+    /// let queue: Queue<u64> = Queue::new();
+    ///
+    /// queue.push(1);
+    /// queue.push(2);
+    ///
+    /// let result = queue.pop();
+    /// assert_eq!(result, Some(1));
+    ///
+    /// let result = queue.pop();
+    /// assert_eq!(result, Some(2));
+    ///
+    /// let result = queue.pop();
+    /// assert!(result.is_none());
+    /// ```
+    pub fn pop(&self) -> Option<T> {
+        let guard = crossbeam_epoch::pin();
+        loop {
+            let head = self.head.load(Acquire, &guard);
+            let next = unsafe { head.deref() }.next.load(Acquire, &guard);
+            if next.is_null() {
+                return None;
+            }
+            let tail = self.tail.load(Acquire, &guard);
+            if tail == head {
+                // the tail must not point to a popped node
+                let _ = self
+                    .tail
+                    .compare_exchange(tail, next, Release, Relaxed, &guard);
+            }
+            if self
+                .head
+                .compare_exchange(head, next, Release, Relaxed, &guard)
+                .is_ok()
+            {
+                // the popped node becomes the new sentinel after its value is taken
+                self.len.fetch_sub(1, Relaxed);
+                unsafe { guard.defer_destroy(head) };
+                return Some(unsafe { next.deref() }.entry.take());
+            }
+        }
+    }
+
+    /// Reads the value at the head of the Queue without popping it.
+    ///
+    /// # Examples
+    /// ```
+    /// use scc::Queue;
+    ///
+    /// let queue: Queue<u64> = Queue::new();
+    ///
+    /// let result = queue.peek(|value| *value);
+    /// assert!(result.is_none());
+    ///
+    /// queue.push(1);
+    /// queue.push(2);
+    ///
+    /// let result = queue.peek(|value| *value);
+    /// assert_eq!(result, Some(1));
+    /// ```
+    pub fn peek<U, F: FnOnce(&T) -> U>(&self, f: F) -> Option<U> {
+        let guard = crossbeam_epoch::pin();
+        let mut f = Some(f);
+        loop {
+            let head = self.head.load(Acquire, &guard);
+            let next = unsafe { head.deref() }.next.load(Acquire, &guard);
+            let node = unsafe { next.as_ref() }?;
+            // the value has been popped if it cannot be read
+            if let Some(result) = node.entry.read(&mut f) {
+                return Some(result);
+            }
+        }
+    }
+
+    /// Returns an estimated number of values in the Queue.
+    ///
+    /// The number is not exact when there are concurrent push or pop operations.
+    ///
+    /// # Examples
+    /// ```
+    /// use scc::Queue;
+    ///
+    /// let queue: Queue<u64> = Queue::new();
+    ///
+    /// queue.push(1);
+    /// queue.push(2);
+    /// assert_eq!(queue.len(), 2);
+    /// ```
+    pub fn len(&self) -> usize {
+        // fetch_sub may be observed before the corresponding fetch_add
+        let len = self.len.load(Relaxed);
+        if len > isize::MAX as usize {
+            0
+        } else {
+            len
+        }
+    }
+
+    /// Checks if the Queue is empty.
+    ///
+    /// # Examples
+    /// ```
+    /// use scc::Queue;
+    ///
+    /// let queue: Queue<u64> = Queue::new();
+    /// assert!(queue.is_empty());
+    ///
+    /// queue.push(1);
+    /// assert!(!queue.is_empty());
+    /// ```
+    pub fn is_empty(&self) -> bool {
+        let guard = crossbeam_epoch::pin();
+        let head = self.head.load(Acquire, &guard);
+        unsafe { head.deref() }.next.load(Acquire, &guard).is_null()
+    }
+}
+
+impl<T> Default for Queue<T> {
+    fn default() -> Self {
+        Queue::new()
+    }
+}
+
+impl<T> Drop for Queue<T> {
+    fn drop(&mut self) {
+        let guard = unsafe { crossbeam_epoch::unprotected() };
+        let mut node = self.head.load(Relaxed, guard);
+        while !node.is_null() {
+            let next = unsafe { node.deref() }.next.load(Relaxed, guard);
+            drop(unsafe { node.into_owned() });
+            node = next;
+        }
+    }
+}
+
+struct Node<T> {
+    entry: Entry<T>,
+    next: Atomic<Node<T>>,
+}
+
+impl<T> Node<T> {
+    fn new(entry: Entry<T>) -> Node<T> {
+        Node {
+            entry,
+            next: Atomic::null(),
+        }
+    }
+}
+
+/// Entry holds a value that can be read by multiple threads, and taken by the thread that unlinked the node.
+pub(crate) struct Entry<T> {
+    value: UnsafeCell<MaybeUninit<T>>,
+    /// The number of threads reading the value, and the TAKEN flag.
+    state: AtomicUsize,
+}
+
+impl<T> Entry<T> {
+    pub fn new(value: T) -> Entry<T> {
+        Entry {
+            value: UnsafeCell::new(MaybeUninit::new(value)),
+            state: AtomicUsize::new(0),
+        }
+    }
+
+    pub fn empty() -> Entry<T> {
+        Entry {
+            value: UnsafeCell::new(MaybeUninit::uninit()),
+            state: AtomicUsize::new(TAKEN),
+        }
+    }
+
+    /// Passes the value to the closure unless the value has been taken.
+    ///
+    /// The closure is consumed only if the value is read.
+    pub fn read<U, F: FnOnce(&T) -> U>(&self, f: &mut Option<F>) -> Option<U> {
+        if self.state.fetch_add(1, Acquire) & TAKEN == TAKEN {
+            self.state.fetch_sub(1, Release);
+            return None;
+        }
+        let reader = Reader { state: &self.state };
+        let result = f
+            .take()
+            .map(|f| f(unsafe { &*(*self.value.get()).as_ptr() }));
+        drop(reader);
+        result
+    }
+
+    /// Takes the value after the readers are gone.
+    ///
+    /// It must be called only once by the thread that unlinked the node.
+    pub fn take(&self) -> T {
+        let mut state = self.state.fetch_or(TAKEN, AcqRel);
+        debug_assert_eq!(state & TAKEN, 0);
+        while state & !TAKEN != 0 {
+            thread::yield_now();
+            state = self.state.load(Acquire);
+        }
+        unsafe { ptr::read((*self.value.get()).as_ptr()) }
+    }
+}
+
+impl<T> Drop for Entry<T> {
+    fn drop(&mut self) {
+        if *self.state.get_mut() & TAKEN == 0 {
+            unsafe { ptr::drop_in_place((*self.value.get()).as_mut_ptr()) };
+        }
+    }
+}
+
+unsafe impl<T: Send> Send for Entry<T> {}
+unsafe impl<T: Send + Sync> Sync for Entry<T> {}
+
+/// Reader decrements the number of readers even if the closure panics.
+struct Reader<'a> {
+    state: &'a AtomicUsize,
+}
+
+impl<'a> Drop for Reader<'a> {
+    fn drop(&mut self) {
+        self.state.fetch_sub(1, Release);
+    }
+}
@@ -0,0 +1,308 @@
+use super::map::{HashMap, Scanner, Statistics};
+use std::hash::{BuildHasher, Hash};
+
+/// A scalable concurrent hash set implementation.
+///
+/// scc::HashSet is a concurrent hash set built on the metadata cells and entry arrays of scc::HashMap.
+/// Keys are stored without values, and therefore an entry of scc::HashSet occupies as much memory as the key does.
+pub struct HashSet<K: Eq + Hash + Sync, H: BuildHasher> {
+    map: HashMap<K, (), H>,
+}
+
+impl<K: Eq + Hash + Sync, H: BuildHasher> HashSet<K, H> {
+    /// Creates an empty HashSet instance with the given hasher and minimum capacity.
+    ///
+    /// # Examples
+    /// ```
+    /// use scc::HashSet;
+    /// use std::collections::hash_map::RandomState;
+    ///
+    /// let hashset: HashSet<u64, RandomState> = HashSet::new(RandomState::new(), Some(1000));
+    ///
+    /// let result = hashset.capacity();
+    /// assert_eq!(result, 1024);
+    /// ```
+    pub fn new(hasher: H, minimum_capacity: Option<usize>) -> HashSet<K, H> {
+        HashSet {
+            map: HashMap::new(hasher, minimum_capacity),
+        }
+    }
+
+    /// Inserts a key into the HashSet.
+    ///
+    /// It returns false if the key exists.
+    ///
+    /// # Examples
+    /// ```
+    /// use scc::HashSet;
+    /// use std::collections::hash_map::RandomState;
+    ///
+    /// let hashset: HashSet<u64, RandomState> = HashSet::new(RandomState::new(), None);
+    ///
+    /// let result = hashset.insert(1);
+    /// assert!(result);
+    ///
+    /// let result = hashset.insert(1);
+    /// assert!(!result);
+    /// ```
+    pub fn insert(&self, key: K) -> bool {
+        self.map.insert(key, ()).is_ok()
+    }
+
+    /// Checks if the key exists.
+    ///
+    /// # Examples
+    /// ```
+    /// use scc::HashSet;
+    /// use std::collections::hash_map::RandomState;
+    ///
+    /// let hashset: HashSet<u64, RandomState> = HashSet::new(RandomState::new(), None);
+    ///
+    /// let result = hashset.contains(1);
+    /// assert!(!result);
+    ///
+    /// let result = hashset.insert(1);
+    /// assert!(result);
+    ///
+    /// let result = hashset.contains(1);
+    /// assert!(result);
+    /// ```
+    pub fn contains(&self, key: K) -> bool {
+        self.contains_ref(&key)
+    }
+
+    /// Removes a key.
+    ///
+    /// # Examples
+    /// ```
+    /// use scc::HashSet;
+    /// use std::collections::hash_map::RandomState;
+    ///
+    /// let hashset: HashSet<u64, RandomState> = HashSet::new(RandomState::new(), None);
+    ///
+    /// let result = hashset.remove(1);
+    /// assert!(!result);
+    ///
+    /// let result = hashset.insert(1);
+    /// assert!(result);
+    ///
+    /// let result = hashset.remove(1);
+    /// assert!(result);
+    /// ```
+    pub fn remove(&self, key: K) -> bool {
+        self.map.remove(key)
+    }
+
+    /// Retains the keys that satisfy the given predicate.
+    ///
+    /// It returns the number of keys remaining and removed.
+    ///
+    /// # Examples
+    /// ```
+    /// use scc::HashSet;
+    /// use std::collections::hash_map::RandomState;
+    ///
+    /// let hashset: HashSet<u64, RandomState> = HashSet::new(RandomState::new(), None);
+    ///
+    /// assert!(hashset.insert(1));
+    /// assert!(hashset.insert(2));
+    ///
+    /// let result = hashset.retain(|key| *key == 1);
+    /// assert_eq!(result, (1, 1));
+    ///
+    /// assert!(hashset.contains(1));
+    /// assert!(!hashset.contains(2));
+    /// ```
+    pub fn retain<F: Fn(&K) -> bool>(&self, f: F) -> (usize, usize) {
+        self.map.retain(|key, _| f(key))
+    }
+
+    /// Clears all the keys.
+    ///
+    /// # Examples
+    /// ```
+    /// use scc::HashSet;
+    /// use std::collections::hash_map::RandomState;
+    ///
+    /// let hashset: HashSet<u64, RandomState> = HashSet::new(RandomState::new(), None);
+    ///
+    /// assert!(hashset.insert(1));
+    /// assert!(hashset.insert(2));
+    ///
+    /// let result = hashset.clear();
+    /// assert_eq!(result, 2);
+    /// ```
+    pub fn clear(&self) -> usize {
+        self.map.clear()
+    }
+
+    /// Returns an estimated size of the HashSet.
+    ///
+    /// It passes the capacity of the HashSet to the given function.
+    ///
+    /// # Examples
+    /// ```
+    /// use scc::HashSet;
+    /// use std::collections::hash_map::RandomState;
+    ///
+    /// let hashset: HashSet<u64, RandomState> = HashSet::new(RandomState::new(), None);
+    ///
+    /// assert!(hashset.insert(1));
+    ///
+    /// let result = hashset.len(|capacity| capacity);
+    /// assert_eq!(result, 1);
+    /// ```
+    pub fn len<F: FnOnce(usize) -> usize>(&self, f: F) -> usize {
+        self.map.len(f)
+    }
+
+    /// Returns the capacity of the HashSet.
+    ///
+    /// # Examples
+    /// ```
+    /// use scc::HashSet;
+    /// use std::collections::hash_map::RandomState;
+    ///
+    /// let hashset: HashSet<u64, RandomState> = HashSet::new(RandomState::new(), Some(1000000));
+    ///
+    /// let result = hashset.capacity();
+    /// assert_eq!(result, 1048576);
+    /// ```
+    pub fn capacity(&self) -> usize {
+        self.map.capacity()
+    }
+
+    /// Returns the statistics of the HashSet.
+    ///
+    /// # Examples
+    /// ```
+    /// use scc::HashSet;
+    /// use std::collections::hash_map::RandomState;
+    ///
+    /// let hashset: HashSet<u64, RandomState> = HashSet::new(RandomState::new(), Some(1000));
+    ///
+    /// assert!(hashset.insert(1));
+    ///
+    /// let statistics = hashset.statistics();
+    /// assert_eq!(statistics.num_entries(), 1);
+    /// ```
+    pub fn statistics(&self) -> Statistics {
+        self.map.statistics()
+    }
+
+    /// Returns a SetScanner.
+    ///
+    /// It is guaranteed to scan all the keys pertaining in the HashSet at the moment,
+    /// however the same key can be scanned more than once if the HashSet is being resized.
+    ///
+    /// # Examples
+    /// ```
+    /// use scc::HashSet;
+    /// use std::collections::hash_map::RandomState;
+    ///
+    /// let hashset: HashSet<u64, RandomState> = HashSet::new(RandomState::new(), None);
+    ///
+    /// assert!(hashset.insert(1));
+    ///
+    /// let mut iter = hashset.iter();
+    /// assert_eq!(iter.next(), Some(&1));
+    /// assert_eq!(iter.next(), None);
+    /// ```
+    pub fn iter<'a>(&'a self) -> SetScanner<'a, K, H> {
+        SetScanner {
+            scanner: self.map.scan(),
+        }
+    }
+
+    /// Checks if all the keys in the HashSet are contained in the other HashSet.
+    ///
+    /// The keys are cloned before the other HashSet is searched, therefore no lock on the HashSet is held while
+    /// the other HashSet is accessed. Holding a SetScanner of either HashSet in the thread poses a possibility of
+    /// deadlock.
+    ///
+    /// # Examples
+    /// ```
+    /// use scc::HashSet;
+    /// use std::collections::hash_map::RandomState;
+    ///
+    /// let hashset: HashSet<u64, RandomState> = HashSet::new(RandomState::new(), None);
+    /// let superset: HashSet<u64, RandomState> = HashSet::new(RandomState::new(), None);
+    ///
+    /// assert!(hashset.insert(1));
+    /// assert!(superset.insert(1));
+    /// assert!(superset.insert(2));
+    ///
+    /// assert!(hashset.is_subset(&superset));
+    /// assert!(!superset.is_subset(&hashset));
+    /// ```
+    pub fn is_subset<O: BuildHasher>(&self, other: &HashSet<K, O>) -> bool
+    where
+        K: Clone,
+    {
+        if self as *const HashSet<K, H> as *const u8 == other as *const HashSet<K, O> as *const u8 {
+            return true;
+        }
+        let keys: Vec<K> = self.iter().cloned().collect();
+        keys.iter().all(|key| other.contains_ref(key))
+    }
+
+    /// Inserts all the keys in the HashSet into the other HashSet.
+    ///
+    /// It returns the number of newly inserted keys. The keys are cloned before they are inserted into the other
+    /// HashSet, therefore no lock on the HashSet is held while the other HashSet is accessed. Holding a SetScanner
+    /// of either HashSet in the thread poses a possibility of deadlock.
+    ///
+    /// # Examples
+    /// ```
+    /// use scc::HashSet;
+    /// use std::collections::hash_map::RandomState;
+    ///
+    /// let hashset: HashSet<u64, RandomState> = HashSet::new(RandomState::new(), None);
+    /// let union: HashSet<u64, RandomState> = HashSet::new(RandomState::new(), None);
+    ///
+    /// assert!(hashset.insert(1));
+    /// assert!(hashset.insert(2));
+    /// assert!(union.insert(2));
+    /// assert!(union.insert(3));
+    ///
+    /// let result = hashset.union_into(&union);
+    /// assert_eq!(result, 1);
+    /// assert!(hashset.is_subset(&union));
+    /// ```
+    pub fn union_into<O: BuildHasher>(&self, other: &HashSet<K, O>) -> usize
+    where
+        K: Clone,
+    {
+        if self as *const HashSet<K, H> as *const u8 == other as *const HashSet<K, O> as *const u8 {
+            return 0;
+        }
+        let keys: Vec<K> = self.iter().cloned().collect();
+        keys.into_iter()
+            .map(|key| other.insert(key))
+            .filter(|inserted| *inserted)
+            .count()
+    }
+
+    /// Checks if the key exists without taking the ownership of the key.
+    fn contains_ref(&self, key: &K) -> bool {
+        let (hash, partial_hash) = self.map.hash(key);
+        self.map
+            .read_entry(|k| k == key, hash, partial_hash, |_, _| ())
+            .is_some()
+    }
+}
+
+/// SetScanner implements Iterator.
+///
+/// It is !Send, thus disallowing other threads to have references to it.
+/// It acquires an exclusive lock on a cell that is currently being scanned.
+pub struct SetScanner<'a, K: Eq + Hash + Sync, H: BuildHasher> {
+    scanner: Scanner<'a, K, (), H>,
+}
+
+impl<'a, K: Eq + Hash + Sync, H: BuildHasher> Iterator for SetScanner<'a, K, H> {
+    type Item = &'a K;
+    fn next(&mut self) -> Option<Self::Item> {
+        self.scanner.next().map(|(key, _)| key)
+    }
+}
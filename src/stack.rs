@@ -0,0 +1,207 @@
+extern crate crossbeam_epoch;
+
+use super::queue::Entry;
+use crossbeam_epoch::{Atomic, Owned};
+use std::sync::atomic::AtomicUsize;
+use std::sync::atomic::Ordering::{Acquire, Relaxed, Release};
+
+/// A lock-free concurrent LIFO stack implementation.
+///
+/// scc::Stack is a Treiber stack whose nodes are reclaimed by the epoch-based reclamation technique provided by the
+/// crossbeam_epoch crate. As with scc::Queue, a popped value is moved out of the node, and pop waits for the peek
+/// closures that started reading the value before it was popped.
+pub struct Stack<T> {
+    top: Atomic<Node<T>>,
+    len: AtomicUsize,
+}
+
+impl<T> Stack<T> {
+    /// Creates an empty Stack instance.
+    ///
+    /// # Examples
+    /// ```
+    /// use scc::Stack;
+    ///
+    /// let stack: Stack<u64> = Stack::new();
+    ///
+    /// let result = stack.pop();
+    /// assert!(result.is_none());
+    /// ```
+    pub fn new() -> Stack<T> {
+        Stack {
+            top: Atomic::null(),
+            len: AtomicUsize::new(0),
+        }
+    }
+
+    /// Pushes a value onto the top of the Stack.
+    ///
+    /// # Examples
+    /// ```
+    /// use scc::Stack;
+    ///
+    /// let stack: Stack<u64> = Stack::new();
+    ///
+    /// stack.push(1);
+    /// assert_eq!(stack.len(), 1);
+    /// ```
+    pub fn push(&self, value: T) {
+        let guard = crossbeam_epoch::pin();
+        let mut new_node = Owned::new(Node {
+            entry: Entry::new(value),
+            next: Atomic::null(),
+        });
+        let mut top = self.top.load(Acquire, &guard);
+        loop {
+            new_node.next = Atomic::from(top);
+            match self
+                .top
+                .compare_exchange(top, new_node, Release, Acquire, &guard)
+            {
+                Ok(_) => {
+                    self.len.fetch_add(1, Relaxed);
+                    return;
+                }
+                Err(error) => {
+                    top = error.current;
+                    new_node = error.new;
+                }
+            }
+        }
+    }
+
+    /// Pops the value at the top of the Stack.
+    ///
+    /// # Examples
+    /// ```
+    /// use scc::Stack;
+    ///
+    /// let stack: Stack<u64> = Stack::new();
+    ///
+    /// stack.push(1);
+    /// stack.push(2);
+    ///
+    /// let result = stack.pop();
+    /// assert_eq!(result, Some(2));
+    ///
+    /// let result = stack.pop();
+    /// assert_eq!(result, Some(1));
+    ///
+    /// let result = stack.pop();
+    /// assert!(result.is_none());
+    /// ```
+    pub fn pop(&self) -> Option<T> {
+        let guard = crossbeam_epoch::pin();
+        let mut top = self.top.load(Acquire, &guard);
+        loop {
+            let top_ref = unsafe { top.as_ref() }?;
+            let next = top_ref.next.load(Relaxed, &guard);
+            match self
+                .top
+                .compare_exchange(top, next, Release, Acquire, &guard)
+            {
+                Ok(_) => {
+                    self.len.fetch_sub(1, Relaxed);
+                    let value = top_ref.entry.take();
+                    unsafe { guard.defer_destroy(top) };
+                    return Some(value);
+                }
+                Err(error) => top = error.current,
+            }
+        }
+    }
+
+    /// Reads the value at the top of the Stack without popping it.
+    ///
+    /// # Examples
+    /// ```
+    /// use scc::Stack;
+    ///
+    /// let stack: Stack<u64> = Stack::new();
+    ///
+    /// let result = stack.peek(|value| *value);
+    /// assert!(result.is_none());
+    ///
+    /// stack.push(1);
+    /// stack.push(2);
+    ///
+    /// let result = stack.peek(|value| *value);
+    /// assert_eq!(result, Some(2));
+    /// ```
+    pub fn peek<U, F: FnOnce(&T) -> U>(&self, f: F) -> Option<U> {
+        let guard = crossbeam_epoch::pin();
+        let mut f = Some(f);
+        loop {
+            let top = self.top.load(Acquire, &guard);
+            let node = unsafe { top.as_ref() }?;
+            // the value has been popped if it cannot be read
+            if let Some(result) = node.entry.read(&mut f) {
+                return Some(result);
+            }
+        }
+    }
+
+    /// Returns an estimated number of values in the Stack.
+    ///
+    /// The number is not exact when there are concurrent push or pop operations.
+    ///
+    /// # Examples
+    /// ```
+    /// use scc::Stack;
+    ///
+    /// let stack: Stack<u64> = Stack::new();
+    ///
+    /// stack.push(1);
+    /// stack.push(2);
+    /// assert_eq!(stack.len(), 2);
+    /// ```
+    pub fn len(&self) -> usize {
+        // fetch_sub may be observed before the corresponding fetch_add
+        let len = self.len.load(Relaxed);
+        if len > isize::MAX as usize {
+            0
+        } else {
+            len
+        }
+    }
+
+    /// Checks if the Stack is empty.
+    ///
+    /// # Examples
+    /// ```
+    /// use scc::Stack;
+    ///
+    /// let stack: Stack<u64> = Stack::new();
+    /// assert!(stack.is_empty());
+    ///
+    /// stack.push(1);
+    /// assert!(!stack.is_empty());
+    /// ```
+    pub fn is_empty(&self) -> bool {
+        let guard = crossbeam_epoch::pin();
+        self.top.load(Acquire, &guard).is_null()
+    }
+}
+
+impl<T> Default for Stack<T> {
+    fn default() -> Self {
+        Stack::new()
+    }
+}
+
+impl<T> Drop for Stack<T> {
+    fn drop(&mut self) {
+        let guard = unsafe { crossbeam_epoch::unprotected() };
+        let mut node = self.top.load(Relaxed, guard);
+        while !node.is_null() {
+            let next = unsafe { node.deref() }.next.load(Relaxed, guard);
+            drop(unsafe { node.into_owned() });
+            node = next;
+        }
+    }
+}
+
+struct Node<T> {
+    entry: Entry<T>,
+    next: Atomic<Node<T>>,
+}
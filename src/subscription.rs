@@ -0,0 +1,197 @@
+use std::sync::atomic::Ordering::{Acquire, Relaxed, Release};
+use std::sync::atomic::{AtomicBool, AtomicUsize};
+use std::sync::mpsc::{self, Receiver, RecvTimeoutError, SyncSender, TrySendError};
+use std::sync::{Arc, RwLock};
+use std::time::Duration;
+
+/// Event describes a modification made to an scc::HashMap.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum Event<K, V> {
+    /// The key-value pair was inserted.
+    Insert(K, V),
+    /// The value of the key was replaced with the new value.
+    Update(K, V),
+    /// The key was removed.
+    Remove(K),
+    /// Events were dropped as the Subscription fell behind.
+    Overflow,
+}
+
+/// Subscription receives the events of an scc::HashMap created by HashMap::subscribe.
+///
+/// The events are delivered through a bounded channel, and the events generated while the channel is full are
+/// dropped. The loss is reported in stream order: receiving the next event after the loss discards the events
+/// remaining in the channel and returns Event::Overflow instead, and the events generated afterwards follow it.
+/// Therefore a subscriber is supposed to discard everything it derived from the HashMap when it receives
+/// Event::Overflow. Dropping the Subscription unsubscribes it.
+pub struct Subscription<K, V> {
+    receiver: Receiver<Event<K, V>>,
+    overflowed: Arc<AtomicBool>,
+}
+
+impl<K, V> Subscription<K, V> {
+    /// Receives an event if there is one available.
+    ///
+    /// # Examples
+    /// ```
+    /// use scc::{Event, HashMap};
+    /// use std::collections::hash_map::RandomState;
+    ///
+    /// let hashmap: HashMap<u64, u32, RandomState> = HashMap::new(RandomState::new(), None);
+    ///
+    /// let subscription = hashmap.subscribe(1);
+    /// assert!(subscription.try_recv().is_none());
+    ///
+    /// assert!(hashmap.insert(1, 0).is_ok());
+    /// assert!(hashmap.remove(1));
+    ///
+    /// assert_eq!(subscription.try_recv(), Some(Event::Overflow));
+    /// assert!(subscription.try_recv().is_none());
+    ///
+    /// assert!(hashmap.insert(1, 1).is_ok());
+    /// assert_eq!(subscription.try_recv(), Some(Event::Insert(1, 1)));
+    /// ```
+    pub fn try_recv(&self) -> Option<Event<K, V>> {
+        if let Some(event) = self.overflow() {
+            return Some(event);
+        }
+        self.receiver.try_recv().ok()
+    }
+
+    /// Receives an event, waiting for one for at most the given duration.
+    ///
+    /// It returns None if no event arrives in time, or the HashMap is dropped.
+    ///
+    /// # Examples
+    /// ```
+    /// use scc::{Event, HashMap};
+    /// use std::collections::hash_map::RandomState;
+    /// use std::sync::Arc;
+    /// use std::thread;
+    /// use std::time::Duration;
+    ///
+    /// let hashmap: Arc<HashMap<u64, u32, RandomState>> =
+    ///     Arc::new(HashMap::new(RandomState::new(), None));
+    ///
+    /// let subscription = hashmap.subscribe(16);
+    /// let hashmap_copied = hashmap.clone();
+    /// let thread_handle = thread::spawn(move || {
+    ///     hashmap_copied.upsert(1, 1);
+    /// });
+    ///
+    /// let event = subscription.recv_timeout(Duration::from_secs(60));
+    /// assert_eq!(event, Some(Event::Insert(1, 1)));
+    /// thread_handle.join().unwrap();
+    /// ```
+    pub fn recv_timeout(&self, timeout: Duration) -> Option<Event<K, V>> {
+        if let Some(event) = self.overflow() {
+            return Some(event);
+        }
+        match self.receiver.recv_timeout(timeout) {
+            Ok(event) => Some(event),
+            Err(RecvTimeoutError::Timeout) | Err(RecvTimeoutError::Disconnected) => None,
+        }
+    }
+
+    /// Returns Event::Overflow after discarding the events in the channel if events have been dropped.
+    ///
+    /// Event::Overflow takes the place of the events in the channel, otherwise it would be delivered ahead of the
+    /// events generated before the loss.
+    fn overflow(&self) -> Option<Event<K, V>> {
+        if !self.overflowed.swap(false, Acquire) {
+            return None;
+        }
+        while self.receiver.try_recv().is_ok() {}
+        Some(Event::Overflow)
+    }
+}
+
+/// EventFn makes an Event out of a modification, refer to new_event.
+type EventFn<K, V> = fn(&K, Option<&V>, Option<&V>) -> Event<K, V>;
+
+/// Subscriber is the sending end of a Subscription.
+struct Subscriber<K, V> {
+    sender: SyncSender<Event<K, V>>,
+    overflowed: Arc<AtomicBool>,
+    new_event: EventFn<K, V>,
+    disconnected: AtomicBool,
+}
+
+/// Subscribers manages the Subscriptions of an scc::HashMap.
+pub(crate) struct Subscribers<K, V> {
+    num_subscribers: AtomicUsize,
+    subscribers: RwLock<Vec<Subscriber<K, V>>>,
+}
+
+impl<K, V> Subscribers<K, V> {
+    pub fn new() -> Subscribers<K, V> {
+        Subscribers {
+            num_subscribers: AtomicUsize::new(0),
+            subscribers: RwLock::new(Vec::new()),
+        }
+    }
+
+    /// Registers a new Subscription of which the channel holds up to the given number of events.
+    pub fn subscribe(&self, capacity: usize) -> Subscription<K, V>
+    where
+        K: Clone,
+        V: Clone,
+    {
+        let (sender, receiver) = mpsc::sync_channel(capacity.max(1));
+        let overflowed = Arc::new(AtomicBool::new(false));
+        let mut subscribers = self.subscribers.write().unwrap();
+        subscribers.retain(|subscriber| !subscriber.disconnected.load(Relaxed));
+        subscribers.push(Subscriber {
+            sender,
+            overflowed: overflowed.clone(),
+            new_event: new_event::<K, V>,
+            disconnected: AtomicBool::new(false),
+        });
+        self.num_subscribers.store(subscribers.len(), Relaxed);
+        Subscription {
+            receiver,
+            overflowed,
+        }
+    }
+
+    /// Delivers the modification to the Subscriptions.
+    pub fn publish(&self, key: &K, old_value: Option<&V>, new_value: Option<&V>) {
+        if self.num_subscribers.load(Relaxed) == 0 {
+            return;
+        }
+        let mut disconnected = false;
+        for subscriber in self.subscribers.read().unwrap().iter() {
+            match subscriber
+                .sender
+                .try_send((subscriber.new_event)(key, old_value, new_value))
+            {
+                Ok(()) => (),
+                Err(TrySendError::Full(_)) => subscriber.overflowed.store(true, Release),
+                Err(TrySendError::Disconnected(_)) => {
+                    subscriber.disconnected.store(true, Relaxed);
+                    disconnected = true;
+                }
+            }
+        }
+
+        // the caller is holding a cell lock, therefore it must not wait for the other threads
+        if disconnected {
+            if let Ok(mut subscribers) = self.subscribers.try_write() {
+                subscribers.retain(|subscriber| !subscriber.disconnected.load(Relaxed));
+                self.num_subscribers.store(subscribers.len(), Relaxed);
+            }
+        }
+    }
+}
+
+fn new_event<K: Clone, V: Clone>(
+    key: &K,
+    old_value: Option<&V>,
+    new_value: Option<&V>,
+) -> Event<K, V> {
+    match (old_value, new_value) {
+        (None, Some(new_value)) => Event::Insert(key.clone(), new_value.clone()),
+        (Some(_), Some(new_value)) => Event::Update(key.clone(), new_value.clone()),
+        (_, None) => Event::Remove(key.clone()),
+    }
+}
@@ -0,0 +1,191 @@
+use super::map::{CellVersion, HashMap};
+use crossbeam_epoch::Guard;
+use std::error::Error;
+use std::fmt;
+use std::hash::{BuildHasher, Hash};
+
+/// Conflict is returned when a Transaction fails to commit.
+///
+/// A cell owning a key read by the transaction was modified after the key was read, and none of the buffered
+/// updates of the transaction were applied. The transaction is supposed to be retried from the beginning.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub struct Conflict;
+
+impl fmt::Display for Conflict {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "the keys read by the transaction were modified by another thread"
+        )
+    }
+}
+
+impl Error for Conflict {}
+
+/// Transaction is an optimistic transaction on an scc::HashMap.
+///
+/// It reads keys without blocking writers, recording the version of each cell owning a read key, and buffers
+/// updates. Committing the transaction exclusively locks the cells owning the read and updated keys by calling
+/// HashMap::lock_many, validates the recorded versions, and applies the updates only if no cell was modified in the
+/// meantime, therefore committed transactions are serializable without taking snapshots. A cell version changes
+/// whenever any key in the cell is inserted, removed, or mutably accessed, and relocating a key to a new array always
+/// fails validation, therefore a transaction may fail even if the keys it read were not modified.
+///
+/// It is !Send, thus disallowing other threads to have references to it.
+/// It keeps the thread pinned to prevent the arrays from being dropped while the Transaction is alive.
+/// Reading keys or committing the Transaction while holding an Accessor, Scanner or MultiAccessor in the thread poses
+/// a possibility of deadlock.
+pub struct Transaction<'a, K: Clone + Eq + Hash + Sync, V: Sync, H: BuildHasher> {
+    hash_map: &'a HashMap<K, V, H>,
+    guard: Guard,
+    reads: Vec<(K, CellVersion<K, V>)>,
+    /// The buffered updates, and None represents a removal.
+    writes: Vec<(K, Option<V>)>,
+}
+
+impl<'a, K: Clone + Eq + Hash + Sync, V: Sync, H: BuildHasher> Transaction<'a, K, V, H> {
+    pub(crate) fn new(hash_map: &'a HashMap<K, V, H>) -> Transaction<'a, K, V, H> {
+        Transaction {
+            hash_map,
+            guard: crossbeam_epoch::pin(),
+            reads: Vec::new(),
+            writes: Vec::new(),
+        }
+    }
+
+    /// Reads a key-value pair.
+    ///
+    /// The buffered update of the key is read if the key was updated by the transaction.
+    ///
+    /// # Examples
+    /// ```
+    /// use scc::HashMap;
+    /// use std::collections::hash_map::RandomState;
+    ///
+    /// let hashmap: HashMap<u64, u32, RandomState> = HashMap::new(RandomState::new(), None);
+    ///
+    /// assert!(hashmap.insert(1, 0).is_ok());
+    ///
+    /// let mut transaction = hashmap.transaction();
+    /// assert_eq!(transaction.read(&1, |_, value| *value), Some(0));
+    ///
+    /// transaction.upsert(1, 1);
+    /// assert_eq!(transaction.read(&1, |_, value| *value), Some(1));
+    ///
+    /// transaction.remove(1);
+    /// assert!(transaction.read(&1, |_, value| *value).is_none());
+    /// ```
+    pub fn read<U, F: FnOnce(&K, &V) -> U>(&mut self, key: &K, f: F) -> Option<U> {
+        if let Some((key, value)) = self.writes.iter().find(|(k, _)| k == key) {
+            return value.as_ref().map(|value| f(key, value));
+        }
+        let (result, cell_version) = self.hash_map.read_versioned(key, f, &self.guard);
+        if self.reads.iter().all(|(k, _)| k != key) {
+            self.reads.push((key.clone(), cell_version));
+        }
+        result
+    }
+
+    /// Buffers an update that inserts the key-value pair or replaces the value of the key.
+    ///
+    /// # Examples
+    /// ```
+    /// use scc::HashMap;
+    /// use std::collections::hash_map::RandomState;
+    ///
+    /// let hashmap: HashMap<u64, u32, RandomState> = HashMap::new(RandomState::new(), None);
+    ///
+    /// assert!(hashmap.insert(1, 0).is_ok());
+    ///
+    /// let mut transaction = hashmap.transaction();
+    /// transaction.upsert(1, 1);
+    /// transaction.upsert(2, 2);
+    /// assert_eq!(hashmap.read(1, |_, value| *value), Some(0));
+    ///
+    /// assert!(transaction.commit().is_ok());
+    /// assert_eq!(hashmap.read(1, |_, value| *value), Some(1));
+    /// assert_eq!(hashmap.read(2, |_, value| *value), Some(2));
+    /// ```
+    pub fn upsert(&mut self, key: K, value: V) {
+        self.write(key, Some(value));
+    }
+
+    /// Buffers an update that removes the key.
+    ///
+    /// # Examples
+    /// ```
+    /// use scc::HashMap;
+    /// use std::collections::hash_map::RandomState;
+    ///
+    /// let hashmap: HashMap<u64, u32, RandomState> = HashMap::new(RandomState::new(), None);
+    ///
+    /// assert!(hashmap.insert(1, 0).is_ok());
+    ///
+    /// let mut transaction = hashmap.transaction();
+    /// transaction.remove(1);
+    /// assert!(transaction.commit().is_ok());
+    ///
+    /// assert!(hashmap.read(1, |_, value| *value).is_none());
+    /// ```
+    pub fn remove(&mut self, key: K) {
+        self.write(key, None);
+    }
+
+    /// Commits the transaction.
+    ///
+    /// It returns a Conflict error without applying the buffered updates if a cell owning a key read by the
+    /// transaction has been modified since the key was read.
+    ///
+    /// # Examples
+    /// ```
+    /// use scc::{Conflict, HashMap};
+    /// use std::collections::hash_map::RandomState;
+    ///
+    /// let hashmap: HashMap<u64, u32, RandomState> = HashMap::new(RandomState::new(), None);
+    ///
+    /// assert!(hashmap.insert(1, 0).is_ok());
+    ///
+    /// let mut transaction = hashmap.transaction();
+    /// let value = transaction.read(&1, |_, value| *value).unwrap();
+    /// transaction.upsert(1, value + 1);
+    ///
+    /// *hashmap.get(1).unwrap().get().1 = 2;
+    ///
+    /// assert_eq!(transaction.commit(), Err(Conflict));
+    /// assert_eq!(hashmap.read(1, |_, value| *value), Some(2));
+    /// ```
+    pub fn commit(self) -> Result<(), Conflict> {
+        let keys: Vec<K> = self
+            .reads
+            .iter()
+            .map(|(key, _)| key)
+            .chain(self.writes.iter().map(|(key, _)| key))
+            .cloned()
+            .collect();
+        let mut multi_accessor = self.hash_map.lock_many(&keys);
+        if self
+            .reads
+            .iter()
+            .any(|(key, cell_version)| multi_accessor.cell_version(key) != *cell_version)
+        {
+            return Err(Conflict);
+        }
+        for (key, value) in self.writes {
+            match value {
+                Some(value) => multi_accessor.upsert(key, value),
+                None => {
+                    multi_accessor.remove(&key);
+                }
+            }
+        }
+        Ok(())
+    }
+
+    fn write(&mut self, key: K, value: Option<V>) {
+        if let Some(write) = self.writes.iter_mut().find(|(k, _)| *k == key) {
+            write.1 = value;
+        } else {
+            self.writes.push((key, value));
+        }
+    }
+}
@@ -0,0 +1,682 @@
+extern crate crossbeam_epoch;
+
+use crossbeam_epoch::{Atomic, Guard, Owned, Shared};
+use std::cmp::Ordering;
+use std::ops::{Bound, RangeBounds};
+use std::sync::atomic::AtomicBool;
+use std::sync::atomic::Ordering::{Acquire, Relaxed, Release};
+use std::sync::{Mutex, MutexGuard};
+
+/// The maximum number of key-value pairs a leaf node manages.
+const LEAF_CAPACITY: usize = 32;
+
+/// The maximum number of children an internal node manages.
+const NODE_CAPACITY: usize = 32;
+
+/// A concurrent ordered index implementation.
+///
+/// scc::TreeIndex is a concurrent B+-tree that is targeted at range scans and point lookups on a read-mostly workload.
+/// Internal nodes are immutable, and each leaf node publishes an immutable sorted array of key-value pairs;
+/// modifying a leaf node replaces the array while the leaf node is exclusively locked,
+/// and structural changes replace the nodes on the path from the root to the leaf node at once.
+/// The epoch-based reclamation technique provided by the crossbeam_epoch crate guarantees that the replaced nodes
+/// and arrays are not reclaimed while being read.
+///
+/// The key features of scc::TreeIndex.
+/// * Lock-free read: readers and scanners never acquire a lock.
+/// * Fine-grained locking: writers exclusively lock a single leaf node unless the leaf node is split or removed.
+/// * Consistent range scan: a Range returns key-value pairs in ascending order even if the tree is being modified.
+pub struct TreeIndex<K: Clone + Ord + Sync, V: Sync> {
+    root: Atomic<Node<K, V>>,
+    structure_mutex: Mutex<()>,
+}
+
+impl<K: Clone + Ord + Sync, V: Sync> TreeIndex<K, V> {
+    /// Creates an empty TreeIndex instance.
+    ///
+    /// # Examples
+    /// ```
+    /// use scc::TreeIndex;
+    ///
+    /// let treeindex: TreeIndex<u64, u32> = TreeIndex::new();
+    ///
+    /// let result = treeindex.read(1, |key, value| *value);
+    /// assert!(result.is_none());
+    /// ```
+    pub fn new() -> TreeIndex<K, V> {
+        TreeIndex {
+            root: Atomic::new(Node::Leaf(Leaf::new(LeafArray::default()))),
+            structure_mutex: Mutex::new(()),
+        }
+    }
+
+    /// Inserts a key-value pair into the TreeIndex.
+    ///
+    /// It returns the key-value pair if the key exists.
+    ///
+    /// # Examples
+    /// ```
+    /// use scc::TreeIndex;
+    ///
+    /// let treeindex: TreeIndex<u64, u32> = TreeIndex::new();
+    ///
+    /// let result = treeindex.insert(1, 0);
+    /// assert!(result.is_ok());
+    ///
+    /// let result = treeindex.insert(1, 1);
+    /// assert_eq!(result, Err((1, 1)));
+    /// ```
+    pub fn insert(&self, key: K, value: V) -> Result<(), (K, V)> {
+        let guard = crossbeam_epoch::pin();
+        loop {
+            let (leaf_ref, _) = self.search_leaf(Some(&key), &guard);
+            let lock = leaf_ref.mutex.lock().unwrap();
+            if leaf_ref.retired.load(Relaxed) {
+                continue;
+            }
+            let array_ref = unsafe { leaf_ref.array.load(Acquire, &guard).deref() };
+            match array_ref.search(&key, &guard) {
+                Ok(_) => return Err((key, value)),
+                Err(position) => {
+                    if array_ref.entries.len() >= LEAF_CAPACITY {
+                        drop(lock);
+                        self.split_leaf(&key, &guard);
+                        continue;
+                    }
+                    let mut new_array = array_ref.clone_entries(&guard);
+                    new_array
+                        .entries
+                        .insert(position, Atomic::new((key, value)));
+                    leaf_ref.replace(new_array, &guard);
+                    return Ok(());
+                }
+            }
+        }
+    }
+
+    /// Removes a key-value pair.
+    ///
+    /// # Examples
+    /// ```
+    /// use scc::TreeIndex;
+    ///
+    /// let treeindex: TreeIndex<u64, u32> = TreeIndex::new();
+    ///
+    /// let result = treeindex.remove(1);
+    /// assert!(!result);
+    ///
+    /// let result = treeindex.insert(1, 0);
+    /// assert!(result.is_ok());
+    ///
+    /// let result = treeindex.remove(1);
+    /// assert!(result);
+    /// ```
+    pub fn remove(&self, key: K) -> bool {
+        let guard = crossbeam_epoch::pin();
+        loop {
+            let (leaf_ref, _) = self.search_leaf(Some(&key), &guard);
+            let lock = leaf_ref.mutex.lock().unwrap();
+            if leaf_ref.retired.load(Relaxed) {
+                continue;
+            }
+            let array_ref = unsafe { leaf_ref.array.load(Acquire, &guard).deref() };
+            if let Ok(position) = array_ref.search(&key, &guard) {
+                let mut new_array = array_ref.clone_entries(&guard);
+                let removed_entry = new_array.entries.remove(position);
+                let empty = new_array.entries.is_empty();
+                leaf_ref.replace(new_array, &guard);
+                unsafe { guard.defer_destroy(removed_entry.load(Relaxed, &guard)) };
+                drop(lock);
+                if empty {
+                    self.remove_leaf(&key, &guard);
+                }
+                return true;
+            }
+            return false;
+        }
+    }
+
+    /// Reads a key-value pair.
+    ///
+    /// It does not acquire a lock.
+    ///
+    /// # Examples
+    /// ```
+    /// use scc::TreeIndex;
+    ///
+    /// let treeindex: TreeIndex<u64, u32> = TreeIndex::new();
+    ///
+    /// let result = treeindex.insert(1, 10);
+    /// assert!(result.is_ok());
+    ///
+    /// let result = treeindex.read(1, |key, value| *value);
+    /// assert_eq!(result.unwrap(), 10);
+    /// ```
+    pub fn read<U, F: FnOnce(&K, &V) -> U>(&self, key: K, f: F) -> Option<U> {
+        let guard = crossbeam_epoch::pin();
+        let (leaf_ref, _) = self.search_leaf(Some(&key), &guard);
+        let array_ref = unsafe { leaf_ref.array.load(Acquire, &guard).deref() };
+        array_ref.search(&key, &guard).ok().map(|position| {
+            let entry_ref = unsafe { array_ref.entries[position].load(Relaxed, &guard).deref() };
+            f(&entry_ref.0, &entry_ref.1)
+        })
+    }
+
+    /// Returns a Range that scans the key-value pairs in the given range in ascending order.
+    ///
+    /// A Range never returns the same key twice, and it returns all the key-value pairs that had been in the range
+    /// and were not removed while being scanned. The returned references are valid as long as the Guard is held.
+    ///
+    /// # Examples
+    /// ```
+    /// use scc::TreeIndex;
+    ///
+    /// let treeindex: TreeIndex<u64, u32> = TreeIndex::new();
+    ///
+    /// for key in 0..16 {
+    ///     assert!(treeindex.insert(key, key as u32).is_ok());
+    /// }
+    ///
+    /// let guard = scc::pin();
+    /// let result: Vec<u64> = treeindex.range(4..8, &guard).map(|(key, _)| *key).collect();
+    /// assert_eq!(result, vec![4, 5, 6, 7]);
+    ///
+    /// let result = treeindex.range(14.., &guard).count();
+    /// assert_eq!(result, 2);
+    /// ```
+    pub fn range<'g, R: RangeBounds<K>>(
+        &'g self,
+        range: R,
+        guard: &'g Guard,
+    ) -> Range<'g, K, V, R> {
+        Range {
+            tree_index: self,
+            range,
+            guard,
+            array: Shared::null(),
+            index: 0,
+            upper_bound: None,
+            last_key: None,
+            started: false,
+        }
+    }
+
+    /// Returns the number of key-value pairs in the TreeIndex.
+    ///
+    /// It scans the whole TreeIndex.
+    ///
+    /// # Examples
+    /// ```
+    /// use scc::TreeIndex;
+    ///
+    /// let treeindex: TreeIndex<u64, u32> = TreeIndex::new();
+    ///
+    /// assert!(treeindex.insert(1, 0).is_ok());
+    /// assert!(treeindex.insert(2, 0).is_ok());
+    ///
+    /// let result = treeindex.len();
+    /// assert_eq!(result, 2);
+    /// ```
+    pub fn len(&self) -> usize {
+        let guard = crossbeam_epoch::pin();
+        self.range(.., &guard).count()
+    }
+
+    /// Checks if the TreeIndex is empty.
+    ///
+    /// # Examples
+    /// ```
+    /// use scc::TreeIndex;
+    ///
+    /// let treeindex: TreeIndex<u64, u32> = TreeIndex::new();
+    /// assert!(treeindex.is_empty());
+    ///
+    /// assert!(treeindex.insert(1, 0).is_ok());
+    /// assert!(!treeindex.is_empty());
+    /// ```
+    pub fn is_empty(&self) -> bool {
+        let guard = crossbeam_epoch::pin();
+        self.range(.., &guard).next().is_none()
+    }
+
+    /// Clears all the key-value pairs.
+    ///
+    /// It returns the number of removed key-value pairs.
+    ///
+    /// # Examples
+    /// ```
+    /// use scc::TreeIndex;
+    ///
+    /// let treeindex: TreeIndex<u64, u32> = TreeIndex::new();
+    ///
+    /// assert!(treeindex.insert(1, 0).is_ok());
+    /// assert!(treeindex.insert(2, 0).is_ok());
+    ///
+    /// let result = treeindex.clear();
+    /// assert_eq!(result, 2);
+    /// assert!(treeindex.is_empty());
+    /// ```
+    pub fn clear(&self) -> usize {
+        let guard = crossbeam_epoch::pin();
+        let keys: Vec<K> = self.range(.., &guard).map(|(key, _)| key.clone()).collect();
+        keys.into_iter()
+            .filter(|key| self.remove(key.clone()))
+            .count()
+    }
+
+    /// Searches the leaf node that is supposed to own the key.
+    ///
+    /// It returns the leftmost leaf node if no key is given, and the smallest separator key greater than the key.
+    fn search_leaf<'g>(
+        &self,
+        key: Option<&K>,
+        guard: &'g Guard,
+    ) -> (&'g Leaf<K, V>, Option<&'g K>) {
+        let mut upper_bound = None;
+        let mut node = self.root.load(Acquire, guard);
+        loop {
+            match unsafe { node.deref() } {
+                Node::Internal(internal_node) => {
+                    let child_index = key.map_or(0, |key| internal_node.child_index(key));
+                    if child_index < internal_node.bounds.len() {
+                        upper_bound = Some(&internal_node.bounds[child_index]);
+                    }
+                    node = internal_node.children[child_index].load(Acquire, guard);
+                }
+                Node::Leaf(leaf) => return (leaf, upper_bound),
+            }
+        }
+    }
+
+    /// Returns the path from the root to the leaf node that is supposed to own the key.
+    ///
+    /// The structure mutex must be held.
+    fn search_path<'g>(&self, key: &K, guard: &'g Guard) -> Vec<(Shared<'g, Node<K, V>>, usize)> {
+        let mut path = Vec::new();
+        let mut node = self.root.load(Acquire, guard);
+        loop {
+            match unsafe { node.deref() } {
+                Node::Internal(internal_node) => {
+                    let child_index = internal_node.child_index(key);
+                    path.push((node, child_index));
+                    node = internal_node.children[child_index].load(Acquire, guard);
+                }
+                Node::Leaf(_) => {
+                    path.push((node, 0));
+                    return path;
+                }
+            }
+        }
+    }
+
+    /// Splits the full leaf node that is supposed to own the key.
+    fn split_leaf(&self, key: &K, guard: &Guard) {
+        let _structure_lock = self.structure_mutex.lock().unwrap();
+        let mut path = self.search_path(key, guard);
+        let (leaf_ptr, _) = path.pop().unwrap();
+        let leaf_ref = unsafe { leaf_ptr.deref() }.leaf();
+        let leaf_lock = leaf_ref.mutex.lock().unwrap();
+        let array = leaf_ref.array.load(Acquire, guard);
+        let array_ref = unsafe { array.deref() };
+        if array_ref.entries.len() < LEAF_CAPACITY {
+            return;
+        }
+
+        // split the array into halves
+        let mut left_array = array_ref.clone_entries(guard);
+        let right_entries = left_array.entries.split_off(LEAF_CAPACITY / 2);
+        let separator = unsafe { right_entries[0].load(Relaxed, guard).deref() }
+            .0
+            .clone();
+        let left = Owned::new(Node::Leaf(Leaf::new(left_array)));
+        let right = Owned::new(Node::Leaf(Leaf::new(LeafArray {
+            entries: right_entries,
+        })));
+
+        // build new internal nodes on the path
+        let mut replacement = vec![(left.into_shared(guard), None)];
+        replacement.push((right.into_shared(guard), Some(separator)));
+        self.replace_path(path, replacement, leaf_ref, leaf_lock, leaf_ptr, guard);
+    }
+
+    /// Removes the empty leaf node that is supposed to own the key.
+    fn remove_leaf(&self, key: &K, guard: &Guard) {
+        let _structure_lock = self.structure_mutex.lock().unwrap();
+        let mut path = self.search_path(key, guard);
+        if path.len() == 1 {
+            // the root leaf node is never removed
+            return;
+        }
+        let (leaf_ptr, _) = path.pop().unwrap();
+        let leaf_ref = unsafe { leaf_ptr.deref() }.leaf();
+        let leaf_lock = leaf_ref.mutex.lock().unwrap();
+        if !unsafe { leaf_ref.array.load(Acquire, guard).deref() }
+            .entries
+            .is_empty()
+        {
+            return;
+        }
+        self.replace_path(path, Vec::new(), leaf_ref, leaf_lock, leaf_ptr, guard);
+    }
+
+    /// Replaces the locked leaf node with the given nodes, and publishes a new root.
+    ///
+    /// Each replacement node is accompanied by the separator key between it and the previous node.
+    fn replace_path<'g>(
+        &self,
+        mut path: Vec<(Shared<'g, Node<K, V>>, usize)>,
+        mut replacement: Vec<Replacement<'g, K, V>>,
+        leaf_ref: &Leaf<K, V>,
+        leaf_lock: MutexGuard<()>,
+        leaf_ptr: Shared<'g, Node<K, V>>,
+        guard: &'g Guard,
+    ) {
+        let mut retired_nodes = vec![leaf_ptr];
+        while let Some((node_ptr, child_index)) = path.pop() {
+            let internal_node = unsafe { node_ptr.deref() }.internal();
+            retired_nodes.push(node_ptr);
+            let mut bounds = internal_node.bounds.clone();
+            let mut children: Vec<Atomic<Node<K, V>>> = internal_node
+                .children
+                .iter()
+                .map(|child| Atomic::from(child.load(Acquire, guard)))
+                .collect();
+
+            // replace the child with the replacement nodes
+            children.remove(child_index);
+            if replacement.is_empty() {
+                if child_index < bounds.len() {
+                    bounds.remove(child_index);
+                } else if child_index > 0 {
+                    bounds.remove(child_index - 1);
+                }
+            }
+            for (offset, (node, separator)) in replacement.into_iter().enumerate() {
+                children.insert(child_index + offset, Atomic::from(node));
+                if let Some(separator) = separator {
+                    bounds.insert(child_index + offset - 1, separator);
+                }
+            }
+
+            replacement = if children.is_empty() {
+                Vec::new()
+            } else if children.len() > NODE_CAPACITY {
+                let right_children = children.split_off(children.len() / 2);
+                let right_bounds = bounds.split_off(children.len());
+                let separator = bounds.pop();
+                let left = Owned::new(Node::Internal(InternalNode { bounds, children }));
+                let right = Owned::new(Node::Internal(InternalNode {
+                    bounds: right_bounds,
+                    children: right_children,
+                }));
+                vec![
+                    (left.into_shared(guard), None),
+                    (right.into_shared(guard), separator),
+                ]
+            } else {
+                let node = Owned::new(Node::Internal(InternalNode { bounds, children }));
+                vec![(node.into_shared(guard), None)]
+            };
+        }
+
+        // build a new root
+        let new_root = match replacement.len() {
+            0 => Owned::new(Node::Leaf(Leaf::new(LeafArray::default()))).into_shared(guard),
+            1 => {
+                // collapse internal nodes having a single child
+                let mut root = replacement.pop().unwrap().0;
+                while let Node::Internal(internal_node) = unsafe { root.deref() } {
+                    if internal_node.children.len() != 1 {
+                        break;
+                    }
+                    let child = internal_node.children[0].load(Acquire, guard);
+                    unsafe { guard.defer_destroy(root) };
+                    root = child;
+                }
+                root
+            }
+            _ => {
+                let mut bounds = Vec::with_capacity(replacement.len() - 1);
+                let mut children = Vec::with_capacity(replacement.len());
+                for (node, separator) in replacement {
+                    if let Some(separator) = separator {
+                        bounds.push(separator);
+                    }
+                    children.push(Atomic::from(node));
+                }
+                Owned::new(Node::Internal(InternalNode { bounds, children })).into_shared(guard)
+            }
+        };
+        self.root.store(new_root, Release);
+
+        // writers waiting for the leaf node retry after the new root is published
+        leaf_ref.retired.store(true, Relaxed);
+        drop(leaf_lock);
+        for node in retired_nodes {
+            if let Node::Leaf(leaf) = unsafe { node.deref() } {
+                unsafe { guard.defer_destroy(leaf.array.load(Relaxed, guard)) };
+            }
+            unsafe { guard.defer_destroy(node) };
+        }
+    }
+}
+
+impl<K: Clone + Ord + Sync, V: Sync> Default for TreeIndex<K, V> {
+    fn default() -> Self {
+        TreeIndex::new()
+    }
+}
+
+impl<K: Clone + Ord + Sync, V: Sync> Drop for TreeIndex<K, V> {
+    fn drop(&mut self) {
+        let guard = unsafe { crossbeam_epoch::unprotected() };
+        let mut nodes = vec![self.root.swap(Shared::null(), Relaxed, guard)];
+        while let Some(node) = nodes.pop() {
+            match unsafe { node.deref() } {
+                Node::Internal(internal_node) => {
+                    for child in internal_node.children.iter() {
+                        nodes.push(child.load(Relaxed, guard));
+                    }
+                }
+                Node::Leaf(leaf) => {
+                    let array = leaf.array.load(Relaxed, guard);
+                    for entry in unsafe { array.deref() }.entries.iter() {
+                        drop(unsafe { entry.load(Relaxed, guard).into_owned() });
+                    }
+                    drop(unsafe { array.into_owned() });
+                }
+            }
+            drop(unsafe { node.into_owned() });
+        }
+    }
+}
+
+/// Range scans the key-value pairs in a range.
+///
+/// It re-locates the next leaf node by searching the TreeIndex for the separator key that bounds the current leaf node,
+/// therefore it remains valid even if leaf nodes are split or removed.
+pub struct Range<'g, K: Clone + Ord + Sync, V: Sync, R: RangeBounds<K>> {
+    tree_index: &'g TreeIndex<K, V>,
+    range: R,
+    guard: &'g Guard,
+    array: Shared<'g, LeafArray<K, V>>,
+    index: usize,
+    upper_bound: Option<&'g K>,
+    last_key: Option<&'g K>,
+    started: bool,
+}
+
+impl<'g, K: Clone + Ord + Sync, V: Sync, R: RangeBounds<K>> Range<'g, K, V, R> {
+    /// Checks if the key is beyond the end of the range.
+    fn beyond_end(&self, key: &K) -> bool {
+        match self.range.end_bound() {
+            Bound::Included(end) => key > end,
+            Bound::Excluded(end) => key >= end,
+            Bound::Unbounded => false,
+        }
+    }
+
+    /// Checks if the key is before the start of the range or the last returned key.
+    fn before_start(&self, key: &K) -> bool {
+        if let Some(last_key) = self.last_key {
+            if key <= last_key {
+                return true;
+            }
+        }
+        match self.range.start_bound() {
+            Bound::Included(start) => key < start,
+            Bound::Excluded(start) => key <= start,
+            Bound::Unbounded => false,
+        }
+    }
+}
+
+impl<'g, K: Clone + Ord + Sync, V: Sync, R: RangeBounds<K>> Iterator for Range<'g, K, V, R> {
+    type Item = (&'g K, &'g V);
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            if !self.array.is_null() {
+                let array_ref = unsafe { self.array.deref() };
+                while self.index < array_ref.entries.len() {
+                    let entry_ref = unsafe {
+                        array_ref.entries[self.index]
+                            .load(Relaxed, self.guard)
+                            .deref()
+                    };
+                    self.index += 1;
+                    if self.before_start(&entry_ref.0) {
+                        continue;
+                    }
+                    if self.beyond_end(&entry_ref.0) {
+                        self.array = Shared::null();
+                        self.upper_bound = None;
+                        return None;
+                    }
+                    self.last_key = Some(&entry_ref.0);
+                    return Some((&entry_ref.0, &entry_ref.1));
+                }
+            }
+
+            // locate the next leaf node
+            let search_key = if !self.started {
+                self.started = true;
+                match self.range.start_bound() {
+                    Bound::Included(start) | Bound::Excluded(start) => Some(start),
+                    Bound::Unbounded => None,
+                }
+            } else if let Some(upper_bound) = self.upper_bound {
+                if self.beyond_end(upper_bound) {
+                    return None;
+                }
+                Some(upper_bound)
+            } else {
+                return None;
+            };
+            let (leaf_ref, upper_bound) = self.tree_index.search_leaf(search_key, self.guard);
+            self.array = leaf_ref.array.load(Acquire, self.guard);
+            self.index = 0;
+            self.upper_bound = upper_bound;
+        }
+    }
+}
+
+/// A replacement node accompanied by the separator key between it and the previous replacement node.
+type Replacement<'g, K, V> = (Shared<'g, Node<K, V>>, Option<K>);
+
+enum Node<K: Clone + Ord + Sync, V: Sync> {
+    Internal(InternalNode<K, V>),
+    Leaf(Leaf<K, V>),
+}
+
+impl<K: Clone + Ord + Sync, V: Sync> Node<K, V> {
+    fn internal(&self) -> &InternalNode<K, V> {
+        match self {
+            Node::Internal(internal_node) => internal_node,
+            Node::Leaf(_) => unreachable!(),
+        }
+    }
+
+    fn leaf(&self) -> &Leaf<K, V> {
+        match self {
+            Node::Internal(_) => unreachable!(),
+            Node::Leaf(leaf) => leaf,
+        }
+    }
+}
+
+/// InternalNode is immutable once published.
+///
+/// The i-th child owns keys that are less than the i-th bound, and not less than the (i - 1)-th bound.
+/// Dropping an InternalNode does not drop the children.
+struct InternalNode<K: Clone + Ord + Sync, V: Sync> {
+    bounds: Vec<K>,
+    children: Vec<Atomic<Node<K, V>>>,
+}
+
+impl<K: Clone + Ord + Sync, V: Sync> InternalNode<K, V> {
+    fn child_index(&self, key: &K) -> usize {
+        match self.bounds.binary_search(key) {
+            Ok(index) => index + 1,
+            Err(index) => index,
+        }
+    }
+}
+
+/// Leaf publishes an immutable LeafArray, and serializes writers.
+struct Leaf<K: Clone + Ord + Sync, V: Sync> {
+    mutex: Mutex<()>,
+    retired: AtomicBool,
+    array: Atomic<LeafArray<K, V>>,
+}
+
+impl<K: Clone + Ord + Sync, V: Sync> Leaf<K, V> {
+    fn new(array: LeafArray<K, V>) -> Leaf<K, V> {
+        Leaf {
+            mutex: Mutex::new(()),
+            retired: AtomicBool::new(false),
+            array: Atomic::new(array),
+        }
+    }
+
+    /// Publishes the new array, and reclaims the old one.
+    ///
+    /// The leaf node must be locked.
+    fn replace(&self, new_array: LeafArray<K, V>, guard: &Guard) {
+        let old_array = self.array.swap(Owned::new(new_array), Release, guard);
+        unsafe { guard.defer_destroy(old_array) };
+    }
+}
+
+/// LeafArray is an immutable sorted array of key-value pairs.
+///
+/// Dropping a LeafArray does not drop the key-value pairs.
+struct LeafArray<K: Clone + Ord + Sync, V: Sync> {
+    entries: Vec<Atomic<(K, V)>>,
+}
+
+impl<K: Clone + Ord + Sync, V: Sync> LeafArray<K, V> {
+    fn search(&self, key: &K, guard: &Guard) -> Result<usize, usize> {
+        self.entries.binary_search_by(|entry| -> Ordering {
+            unsafe { entry.load(Relaxed, guard).deref() }.0.cmp(key)
+        })
+    }
+
+    /// Makes a copy of the LeafArray sharing the key-value pairs.
+    fn clone_entries(&self, guard: &Guard) -> LeafArray<K, V> {
+        LeafArray {
+            entries: self
+                .entries
+                .iter()
+                .map(|entry| Atomic::from(entry.load(Relaxed, guard)))
+                .collect(),
+        }
+    }
+}
+
+impl<K: Clone + Ord + Sync, V: Sync> Default for LeafArray<K, V> {
+    fn default() -> Self {
+        LeafArray {
+            entries: Vec::new(),
+        }
+    }
+}
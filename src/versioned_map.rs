@@ -0,0 +1,441 @@
+use super::map::HashMap;
+use crossbeam_epoch::{Atomic, Guard, Owned, Shared};
+use std::collections::hash_map::RandomState;
+use std::hash::{BuildHasher, Hash};
+use std::sync::atomic::AtomicU64;
+use std::sync::atomic::Ordering::{Acquire, Relaxed, Release, SeqCst};
+
+/// A multi-version concurrent map implementation.
+///
+/// scc::VersionedMap is built on scc::HashMap, and each key owns a chain of versions ordered from the newest to the
+/// oldest. A version consists of a commit timestamp and a value, or a tombstone if the key was removed. Writers
+/// exclusively lock the cell owning the key, draw a commit timestamp from the logical clock of the VersionedMap, and
+/// prepend a new version to the chain, and readers search the chain for the newest version that is not newer than
+/// the read timestamp under a shared lock on the cell. The commit timestamp is drawn while the cell is locked,
+/// therefore a reader never misses a version committed at or before its read timestamp.
+///
+/// Versions that no active Snapshot can read are pruned by VersionedMap::gc, and the epoch-based reclamation technique
+/// provided by the crossbeam_epoch crate guarantees that a pruned version is not reclaimed while being read.
+///
+/// The key features of scc::VersionedMap.
+/// * Snapshot reads: a Snapshot reads the state of the VersionedMap as of the time it was taken.
+/// * Non-blocking versioning: writers never wait for readers of older versions.
+/// * Garbage collection driven by the oldest active Snapshot.
+pub struct VersionedMap<K: Eq + Hash + Sync, V: Send + Sync, H: BuildHasher> {
+    map: HashMap<K, VersionChain<V>, H>,
+    /// The commit timestamp of the latest version.
+    clock: AtomicU64,
+    /// The number of active snapshots per read timestamp.
+    snapshots: HashMap<u64, usize, RandomState>,
+    /// The greatest commit timestamp that garbage collection has considered.
+    watermark: AtomicU64,
+}
+
+impl<K: Eq + Hash + Sync, V: Send + Sync, H: BuildHasher> VersionedMap<K, V, H> {
+    /// Creates an empty VersionedMap instance with the given hasher and minimum capacity.
+    ///
+    /// # Examples
+    /// ```
+    /// use scc::VersionedMap;
+    /// use std::collections::hash_map::RandomState;
+    ///
+    /// let versionedmap: VersionedMap<u64, u32, RandomState> =
+    ///     VersionedMap::new(RandomState::new(), Some(1000));
+    ///
+    /// let result = versionedmap.capacity();
+    /// assert_eq!(result, 1024);
+    /// ```
+    pub fn new(hasher: H, minimum_capacity: Option<usize>) -> VersionedMap<K, V, H> {
+        VersionedMap {
+            map: HashMap::new(hasher, minimum_capacity),
+            clock: AtomicU64::new(0),
+            snapshots: HashMap::new(RandomState::new(), None),
+            watermark: AtomicU64::new(0),
+        }
+    }
+
+    /// Inserts a key-value pair or a new version of the key, and returns the commit timestamp of the version.
+    ///
+    /// # Examples
+    /// ```
+    /// use scc::VersionedMap;
+    /// use std::collections::hash_map::RandomState;
+    ///
+    /// let versionedmap: VersionedMap<u64, u32, RandomState> = VersionedMap::new(RandomState::new(), None);
+    ///
+    /// let result = versionedmap.upsert(1, 0);
+    /// assert_eq!(result, 1);
+    ///
+    /// let result = versionedmap.upsert(1, 1);
+    /// assert_eq!(result, 2);
+    /// ```
+    pub fn upsert(&self, key: K, value: V) -> u64 {
+        let accessor = match self.map.insert(key, VersionChain::default()) {
+            Ok(accessor) => accessor,
+            Err((accessor, _)) => accessor,
+        };
+        self.commit(accessor.get().1, Some(value))
+    }
+
+    /// Removes the key by inserting a tombstone version, and returns the commit timestamp of the tombstone.
+    ///
+    /// It returns None if the key does not exist.
+    ///
+    /// # Examples
+    /// ```
+    /// use scc::VersionedMap;
+    /// use std::collections::hash_map::RandomState;
+    ///
+    /// let versionedmap: VersionedMap<u64, u32, RandomState> = VersionedMap::new(RandomState::new(), None);
+    ///
+    /// let result = versionedmap.remove(1);
+    /// assert!(result.is_none());
+    ///
+    /// assert_eq!(versionedmap.upsert(1, 0), 1);
+    ///
+    /// let result = versionedmap.remove(1);
+    /// assert_eq!(result, Some(2));
+    ///
+    /// let result = versionedmap.remove(1);
+    /// assert!(result.is_none());
+    /// ```
+    pub fn remove(&self, key: K) -> Option<u64> {
+        let accessor = self.map.get(key)?;
+        let chain = accessor.get().1;
+        let guard = crossbeam_epoch::pin();
+        match chain.latest(&guard) {
+            Some(version) if version.value.is_some() => Some(self.commit(chain, None)),
+            _ => None,
+        }
+    }
+
+    /// Reads the newest version of the key that is not newer than the given timestamp.
+    ///
+    /// The returned reference is valid as long as the given Guard is held. The timestamp is supposed to be in the range
+    /// of the timestamps of the active snapshots and the current timestamp, otherwise the version that was visible at
+    /// the timestamp may have been pruned.
+    ///
+    /// # Examples
+    /// ```
+    /// use scc::VersionedMap;
+    /// use std::collections::hash_map::RandomState;
+    ///
+    /// let versionedmap: VersionedMap<u64, u32, RandomState> = VersionedMap::new(RandomState::new(), None);
+    ///
+    /// assert_eq!(versionedmap.upsert(1, 0), 1);
+    /// assert_eq!(versionedmap.upsert(1, 1), 2);
+    /// assert_eq!(versionedmap.remove(1), Some(3));
+    ///
+    /// let guard = scc::pin();
+    /// assert!(versionedmap.read_at(1, 0, &guard).is_none());
+    /// assert_eq!(versionedmap.read_at(1, 1, &guard), Some(&0));
+    /// assert_eq!(versionedmap.read_at(1, 2, &guard), Some(&1));
+    /// assert!(versionedmap.read_at(1, 3, &guard).is_none());
+    /// ```
+    pub fn read_at<'g>(&self, key: K, timestamp: u64, guard: &'g Guard) -> Option<&'g V> {
+        self.map
+            .read(key, |_, chain| chain.visible(timestamp, guard))
+            .flatten()
+            .and_then(|version| version.value.as_ref())
+    }
+
+    /// Takes a snapshot of the VersionedMap.
+    ///
+    /// Versions visible to the Snapshot are not pruned until the Snapshot is dropped.
+    ///
+    /// # Examples
+    /// ```
+    /// use scc::VersionedMap;
+    /// use std::collections::hash_map::RandomState;
+    ///
+    /// let versionedmap: VersionedMap<u64, u32, RandomState> = VersionedMap::new(RandomState::new(), None);
+    ///
+    /// assert_eq!(versionedmap.upsert(1, 0), 1);
+    ///
+    /// let snapshot = versionedmap.snapshot();
+    /// assert_eq!(snapshot.timestamp(), 1);
+    ///
+    /// assert_eq!(versionedmap.upsert(1, 1), 2);
+    /// assert_eq!(versionedmap.gc(), 0);
+    ///
+    /// let guard = scc::pin();
+    /// assert_eq!(snapshot.read(1, &guard), Some(&0));
+    /// ```
+    pub fn snapshot<'a>(&'a self) -> Snapshot<'a, K, V, H> {
+        loop {
+            let timestamp = self.clock.load(SeqCst);
+            match self.snapshots.insert(timestamp, 1) {
+                Ok(_) => (),
+                Err((accessor, _)) => *accessor.get().1 += 1,
+            }
+
+            // garbage collection that has yet to consider the timestamp is guaranteed to see the registered snapshot
+            if self.watermark.load(SeqCst) <= timestamp {
+                return Snapshot {
+                    versioned_map: self,
+                    timestamp,
+                };
+            }
+            self.release(timestamp);
+        }
+    }
+
+    /// Returns the commit timestamp of the latest version.
+    ///
+    /// # Examples
+    /// ```
+    /// use scc::VersionedMap;
+    /// use std::collections::hash_map::RandomState;
+    ///
+    /// let versionedmap: VersionedMap<u64, u32, RandomState> = VersionedMap::new(RandomState::new(), None);
+    ///
+    /// assert_eq!(versionedmap.now(), 0);
+    /// assert_eq!(versionedmap.upsert(1, 0), 1);
+    /// assert_eq!(versionedmap.now(), 1);
+    /// ```
+    pub fn now(&self) -> u64 {
+        self.clock.load(SeqCst)
+    }
+
+    /// Prunes the versions that no active snapshot can read, and returns the number of pruned versions.
+    ///
+    /// For each key, the newest version that the oldest active snapshot can read is retained along with the newer
+    /// versions, and a key of which the only retained version is a tombstone is removed.
+    ///
+    /// # Examples
+    /// ```
+    /// use scc::VersionedMap;
+    /// use std::collections::hash_map::RandomState;
+    ///
+    /// let versionedmap: VersionedMap<u64, u32, RandomState> = VersionedMap::new(RandomState::new(), None);
+    ///
+    /// assert_eq!(versionedmap.upsert(1, 0), 1);
+    /// assert_eq!(versionedmap.upsert(1, 1), 2);
+    /// assert_eq!(versionedmap.upsert(2, 0), 3);
+    /// assert_eq!(versionedmap.remove(2), Some(4));
+    ///
+    /// let result = versionedmap.gc();
+    /// assert_eq!(result, 3);
+    /// assert_eq!(versionedmap.len(|capacity| capacity), 1);
+    /// ```
+    pub fn gc(&self) -> usize {
+        // the watermark is published before the snapshots are examined, refer to VersionedMap::snapshot
+        let timestamp = self.clock.load(SeqCst);
+        self.watermark.fetch_max(timestamp, SeqCst);
+        let oldest = self
+            .snapshots
+            .iter()
+            .map(|(snapshot_timestamp, _)| *snapshot_timestamp)
+            .fold(timestamp, u64::min);
+
+        let guard = crossbeam_epoch::pin();
+        let num_pruned = std::cell::Cell::new(0);
+        self.map.retain(|_, chain| {
+            let (num_pruned_versions, retain) = chain.prune(oldest, &guard);
+            num_pruned.set(num_pruned.get() + num_pruned_versions);
+            retain
+        });
+        num_pruned.get()
+    }
+
+    /// Returns an estimated number of keys in the VersionedMap, including the keys that have been removed but not
+    /// garbage-collected.
+    ///
+    /// It passes the capacity of the VersionedMap to the given function.
+    ///
+    /// # Examples
+    /// ```
+    /// use scc::VersionedMap;
+    /// use std::collections::hash_map::RandomState;
+    ///
+    /// let versionedmap: VersionedMap<u64, u32, RandomState> = VersionedMap::new(RandomState::new(), None);
+    ///
+    /// assert_eq!(versionedmap.upsert(1, 0), 1);
+    /// assert_eq!(versionedmap.upsert(1, 1), 2);
+    ///
+    /// let result = versionedmap.len(|capacity| capacity);
+    /// assert_eq!(result, 1);
+    /// ```
+    pub fn len<F: FnOnce(usize) -> usize>(&self, f: F) -> usize {
+        self.map.len(f)
+    }
+
+    /// Returns the capacity of the VersionedMap.
+    ///
+    /// # Examples
+    /// ```
+    /// use scc::VersionedMap;
+    /// use std::collections::hash_map::RandomState;
+    ///
+    /// let versionedmap: VersionedMap<u64, u32, RandomState> =
+    ///     VersionedMap::new(RandomState::new(), Some(1000000));
+    ///
+    /// let result = versionedmap.capacity();
+    /// assert_eq!(result, 1048576);
+    /// ```
+    pub fn capacity(&self) -> usize {
+        self.map.capacity()
+    }
+
+    /// Prepends a new version to the chain while the cell owning the key is exclusively locked.
+    fn commit(&self, chain: &VersionChain<V>, value: Option<V>) -> u64 {
+        let guard = crossbeam_epoch::pin();
+        let commit_timestamp = self.clock.fetch_add(1, SeqCst) + 1;
+        let version = Owned::new(Version {
+            commit_timestamp,
+            value,
+            next: Atomic::from(chain.head.load(Relaxed, &guard)),
+        });
+        chain.head.store(version, Release);
+        commit_timestamp
+    }
+
+    /// Unregisters a snapshot.
+    fn release(&self, timestamp: u64) {
+        if let Some(accessor) = self.snapshots.get(timestamp) {
+            let count = accessor.get().1;
+            *count -= 1;
+            if *count == 0 {
+                accessor.erase();
+            }
+        }
+    }
+}
+
+/// Snapshot is a consistent view of a VersionedMap as of the time it was taken.
+///
+/// The versions visible to the Snapshot are not pruned while the Snapshot is alive.
+pub struct Snapshot<'a, K: Eq + Hash + Sync, V: Send + Sync, H: BuildHasher> {
+    versioned_map: &'a VersionedMap<K, V, H>,
+    timestamp: u64,
+}
+
+impl<'a, K: Eq + Hash + Sync, V: Send + Sync, H: BuildHasher> Snapshot<'a, K, V, H> {
+    /// Returns the read timestamp of the Snapshot.
+    ///
+    /// # Examples
+    /// ```
+    /// use scc::VersionedMap;
+    /// use std::collections::hash_map::RandomState;
+    ///
+    /// let versionedmap: VersionedMap<u64, u32, RandomState> = VersionedMap::new(RandomState::new(), None);
+    ///
+    /// assert_eq!(versionedmap.upsert(1, 0), 1);
+    ///
+    /// let snapshot = versionedmap.snapshot();
+    /// assert_eq!(snapshot.timestamp(), 1);
+    /// ```
+    pub fn timestamp(&self) -> u64 {
+        self.timestamp
+    }
+
+    /// Reads the version of the key that was the latest when the Snapshot was taken.
+    ///
+    /// The returned reference is valid as long as the given Guard is held.
+    ///
+    /// # Examples
+    /// ```
+    /// use scc::VersionedMap;
+    /// use std::collections::hash_map::RandomState;
+    ///
+    /// let versionedmap: VersionedMap<u64, u32, RandomState> = VersionedMap::new(RandomState::new(), None);
+    ///
+    /// assert_eq!(versionedmap.upsert(1, 0), 1);
+    ///
+    /// let snapshot = versionedmap.snapshot();
+    /// assert_eq!(versionedmap.remove(1), Some(2));
+    ///
+    /// let guard = scc::pin();
+    /// assert_eq!(snapshot.read(1, &guard), Some(&0));
+    /// assert!(versionedmap.read_at(1, versionedmap.now(), &guard).is_none());
+    /// ```
+    pub fn read<'g>(&self, key: K, guard: &'g Guard) -> Option<&'g V> {
+        self.versioned_map.read_at(key, self.timestamp, guard)
+    }
+}
+
+impl<'a, K: Eq + Hash + Sync, V: Send + Sync, H: BuildHasher> Drop for Snapshot<'a, K, V, H> {
+    fn drop(&mut self) {
+        self.versioned_map.release(self.timestamp);
+    }
+}
+
+/// Version is a value of a key committed at a certain time, and None represents that the key was removed.
+struct Version<V> {
+    commit_timestamp: u64,
+    value: Option<V>,
+    next: Atomic<Version<V>>,
+}
+
+/// VersionChain is the list of the versions of a key, ordered from the newest to the oldest.
+///
+/// The chain is only modified while the cell owning the key is exclusively locked, and a removed version is reclaimed
+/// only when no thread can hold a reference to it.
+struct VersionChain<V> {
+    head: Atomic<Version<V>>,
+}
+
+impl<V> VersionChain<V> {
+    fn latest<'g>(&self, guard: &'g Guard) -> Option<&'g Version<V>> {
+        unsafe { self.head.load(Acquire, guard).as_ref() }
+    }
+
+    /// Returns the newest version that is not newer than the given timestamp.
+    fn visible<'g>(&self, timestamp: u64, guard: &'g Guard) -> Option<&'g Version<V>> {
+        let mut current = self.latest(guard);
+        while let Some(version) = current {
+            if version.commit_timestamp <= timestamp {
+                return Some(version);
+            }
+            current = unsafe { version.next.load(Acquire, guard).as_ref() };
+        }
+        None
+    }
+
+    /// Prunes the versions older than the version visible at the given timestamp.
+    ///
+    /// It returns the number of pruned versions, and whether the chain has to be retained.
+    fn prune(&self, timestamp: u64, guard: &Guard) -> (usize, bool) {
+        let version = match self.visible(timestamp, guard) {
+            Some(version) => version,
+            None => return (0, true),
+        };
+        let mut num_pruned_versions = 0;
+        let mut current = version.next.swap(Shared::null(), Relaxed, guard);
+        while !current.is_null() {
+            let next = unsafe { current.deref() }.next.load(Relaxed, guard);
+            unsafe { guard.defer_destroy(current) };
+            num_pruned_versions += 1;
+            current = next;
+        }
+
+        // the key is not visible if the only version is a tombstone
+        let latest = self.head.load(Relaxed, guard);
+        if version.value.is_none() && std::ptr::eq(latest.as_raw(), version) {
+            (num_pruned_versions + 1, false)
+        } else {
+            (num_pruned_versions, true)
+        }
+    }
+}
+
+impl<V> Default for VersionChain<V> {
+    fn default() -> Self {
+        VersionChain {
+            head: Atomic::null(),
+        }
+    }
+}
+
+impl<V> Drop for VersionChain<V> {
+    fn drop(&mut self) {
+        // readers may hold references to the versions
+        let guard = crossbeam_epoch::pin();
+        let mut current = self.head.swap(Shared::null(), Relaxed, &guard);
+        while !current.is_null() {
+            let next = unsafe { current.deref() }.next.load(Relaxed, &guard);
+            unsafe { guard.defer_destroy(current) };
+            current = next;
+        }
+    }
+}
@@ -1,14 +1,22 @@
 #[cfg(test)]
 mod test {
     use proptest::prelude::*;
-    use scc::HashMap;
-    use std::collections::hash_map::RandomState;
+    use scc::{
+        Bag, ChangeListener, Conflict, Deadlock, Event, Full, HashCache, HashCounter, HashIndex,
+        HashMap, HashMultiMap, HashSet, LockMode, LockTable, ManualClock, PersistentMap, Queue,
+        Stack, Statistics, Transaction, TreeIndex, VersionedMap,
+    };
+    use serde::de::value::MapDeserializer;
+    use serde::Deserialize;
+    use std::collections::hash_map::{DefaultHasher, RandomState};
     use std::collections::BTreeSet;
-    use std::hash::{Hash, Hasher};
+    use std::convert::TryInto;
+    use std::hash::{BuildHasher, BuildHasherDefault, Hash, Hasher};
     use std::sync::atomic::Ordering::{Acquire, Relaxed, Release};
     use std::sync::atomic::{AtomicU64, AtomicUsize};
-    use std::sync::Arc;
+    use std::sync::{Arc, Barrier, Mutex};
     use std::thread;
+    use std::time::Duration;
 
     proptest! {
         #[test]
@@ -207,6 +215,1798 @@ mod test {
         }
     }
 
+    #[derive(Default)]
+    struct ConstantHasher;
+
+    impl Hasher for ConstantHasher {
+        fn finish(&self) -> u64 {
+            0
+        }
+        fn write(&mut self, _: &[u8]) {}
+    }
+
+    impl BuildHasher for ConstantHasher {
+        type Hasher = ConstantHasher;
+        fn build_hasher(&self) -> ConstantHasher {
+            ConstantHasher
+        }
+    }
+
+    #[test]
+    fn statistics_histogram() {
+        let hashmap: HashMap<u64, u64, RandomState> = HashMap::new(RandomState::new(), None);
+        for i in 0..64 {
+            assert!(hashmap.insert(i, i).is_ok());
+        }
+        let statistics = hashmap.statistics();
+        println!("{}", statistics);
+        assert_eq!(
+            statistics.occupancy_histogram().iter().sum::<usize>(),
+            statistics.num_cells()
+        );
+        assert_eq!(
+            statistics.link_length_histogram().iter().sum::<usize>(),
+            statistics.num_cells()
+        );
+        assert_eq!(
+            statistics
+                .occupancy_histogram()
+                .iter()
+                .enumerate()
+                .map(|(size, cells)| size * cells)
+                .sum::<usize>()
+                + statistics.num_linked_entries(),
+            64
+        );
+
+        let hashmap: HashMap<u64, u64, ConstantHasher> = HashMap::new(ConstantHasher, None);
+        for i in 0..24 {
+            assert!(hashmap.insert(i, i).is_ok());
+        }
+        let statistics = hashmap.statistics();
+        println!("{}", statistics);
+        assert_eq!(statistics.num_partial_hash_collisions(), 24);
+        assert_eq!(statistics.occupancy_histogram()[16], 1);
+        assert_eq!(statistics.link_length_histogram()[8], 1);
+        assert_eq!(statistics.max_link_length(), 8);
+    }
+
+    #[test]
+    fn hash_diagnostics() {
+        let keys: Vec<u64> = (0..65536).collect();
+        let hashmap: HashMap<u64, u64, RandomState> = HashMap::new(RandomState::new(), None);
+        let diagnostics = hashmap.hash_diagnostics(keys.iter());
+        println!("{}", diagnostics);
+        assert_eq!(diagnostics.num_keys(), 65536);
+        assert!(diagnostics.cell_index_skew() < 4.0);
+        assert!(diagnostics.partial_hash_collision_rate() < 0.01);
+        assert!(diagnostics.predicted_max_link_arrays() <= 8);
+
+        let hashmap: HashMap<u64, u64, ConstantHasher> = HashMap::new(ConstantHasher, None);
+        let diagnostics = hashmap.hash_diagnostics(keys[0..1024].iter());
+        println!("{}", diagnostics);
+        assert_eq!(diagnostics.num_occupied_cells(), 1);
+        assert_eq!(diagnostics.max_cell_load(), 1024);
+        assert_eq!(diagnostics.num_partial_hash_collisions(), 1024);
+        assert_eq!(diagnostics.predicted_linked_entries(), 1008);
+        assert_eq!(diagnostics.predicted_max_link_arrays(), 252);
+        assert!(diagnostics.cell_index_skew() > 32.0);
+    }
+
+    #[test]
+    fn prehashed() {
+        let build_hasher = RandomState::new();
+        let hash = |key: &u64| build_hasher.hash_one(key);
+        let hashmap: HashMap<u64, u64, RandomState> = HashMap::new(build_hasher.clone(), None);
+        for key in 0..4096 {
+            if key % 2 == 0 {
+                assert!(hashmap.insert_with_hash(key, hash(&key), key).is_ok());
+            } else {
+                assert!(hashmap.insert(key, key).is_ok());
+            }
+        }
+        for key in 0..4096 {
+            assert_eq!(hashmap.read(key, |_, value| *value), Some(key));
+            assert_eq!(
+                hashmap.read_with_hash(key, hash(&key), |_, value| *value),
+                Some(key)
+            );
+            assert_eq!(
+                *hashmap.get_with_hash(key, hash(&key)).unwrap().get().1,
+                key
+            );
+        }
+        for key in 0..4096 {
+            if key % 2 == 0 {
+                assert!(hashmap.remove(key));
+            } else {
+                assert!(hashmap.remove_with_hash(key, hash(&key)));
+            }
+        }
+        assert!(hashmap.iter().next().is_none());
+    }
+
+    #[test]
+    fn raw_entry() {
+        let build_hasher = RandomState::new();
+        let hashmap: HashMap<(u64, String), u64, RandomState> =
+            HashMap::new(build_hasher.clone(), None);
+        for key in 0..4096 {
+            assert!(hashmap.insert((key, key.to_string()), key).is_ok());
+            let row = (key / 2, (key / 2).to_string());
+            let hash = build_hasher.hash_one(&row);
+            let found = hashmap.raw_entry().read_from_hash(
+                hash,
+                |k| k.0 == row.0 && k.1 == row.1,
+                |_, v| *v,
+            );
+            assert_eq!(found, Some(key / 2));
+        }
+        for key in 0..4096 {
+            let row = (key, key.to_string());
+            let hash = build_hasher.hash_one(&row);
+            let accessor = hashmap
+                .raw_entry()
+                .from_hash(hash, |k| k.0 == row.0 && k.1 == row.1);
+            assert!(accessor.unwrap().erase());
+            assert!(hashmap
+                .raw_entry()
+                .from_hash(hash, |k| k.0 == row.0)
+                .is_none());
+        }
+    }
+
+    struct CountingHasher {
+        build_hasher: RandomState,
+        count: Arc<AtomicUsize>,
+    }
+
+    impl BuildHasher for CountingHasher {
+        type Hasher = <RandomState as BuildHasher>::Hasher;
+        fn build_hasher(&self) -> Self::Hasher {
+            self.count.fetch_add(1, Relaxed);
+            self.build_hasher.build_hasher()
+        }
+    }
+
+    #[test]
+    fn hash_cache() {
+        let range = 65536;
+        let mut counts = Vec::new();
+        for cache_hash in [false, true] {
+            let count = Arc::new(AtomicUsize::new(0));
+            let build_hasher = CountingHasher {
+                build_hasher: RandomState::new(),
+                count: count.clone(),
+            };
+            let hashmap: HashMap<String, usize, CountingHasher> = if cache_hash {
+                HashMap::with_hash_cache(build_hasher, None)
+            } else {
+                HashMap::new(build_hasher, None)
+            };
+            for key in 0..range {
+                assert!(hashmap.insert(key.to_string(), key).is_ok());
+            }
+            for key in 0..range {
+                assert_eq!(hashmap.read(key.to_string(), |_, value| *value), Some(key));
+            }
+            assert_eq!(hashmap.retain(|key, _| key.len() < 2), (10, range - 10));
+            for key in 0..10 {
+                assert_eq!(hashmap.read(key.to_string(), |_, value| *value), Some(key));
+            }
+            counts.push(count.load(Relaxed));
+        }
+        // no entries are rehashed when resizing a HashMap caching hash values
+        assert!(counts[0] > range * 2 + 10);
+        assert_eq!(counts[1], range * 2 + 10);
+    }
+
+    #[test]
+    fn hashset() {
+        let num_threads = 8;
+        let range = 4096;
+        let hashset: Arc<HashSet<usize, RandomState>> =
+            Arc::new(HashSet::new(RandomState::new(), None));
+        let mut thread_handles = Vec::with_capacity(num_threads);
+        for thread_id in 0..num_threads {
+            let hashset_copied = hashset.clone();
+            thread_handles.push(thread::spawn(move || {
+                for key in (thread_id * range)..((thread_id + 1) * range) {
+                    assert!(hashset_copied.insert(key));
+                    assert!(!hashset_copied.insert(key));
+                    assert!(hashset_copied.contains(key));
+                }
+                for key in (thread_id * range)..((thread_id + 1) * range) {
+                    if key % 2 == 1 {
+                        assert!(hashset_copied.remove(key));
+                        assert!(!hashset_copied.contains(key));
+                    }
+                }
+            }));
+        }
+        for handle in thread_handles {
+            handle.join().unwrap();
+        }
+        assert_eq!(hashset.iter().count(), num_threads * range / 2);
+        assert!(hashset.iter().all(|key| key % 2 == 0));
+
+        let union: HashSet<usize, RandomState> = HashSet::new(RandomState::new(), None);
+        for key in 0..(num_threads * range) {
+            if key % 4 == 0 {
+                assert!(union.insert(key));
+            }
+        }
+        assert!(union.is_subset(&hashset));
+        assert!(!hashset.is_subset(&union));
+        assert_eq!(hashset.union_into(&union), num_threads * range / 4);
+        assert!(hashset.is_subset(&union));
+        assert!(union.is_subset(&hashset));
+        assert_eq!(
+            union.retain(|key| key % 4 == 0),
+            (num_threads * range / 4, num_threads * range / 4)
+        );
+        assert_eq!(union.clear(), num_threads * range / 4);
+        assert!(union.is_subset(&hashset));
+
+        // set operations in opposite directions do not deadlock
+        let union: Arc<HashSet<usize, RandomState>> =
+            Arc::new(HashSet::new(RandomState::new(), None));
+        let mut thread_handles = Vec::with_capacity(num_threads);
+        for thread_id in 0..num_threads {
+            let (hashset_copied, union_copied) = (hashset.clone(), union.clone());
+            thread_handles.push(thread::spawn(move || {
+                let (from, to) = if thread_id % 2 == 0 {
+                    (&hashset_copied, &union_copied)
+                } else {
+                    (&union_copied, &hashset_copied)
+                };
+                for _ in 0..4 {
+                    from.union_into(to);
+                    from.is_subset(to);
+                }
+            }));
+        }
+        for handle in thread_handles {
+            handle.join().unwrap();
+        }
+        assert!(hashset.is_subset(&union));
+        assert!(union.is_subset(&hashset));
+    }
+
+    #[test]
+    fn hashindex() {
+        let num_threads = 8;
+        let range = 4096;
+        let stable = 1024;
+        let hashindex: Arc<HashIndex<usize, usize, RandomState>> =
+            Arc::new(HashIndex::new(RandomState::new(), None));
+        for key in 0..stable {
+            assert!(hashindex.insert(key, key).is_ok());
+        }
+        let mut thread_handles = Vec::with_capacity(num_threads);
+        for thread_id in 0..num_threads {
+            let hashindex_copied = hashindex.clone();
+            thread_handles.push(thread::spawn(move || {
+                let start = stable + thread_id * range;
+                for _ in 0..4 {
+                    for key in start..(start + range) {
+                        assert!(hashindex_copied.insert(key, key).is_ok());
+                        assert_eq!(hashindex_copied.read(key, |_, value| *value), Some(key));
+                        assert_eq!(
+                            hashindex_copied.read(key % stable, |_, value| *value),
+                            Some(key % stable)
+                        );
+                    }
+                    for key in start..(start + range) {
+                        hashindex_copied.upsert(key, key + 1);
+                        assert_eq!(hashindex_copied.read(key, |_, value| *value), Some(key + 1));
+                    }
+                    for key in start..(start + range) {
+                        assert!(hashindex_copied.remove(key));
+                        assert!(hashindex_copied.read(key, |_, _| ()).is_none());
+                        assert_eq!(
+                            hashindex_copied.read(key % stable, |_, value| *value),
+                            Some(key % stable)
+                        );
+                    }
+                }
+            }));
+        }
+        for handle in thread_handles {
+            handle.join().unwrap();
+        }
+        for key in 0..stable {
+            assert_eq!(hashindex.read(key, |_, value| *value), Some(key));
+        }
+        assert_eq!(
+            hashindex.retain(|key, _| key % 2 == 0),
+            (stable / 2, stable / 2)
+        );
+        assert_eq!(hashindex.clear(), stable / 2);
+        assert!(hashindex.read(0, |_, _| ()).is_none());
+    }
+
+    #[test]
+    fn treeindex() {
+        let num_threads = 8;
+        let range = 4096;
+        let stable = 1024;
+        let treeindex: Arc<TreeIndex<usize, usize>> = Arc::new(TreeIndex::new());
+        for key in 0..stable {
+            assert!(treeindex.insert(key * (num_threads + 1), key).is_ok());
+        }
+        let mut thread_handles = Vec::with_capacity(num_threads);
+        for thread_id in 0..num_threads {
+            let treeindex_copied = treeindex.clone();
+            thread_handles.push(thread::spawn(move || {
+                let key_of = |index: usize| index * (num_threads + 1) + thread_id + 1;
+                for _ in 0..4 {
+                    for index in 0..range {
+                        assert!(treeindex_copied.insert(key_of(index), index).is_ok());
+                        assert_eq!(
+                            treeindex_copied.read(key_of(index), |_, value| *value),
+                            Some(index)
+                        );
+                    }
+                    // stable keys are always visible, and keys are scanned in ascending order
+                    let guard = scc::pin();
+                    let mut prev_key = None;
+                    let mut num_stable_keys = 0;
+                    for (key, _) in treeindex_copied.range(.., &guard) {
+                        if let Some(prev_key) = prev_key {
+                            assert!(prev_key < *key);
+                        }
+                        prev_key.replace(*key);
+                        if key % (num_threads + 1) == 0 {
+                            num_stable_keys += 1;
+                        }
+                    }
+                    assert_eq!(num_stable_keys, stable);
+                    let start = key_of(range / 4);
+                    let end = key_of(range / 2);
+                    assert_eq!(
+                        treeindex_copied
+                            .range(start..end, &guard)
+                            .filter(|(key, _)| *key % (num_threads + 1) == thread_id + 1)
+                            .count(),
+                        range / 4
+                    );
+                    drop(guard);
+                    for index in 0..range {
+                        assert!(treeindex_copied.remove(key_of(index)));
+                        assert!(treeindex_copied.read(key_of(index), |_, _| ()).is_none());
+                    }
+                }
+            }));
+        }
+        for handle in thread_handles {
+            handle.join().unwrap();
+        }
+        assert_eq!(treeindex.len(), stable);
+        for key in 0..stable {
+            assert_eq!(
+                treeindex.read(key * (num_threads + 1), |_, value| *value),
+                Some(key)
+            );
+        }
+        assert_eq!(treeindex.clear(), stable);
+        assert!(treeindex.is_empty());
+    }
+
+    #[test]
+    fn queue() {
+        let num_threads = 8;
+        let range = 65536;
+        let queue: Arc<Queue<usize>> = Arc::new(Queue::new());
+        let num_popped = Arc::new(AtomicUsize::new(0));
+        let sum_popped = Arc::new(AtomicUsize::new(0));
+        let mut thread_handles = Vec::with_capacity(num_threads * 2);
+        for thread_id in 0..num_threads {
+            let queue_copied = queue.clone();
+            thread_handles.push(thread::spawn(move || {
+                for value in 0..range {
+                    queue_copied.push(thread_id * range + value);
+                }
+            }));
+            let queue_copied = queue.clone();
+            let num_popped_copied = num_popped.clone();
+            let sum_popped_copied = sum_popped.clone();
+            thread_handles.push(thread::spawn(move || {
+                // values pushed by the same thread are popped in order
+                let mut last_popped = vec![None; num_threads];
+                while num_popped_copied.load(Relaxed) < num_threads * range {
+                    queue_copied.peek(|value| assert!(*value < num_threads * range));
+                    if let Some(value) = queue_copied.pop() {
+                        let producer = value / range;
+                        if let Some(last) = last_popped[producer] {
+                            assert!(last < value);
+                        }
+                        last_popped[producer].replace(value);
+                        num_popped_copied.fetch_add(1, Relaxed);
+                        sum_popped_copied.fetch_add(value, Relaxed);
+                    }
+                }
+            }));
+        }
+        for handle in thread_handles {
+            handle.join().unwrap();
+        }
+        let total = num_threads * range;
+        assert_eq!(sum_popped.load(Relaxed), total * (total - 1) / 2);
+        assert!(queue.is_empty());
+        assert_eq!(queue.len(), 0);
+        // a popped value is not retained by the Queue
+        let queue: Queue<Arc<usize>> = Queue::new();
+        queue.push(Arc::new(1));
+        queue.push(Arc::new(2));
+        assert_eq!(Arc::try_unwrap(queue.pop().unwrap()), Ok(1));
+        assert_eq!(queue.peek(|value| **value), Some(2));
+    }
+
+    #[test]
+    fn stack() {
+        let num_threads = 8;
+        let range = 65536;
+        let stack: Arc<Stack<usize>> = Arc::new(Stack::new());
+        let mut thread_handles = Vec::with_capacity(num_threads);
+        for thread_id in 0..num_threads {
+            let stack_copied = stack.clone();
+            thread_handles.push(thread::spawn(move || {
+                let mut popped = Vec::with_capacity(range);
+                for value in 0..range {
+                    stack_copied.push(thread_id * range + value);
+                    if value % 2 == 1 {
+                        // other threads draining the Stack may have popped every value
+                        if let Some(value) = stack_copied.pop() {
+                            popped.push(value);
+                        }
+                        stack_copied.peek(|value| assert!(*value < num_threads * range));
+                    }
+                }
+                while let Some(value) = stack_copied.pop() {
+                    popped.push(value);
+                }
+                popped
+            }));
+        }
+        let mut popped = BTreeSet::new();
+        for handle in thread_handles {
+            for value in handle.join().unwrap() {
+                assert!(popped.insert(value));
+            }
+        }
+        assert_eq!(popped.len(), num_threads * range);
+        assert!(stack.is_empty());
+        assert_eq!(stack.len(), 0);
+        // a popped value is not retained by the Stack
+        let stack: Stack<Arc<usize>> = Stack::new();
+        stack.push(Arc::new(1));
+        stack.push(Arc::new(2));
+        assert_eq!(Arc::try_unwrap(stack.pop().unwrap()), Ok(2));
+        assert_eq!(stack.peek(|value| **value), Some(1));
+    }
+
+    #[test]
+    fn bag() {
+        let num_threads = 8;
+        let range = 65536;
+        let bag: Arc<Bag<usize>> = Arc::new(Bag::new());
+        let mut thread_handles = Vec::with_capacity(num_threads);
+        for thread_id in 0..num_threads {
+            let bag_copied = bag.clone();
+            thread_handles.push(thread::spawn(move || {
+                let mut popped = Vec::with_capacity(range);
+                for value in 0..range {
+                    bag_copied.push(thread_id * range + value);
+                    if value % 4 == 3 {
+                        popped.push(bag_copied.pop().unwrap());
+                    }
+                }
+                while let Some(value) = bag_copied.pop() {
+                    popped.push(value);
+                }
+                popped
+            }));
+        }
+        let mut popped = BTreeSet::new();
+        for handle in thread_handles {
+            for value in handle.join().unwrap() {
+                assert!(popped.insert(value));
+            }
+        }
+        assert_eq!(popped.len(), num_threads * range);
+        assert!(bag.is_empty());
+
+        // values left in the Bag are dropped
+        let data_size = 256;
+        let bag: Bag<Data> = Bag::new();
+        let checker = AtomicUsize::new(0);
+        for _ in 0..data_size {
+            bag.push(Data::new(0, &checker));
+        }
+        assert_eq!(bag.len(), data_size);
+        assert_eq!(checker.load(Relaxed), data_size);
+        drop(bag.pop());
+        assert_eq!(checker.load(Relaxed), data_size - 1);
+        drop(bag);
+        assert_eq!(checker.load(Relaxed), 0);
+    }
+
+    #[test]
+    fn hashcache() {
+        let num_threads = 8;
+        let range = 65536;
+        let hot_keys = 64;
+        let hashcache: Arc<HashCache<usize, usize, RandomState>> =
+            Arc::new(HashCache::new(RandomState::new(), 4096));
+        let capacity = hashcache.capacity();
+        for key in 0..hot_keys {
+            assert_eq!(hashcache.put(key, key), Ok(Vec::new()));
+        }
+        let num_evicted = Arc::new(AtomicUsize::new(0));
+        let num_hot_key_misses = Arc::new(AtomicUsize::new(0));
+        let mut thread_handles = Vec::with_capacity(num_threads);
+        for thread_id in 0..num_threads {
+            let hashcache_copied = hashcache.clone();
+            let num_evicted_copied = num_evicted.clone();
+            let num_hot_key_misses_copied = num_hot_key_misses.clone();
+            thread_handles.push(thread::spawn(move || {
+                let start = hot_keys + thread_id * range;
+                for key in start..(start + range) {
+                    for (evicted_key, evicted_value) in hashcache_copied.put(key, key).unwrap() {
+                        assert_eq!(evicted_key, evicted_value);
+                        num_evicted_copied.fetch_add(1, Relaxed);
+                    }
+                    // hot keys are referenced more frequently than the cache is swept
+                    if hashcache_copied.read(key % hot_keys, |_, _| ()).is_none() {
+                        num_hot_key_misses_copied.fetch_add(1, Relaxed);
+                    }
+                    assert!(hashcache_copied.len() <= capacity);
+                }
+            }));
+        }
+        for handle in thread_handles {
+            handle.join().unwrap();
+        }
+        let len = hashcache.len();
+        assert_eq!(
+            num_evicted.load(Relaxed),
+            num_threads * range + hot_keys - len
+        );
+        assert!(num_hot_key_misses.load(Relaxed) < num_threads * range / 100);
+        let num_hot_keys = (0..hot_keys).filter(|key| hashcache.remove(*key)).count();
+        assert_eq!(hashcache.clear(), len - num_hot_keys);
+        assert!(hashcache.is_empty());
+    }
+
+    #[test]
+    fn weighted_hashcache() {
+        let num_threads = 8;
+        let range = 16384;
+        let max_weight = 65536;
+        let hashcache: Arc<HashCache<usize, Vec<u8>, RandomState>> =
+            Arc::new(HashCache::with_weigher(
+                RandomState::new(),
+                4096,
+                max_weight,
+                |_, value: &Vec<u8>| value.len(),
+            ));
+        let evicted_weight = Arc::new(AtomicUsize::new(0));
+        let mut thread_handles = Vec::with_capacity(num_threads);
+        for thread_id in 0..num_threads {
+            let hashcache_copied = hashcache.clone();
+            let evicted_weight_copied = evicted_weight.clone();
+            thread_handles.push(thread::spawn(move || {
+                for key in (thread_id * range)..((thread_id + 1) * range) {
+                    for (evicted_key, evicted_value) in
+                        hashcache_copied.put(key, vec![0; key % 256]).unwrap()
+                    {
+                        assert_eq!(evicted_value.len(), evicted_key % 256);
+                        evicted_weight_copied.fetch_add(evicted_value.len(), Relaxed);
+                    }
+                }
+            }));
+        }
+        for handle in thread_handles {
+            handle.join().unwrap();
+        }
+        let total_weight: usize = (0..num_threads * range).map(|key| key % 256).sum();
+        assert!(hashcache.weight() <= max_weight);
+        assert_eq!(
+            hashcache.weight() + evicted_weight.load(Relaxed),
+            total_weight
+        );
+
+        // a key-value pair heavier than the maximum weight is evicted
+        assert!(hashcache.clear() > 0);
+        assert_eq!(hashcache.weight(), 0);
+        let result = hashcache.put(0, vec![0; max_weight + 1]);
+        assert_eq!(result.unwrap(), vec![(0, vec![0; max_weight + 1])]);
+        assert!(hashcache.is_empty());
+    }
+
+    #[test]
+    fn ttl() {
+        let clock = Arc::new(ManualClock::default());
+        let hashmap: Arc<HashMap<usize, usize, RandomState>> =
+            Arc::new(HashMap::with_clock(RandomState::new(), None, clock.clone()));
+        let num_threads = 8;
+        let range = 4096;
+        let mut thread_handles = Vec::with_capacity(num_threads);
+        for thread_id in 0..num_threads {
+            let hashmap_copied = hashmap.clone();
+            thread_handles.push(thread::spawn(move || {
+                for key in (thread_id * range)..((thread_id + 1) * range) {
+                    // odd keys expire after one second, and even keys never expire
+                    let result = if key % 2 == 1 {
+                        hashmap_copied.insert_with_ttl(key, key, Duration::from_secs(1))
+                    } else {
+                        hashmap_copied.insert(key, key)
+                    };
+                    assert!(result.is_ok());
+                }
+            }));
+        }
+        for handle in thread_handles {
+            handle.join().unwrap();
+        }
+
+        // deadlines are retained while the HashMap is being resized
+        assert!(hashmap.capacity() > 256);
+        assert_eq!(hashmap.iter().count(), num_threads * range);
+        clock.advance(Duration::from_millis(999));
+        assert_eq!(hashmap.read(1, |_, value| *value), Some(1));
+        clock.advance(Duration::from_millis(1));
+        assert!(hashmap.read(1, |_, _| ()).is_none());
+        assert!(hashmap.get(1).is_none());
+        assert!(!hashmap.remove(3));
+        assert_eq!(hashmap.read(2, |_, value| *value), Some(2));
+
+        // expired keys can be inserted again
+        assert!(hashmap.insert(1, 2).is_ok());
+        assert_eq!(hashmap.read(1, |_, value| *value), Some(2));
+        assert!(hashmap.iter().all(|(key, _)| key % 2 == 0 || *key == 1));
+        assert_eq!(hashmap.purge_expired(), 0);
+        assert_eq!(
+            hashmap.statistics().num_entries(),
+            num_threads * range / 2 + 1
+        );
+
+        // linked entries expire
+        let hashmap: HashMap<u64, u64, ConstantHasher> =
+            HashMap::with_clock(ConstantHasher, None, clock.clone());
+        for key in 0..64 {
+            assert!(hashmap
+                .insert_with_ttl(key, key, Duration::from_secs(key + 1))
+                .is_ok());
+        }
+        assert!(hashmap.statistics().num_linked_entries() > 0);
+        clock.advance(Duration::from_secs(32));
+        for key in 0..64 {
+            assert_eq!(hashmap.read(key, |_, _| ()).is_some(), key >= 32);
+        }
+        assert_eq!(hashmap.purge_expired(), 32);
+        assert_eq!(hashmap.clear(), 32);
+    }
+
+    #[test]
+    fn hashmultimap() {
+        let num_threads = 8;
+        let num_keys = 64;
+        let range = 4096;
+        let hashmultimap: Arc<HashMultiMap<usize, usize, RandomState>> =
+            Arc::new(HashMultiMap::new(RandomState::new(), None));
+        let mut thread_handles = Vec::with_capacity(num_threads);
+        for thread_id in 0..num_threads {
+            let hashmultimap_copied = hashmultimap.clone();
+            thread_handles.push(thread::spawn(move || {
+                // every thread inserts distinct values into the same keys twice
+                for value in (thread_id * range)..((thread_id + 1) * range) {
+                    assert!(hashmultimap_copied.insert(value % num_keys, value) > 0);
+                    assert!(hashmultimap_copied.insert(value % num_keys, value) > 1);
+                }
+                for value in (thread_id * range)..((thread_id + 1) * range) {
+                    if value % 2 == 1 {
+                        assert!(hashmultimap_copied.remove(value % num_keys, value));
+                        assert!(hashmultimap_copied.remove(value % num_keys, value));
+                        assert!(!hashmultimap_copied.remove(value % num_keys, value));
+                    }
+                }
+            }));
+        }
+        for handle in thread_handles {
+            handle.join().unwrap();
+        }
+        assert_eq!(hashmultimap.len(|capacity| capacity), num_keys / 2);
+        for key in 0..num_keys {
+            let mut values = Vec::new();
+            let count = hashmultimap.for_each_value(key, |value| values.push(*value));
+            assert_eq!(count, hashmultimap.count(key));
+            if key % 2 == 1 {
+                assert_eq!(count, 0);
+            } else {
+                assert_eq!(count, 2 * num_threads * range / num_keys);
+                assert!(values.iter().all(|value| value % num_keys == key));
+            }
+        }
+        assert_eq!(
+            hashmultimap.remove_all(0),
+            2 * num_threads * range / num_keys
+        );
+        assert_eq!(hashmultimap.count(0), 0);
+        assert_eq!(hashmultimap.clear(), num_keys / 2 - 1);
+    }
+
+    #[test]
+    fn hashcounter() {
+        let num_threads = 8;
+        let num_keys = 1024;
+        let num_iterations = 64;
+        let hashcounter: Arc<HashCounter<usize, RandomState>> =
+            Arc::new(HashCounter::new(RandomState::new(), None));
+        let mut thread_handles = Vec::with_capacity(num_threads);
+        for thread_id in 0..num_threads {
+            let hashcounter_copied = hashcounter.clone();
+            thread_handles.push(thread::spawn(move || {
+                // threads start from different keys in order for insertions to race with increments
+                for iteration in 0..num_iterations {
+                    for key in 0..num_keys {
+                        let key = (key + thread_id * num_keys / num_threads) % num_keys;
+                        let result = hashcounter_copied.add(key, key as u64 + 1);
+                        assert!(result >= (iteration as u64 + 1) * (key as u64 + 1));
+                    }
+                }
+            }));
+        }
+        for handle in thread_handles {
+            handle.join().unwrap();
+        }
+        assert_eq!(hashcounter.len(|capacity| capacity), num_keys);
+        for key in 0..num_keys {
+            assert_eq!(
+                hashcounter.get(key),
+                (num_threads * num_iterations * (key + 1)) as u64
+            );
+        }
+        assert_eq!(
+            hashcounter.remove(0),
+            Some((num_threads * num_iterations) as u64)
+        );
+        assert!(hashcounter.remove(0).is_none());
+        assert_eq!(hashcounter.clear(), num_keys - 1);
+    }
+
+    #[test]
+    fn locktable() {
+        let num_threads = 8;
+        let num_keys = 16;
+        let num_iterations = 4096;
+        let locktable: Arc<LockTable<usize, RandomState>> =
+            Arc::new(LockTable::new(RandomState::new()));
+        // each counter is the number of readers, or usize::MAX if written
+        let counters: Arc<Vec<AtomicUsize>> =
+            Arc::new((0..num_keys).map(|_| AtomicUsize::new(0)).collect());
+        let mut thread_handles = Vec::with_capacity(num_threads);
+        for thread_id in 0..num_threads {
+            let locktable_copied = locktable.clone();
+            let counters_copied = counters.clone();
+            thread_handles.push(thread::spawn(move || {
+                for iteration in 0..num_iterations {
+                    let transaction_id = (thread_id * num_iterations + iteration) as u64;
+                    // keys are locked in ascending order to avoid deadlocks
+                    let first_key = (thread_id + iteration) % (num_keys - 1);
+                    let keys = [first_key, first_key + 1];
+                    if iteration % 4 == 0 {
+                        for key in keys.iter() {
+                            assert!(locktable_copied
+                                .lock(transaction_id, *key, LockMode::Exclusive)
+                                .is_ok());
+                            assert_eq!(counters_copied[*key].swap(usize::MAX, Relaxed), 0);
+                        }
+                        for key in keys.iter() {
+                            counters_copied[*key].store(0, Relaxed);
+                        }
+                    } else if iteration % 4 == 1 {
+                        // only a single transaction at a time can upgrade the lock on the last key
+                        assert!(locktable_copied
+                            .lock(transaction_id, keys[0], LockMode::Exclusive)
+                            .is_ok());
+                        assert_eq!(counters_copied[keys[0]].swap(usize::MAX, Relaxed), 0);
+                        assert!(locktable_copied
+                            .lock(transaction_id, keys[1], LockMode::Shared)
+                            .is_ok());
+                        assert_ne!(counters_copied[keys[1]].load(Relaxed), usize::MAX);
+                        assert!(locktable_copied
+                            .lock(transaction_id, keys[1], LockMode::Exclusive)
+                            .is_ok());
+                        assert_eq!(counters_copied[keys[1]].swap(usize::MAX, Relaxed), 0);
+                        for key in keys.iter() {
+                            counters_copied[*key].store(0, Relaxed);
+                        }
+                    } else {
+                        for key in keys.iter() {
+                            assert!(locktable_copied
+                                .lock(transaction_id, *key, LockMode::Shared)
+                                .is_ok());
+                            assert_ne!(counters_copied[*key].fetch_add(1, Relaxed), usize::MAX);
+                        }
+                        for key in keys.iter() {
+                            counters_copied[*key].fetch_sub(1, Relaxed);
+                        }
+                    }
+                    assert_eq!(locktable_copied.unlock_all(transaction_id), 2);
+                }
+            }));
+        }
+        for handle in thread_handles {
+            handle.join().unwrap();
+        }
+        assert!(counters.iter().all(|counter| counter.load(Relaxed) == 0));
+        for key in 0..num_keys {
+            assert!(locktable.try_lock(0, key, LockMode::Exclusive));
+        }
+        assert_eq!(locktable.unlock_all(0), num_keys);
+    }
+
+    #[test]
+    fn deadlock() {
+        let num_threads = 4;
+        let num_iterations = 64;
+        let locktable: Arc<LockTable<usize, RandomState>> = Arc::new(
+            LockTable::with_deadlock_detection(RandomState::new(), Duration::from_millis(1)),
+        );
+        let barrier = Arc::new(Barrier::new(num_threads));
+        let num_victims = Arc::new(AtomicUsize::new(0));
+        let mut thread_handles = Vec::with_capacity(num_threads);
+        for thread_id in 0..num_threads {
+            let locktable_copied = locktable.clone();
+            let barrier_copied = barrier.clone();
+            let num_victims_copied = num_victims.clone();
+            thread_handles.push(thread::spawn(move || {
+                for iteration in 0..num_iterations {
+                    // each transaction waits for the key locked by the next transaction
+                    let transaction_id = (iteration * num_threads + thread_id) as u64;
+                    assert!(locktable_copied
+                        .lock(transaction_id, thread_id, LockMode::Exclusive)
+                        .is_ok());
+                    barrier_copied.wait();
+                    let result = locktable_copied.lock(
+                        transaction_id,
+                        (thread_id + 1) % num_threads,
+                        LockMode::Shared,
+                    );
+                    if result.is_err() {
+                        // the youngest transaction is chosen as the victim
+                        assert_eq!(thread_id, num_threads - 1);
+                        num_victims_copied.fetch_add(1, Relaxed);
+                    }
+                    locktable_copied.unlock_all(transaction_id);
+                    barrier_copied.wait();
+                }
+            }));
+        }
+        for handle in thread_handles {
+            handle.join().unwrap();
+        }
+        assert_eq!(num_victims.load(Relaxed), num_iterations);
+    }
+
+    #[test]
+    fn accessor_deadlock() {
+        let num_threads = 4;
+        let num_iterations = 16;
+        let hashmap: Arc<HashMap<usize, usize, RandomState>> =
+            Arc::new(HashMap::with_deadlock_detection(
+                RandomState::new(),
+                Some(1024),
+                Duration::from_millis(1),
+            ));
+        for key in 0..64 {
+            assert!(hashmap.insert(key, 0).is_ok());
+        }
+
+        // a thread locking a cell that it holds is woken up with a Deadlock error, therefore keys in distinct cells
+        // are found by holding an Accessor on each of them
+        let mut keys = Vec::with_capacity(num_threads);
+        let mut accessors = Vec::with_capacity(num_threads);
+        for key in 0..64 {
+            match hashmap.lock(key) {
+                Ok(accessor) => {
+                    accessors.push(accessor.unwrap());
+                    keys.push(key);
+                }
+                Err(Deadlock) => continue,
+            }
+            if keys.len() == num_threads {
+                break;
+            }
+        }
+        assert_eq!(keys.len(), num_threads);
+        drop(accessors);
+
+        // each thread holds an Accessor, and waits for the Accessor of the next thread; threads waiting in read and
+        // get are recorded in the wait-for graph, but only those waiting in lock can be chosen as the victim
+        let keys = Arc::new(keys);
+        let barrier = Arc::new(Barrier::new(num_threads));
+        let num_victims = Arc::new(AtomicUsize::new(0));
+        let mut thread_handles = Vec::with_capacity(num_threads);
+        for thread_id in 0..num_threads {
+            let hashmap_copied = hashmap.clone();
+            let keys_copied = keys.clone();
+            let barrier_copied = barrier.clone();
+            let num_victims_copied = num_victims.clone();
+            thread_handles.push(thread::spawn(move || {
+                let next_key = keys_copied[(thread_id + 1) % num_threads];
+                for iteration in 0..num_iterations {
+                    let accessor = hashmap_copied
+                        .lock(keys_copied[thread_id])
+                        .unwrap()
+                        .unwrap();
+                    *accessor.get().1 = iteration;
+                    barrier_copied.wait();
+                    match thread_id {
+                        0 => assert!(hashmap_copied.read(next_key, |_, _| ()).is_some()),
+                        1 => assert!(hashmap_copied.get(next_key).is_some()),
+                        _ => {
+                            if hashmap_copied.lock(next_key).is_err() {
+                                num_victims_copied.fetch_add(1, Relaxed);
+                            }
+                        }
+                    }
+                    drop(accessor);
+                    barrier_copied.wait();
+                }
+            }));
+        }
+        for handle in thread_handles {
+            handle.join().unwrap();
+        }
+        assert_eq!(num_victims.load(Relaxed), num_iterations);
+    }
+
+    #[test]
+    fn lock_many() {
+        let num_accounts = 64;
+        let num_tokens = 64;
+        let num_iterations = 16384;
+        let hashmap: Arc<HashMap<usize, usize, RandomState>> =
+            Arc::new(HashMap::new(RandomState::new(), None));
+        for account in 0..num_accounts {
+            assert!(hashmap.insert(account, 1000).is_ok());
+        }
+        // each token is stored in either one of two keys
+        let token_keys = |token: usize| [1 << 20 | token << 1, 1 << 20 | token << 1 | 1];
+        for token in 0..num_tokens {
+            assert!(hashmap.insert(token_keys(token)[0], token).is_ok());
+        }
+        let num_threads = 6;
+        let mut thread_handles = Vec::with_capacity(num_threads);
+        for thread_id in 0..num_threads {
+            let hashmap_copied = hashmap.clone();
+            thread_handles.push(thread::spawn(move || {
+                for iteration in 0..num_iterations {
+                    match thread_id % 3 {
+                        0 => {
+                            // transfers a unit between accounts
+                            let from = (iteration * 7 + thread_id) % num_accounts;
+                            let to = (iteration * 13 + 1) % num_accounts;
+                            let mut multi_accessor = hashmap_copied.lock_many(&[from, to]);
+                            if from != to && *multi_accessor.read(&from).unwrap() > 0 {
+                                *multi_accessor.get_mut(&from).unwrap() -= 1;
+                                *multi_accessor.get_mut(&to).unwrap() += 1;
+                            }
+                        }
+                        1 => {
+                            // moves a token to the other key
+                            let keys = token_keys((iteration + thread_id) % num_tokens);
+                            let mut multi_accessor = hashmap_copied.lock_many(&keys);
+                            let (from, to) = if multi_accessor.read(&keys[0]).is_some() {
+                                (keys[0], keys[1])
+                            } else {
+                                (keys[1], keys[0])
+                            };
+                            assert!(multi_accessor.read(&to).is_none());
+                            let (_, token) = multi_accessor.remove(&from).unwrap();
+                            assert!(multi_accessor.insert(to, token).is_ok());
+                        }
+                        _ => {
+                            // enlarges and shrinks the HashMap
+                            let key = 1 << 24 | thread_id << 16 | (iteration % 2048);
+                            if (iteration / 2048) % 2 == 0 {
+                                assert!(hashmap_copied.insert(key, key).is_ok());
+                            } else {
+                                assert!(hashmap_copied.remove(key));
+                            }
+                        }
+                    }
+                }
+            }));
+        }
+        for handle in thread_handles {
+            handle.join().unwrap();
+        }
+        let total: usize = (0..num_accounts)
+            .map(|account| hashmap.read(account, |_, balance| *balance).unwrap())
+            .sum();
+        assert_eq!(total, num_accounts * 1000);
+        for token in 0..num_tokens {
+            let keys = token_keys(token);
+            let found: Vec<usize> = keys
+                .iter()
+                .filter_map(|key| hashmap.read(*key, |_, value| *value))
+                .collect();
+            assert_eq!(found, vec![token]);
+        }
+        assert_eq!(
+            hashmap.retain(|key, _| *key < 1 << 24),
+            (num_accounts + num_tokens, 0)
+        );
+    }
+
+    #[test]
+    fn transaction() {
+        let num_accounts = 16;
+        let num_iterations = 4096;
+        let hashmap: Arc<HashMap<usize, usize, RandomState>> =
+            Arc::new(HashMap::new(RandomState::new(), None));
+        for account in 0..num_accounts {
+            assert!(hashmap.insert(account, 1000).is_ok());
+        }
+        // retries the given transaction until it commits
+        fn run<F: FnMut(&mut Transaction<usize, usize, RandomState>) -> usize>(
+            hashmap: &HashMap<usize, usize, RandomState>,
+            mut f: F,
+        ) -> usize {
+            loop {
+                let mut transaction = hashmap.transaction();
+                let result = f(&mut transaction);
+                if transaction.commit().is_ok() {
+                    return result;
+                }
+            }
+        }
+        let num_threads = 6;
+        let mut thread_handles = Vec::with_capacity(num_threads);
+        for thread_id in 0..num_threads {
+            let hashmap_copied = hashmap.clone();
+            thread_handles.push(thread::spawn(move || {
+                let mut num_increments = 0;
+                for iteration in 0..num_iterations {
+                    match thread_id % 3 {
+                        0 => {
+                            // transfers a unit between accounts, and increments the counter
+                            let from = (iteration * 7 + thread_id) % num_accounts;
+                            let to = (iteration * 13 + 1) % num_accounts;
+                            num_increments += run(&hashmap_copied, |transaction| {
+                                let balance = transaction.read(&from, |_, v| *v).unwrap();
+                                if from != to && balance > 0 {
+                                    let other = transaction.read(&to, |_, v| *v).unwrap();
+                                    transaction.upsert(from, balance - 1);
+                                    transaction.upsert(to, other + 1);
+                                }
+                                let counter = transaction.read(&(1 << 20), |_, v| *v);
+                                transaction.upsert(1 << 20, counter.unwrap_or(0) + 1);
+                                1
+                            });
+                        }
+                        1 => {
+                            // the sum of the balances read by a committed transaction is invariant
+                            let total = run(&hashmap_copied, |transaction| {
+                                (0..num_accounts)
+                                    .map(|account| transaction.read(&account, |_, v| *v).unwrap())
+                                    .sum()
+                            });
+                            assert_eq!(total, num_accounts * 1000);
+                        }
+                        _ => {
+                            // enlarges and shrinks the HashMap
+                            let key = 1 << 24 | thread_id << 16 | (iteration % 1024);
+                            if (iteration / 1024) % 2 == 0 {
+                                assert!(hashmap_copied.insert(key, key).is_ok());
+                            } else {
+                                assert!(hashmap_copied.remove(key));
+                            }
+                        }
+                    }
+                }
+                num_increments
+            }));
+        }
+        let mut num_increments = 0;
+        for handle in thread_handles {
+            num_increments += handle.join().unwrap();
+        }
+        let total: usize = (0..num_accounts)
+            .map(|account| hashmap.read(account, |_, balance| *balance).unwrap())
+            .sum();
+        assert_eq!(total, num_accounts * 1000);
+        assert_eq!(hashmap.read(1 << 20, |_, v| *v), Some(num_increments));
+    }
+
+    #[test]
+    fn transaction_version() {
+        let hashmap: HashMap<usize, usize, RandomState> = HashMap::new(RandomState::new(), None);
+        assert!(hashmap.insert(1, 1).is_ok());
+
+        // exclusive locks released without modifications do not invalidate reads
+        let mut transaction = hashmap.transaction();
+        assert_eq!(transaction.read(&1, |_, v| *v), Some(1));
+        drop(hashmap.get(1));
+        drop(hashmap.lock_many(&[1, 2]));
+        assert!(hashmap.transaction().commit().is_ok());
+        assert!(transaction.commit().is_ok());
+
+        // mutable access to a value invalidates reads
+        let mut transaction = hashmap.transaction();
+        assert_eq!(transaction.read(&1, |_, v| *v), Some(1));
+        *hashmap.get(1).unwrap().get().1 = 2;
+        assert_eq!(transaction.commit(), Err(Conflict));
+
+        let mut transaction = hashmap.transaction();
+        assert_eq!(transaction.read(&1, |_, v| *v), Some(2));
+        assert!(hashmap.lock_many(&[1]).get_mut(&1).is_some());
+        assert_eq!(transaction.commit(), Err(Conflict));
+
+        // scans that do not erase the key-value pair do not invalidate reads
+        let mut transaction = hashmap.transaction();
+        assert_eq!(transaction.read(&1, |_, v| *v), Some(2));
+        assert_eq!(hashmap.retain(|_, _| true), (1, 0));
+        assert_eq!(hashmap.purge_expired(), 0);
+        assert_eq!(serde_json::to_string(&hashmap).unwrap(), "{\"1\":2}");
+        assert!(transaction.commit().is_ok());
+
+        // erasing the key-value pair in a scan invalidates reads
+        let mut transaction = hashmap.transaction();
+        assert_eq!(transaction.read(&1, |_, v| *v), Some(2));
+        assert_eq!(hashmap.retain(|_, _| false), (0, 1));
+        assert_eq!(transaction.commit(), Err(Conflict));
+    }
+
+    #[test]
+    fn versionedmap() {
+        let num_keys = 16;
+        let num_iterations = 4096;
+        let versionedmap: Arc<VersionedMap<usize, usize, RandomState>> =
+            Arc::new(VersionedMap::new(RandomState::new(), None));
+        let num_threads = 6;
+        let mut thread_handles = Vec::with_capacity(num_threads);
+        for thread_id in 0..num_threads {
+            let versionedmap_copied = versionedmap.clone();
+            thread_handles.push(thread::spawn(move || {
+                for iteration in 0..num_iterations {
+                    match thread_id % 3 {
+                        0 => {
+                            // each key is updated with increasing values, and removed now and then
+                            let key = thread_id << 16 | (iteration % num_keys);
+                            if iteration % 7 == 0 {
+                                versionedmap_copied.remove(key);
+                            } else {
+                                versionedmap_copied.upsert(key, iteration);
+                            }
+                        }
+                        1 => {
+                            // a snapshot keeps reading the same versions while the versions are pruned
+                            let snapshot = versionedmap_copied.snapshot();
+                            let guard = scc::pin();
+                            let key = ((iteration % 2) * 3) << 16 | (iteration % num_keys);
+                            let value = snapshot.read(key, &guard).copied();
+                            versionedmap_copied.gc();
+                            assert_eq!(snapshot.read(key, &guard).copied(), value);
+                            let newer_snapshot = versionedmap_copied.snapshot();
+                            assert!(newer_snapshot.timestamp() >= snapshot.timestamp());
+                            drop(snapshot);
+                            let newer_value = newer_snapshot.read(key, &guard).copied();
+                            versionedmap_copied.gc();
+                            assert_eq!(newer_snapshot.read(key, &guard).copied(), newer_value);
+                        }
+                        _ => {
+                            versionedmap_copied.gc();
+                        }
+                    }
+                }
+            }));
+        }
+        for handle in thread_handles {
+            handle.join().unwrap();
+        }
+        let now = versionedmap.now();
+        let guard = scc::pin();
+        let latest: Vec<Option<usize>> = (0..num_keys)
+            .map(|key| versionedmap.read_at(key, now, &guard).copied())
+            .collect();
+        versionedmap.gc();
+        assert_eq!(versionedmap.gc(), 0);
+        for (key, value) in latest.into_iter().enumerate() {
+            assert_eq!(versionedmap.read_at(key, now, &guard).copied(), value);
+        }
+        let num_removed = (0..num_keys)
+            .filter(|key| (num_iterations - num_keys + key) % 7 == 0)
+            .count();
+        assert_eq!(
+            versionedmap.len(|capacity| capacity),
+            num_keys * 2 - num_removed * 2
+        );
+    }
+
+    #[test]
+    fn serde() {
+        let num_entries = 65536;
+        let hashmap: HashMap<u64, String, RandomState> = HashMap::new(RandomState::new(), None);
+        for key in 0..num_entries {
+            assert!(hashmap.insert(key, key.to_string()).is_ok());
+        }
+        let serialized = serde_json::to_string(&hashmap).unwrap();
+        let deserialized: HashMap<u64, String, RandomState> =
+            serde_json::from_str(&serialized).unwrap();
+        assert_eq!(deserialized.len(|capacity| capacity), num_entries as usize);
+        for key in 0..num_entries {
+            assert_eq!(
+                deserialized.read(key, |_, value| value.clone()),
+                Some(key.to_string())
+            );
+        }
+        // the minimum capacity is not affected by the number of entries
+        assert_eq!(
+            deserialized.retain(|key, _| *key < 16),
+            (16, num_entries as usize - 16)
+        );
+        for key in 0..16 {
+            assert!(deserialized.remove(key));
+        }
+        assert_eq!(deserialized.capacity(), 256);
+
+        // the array is allocated in advance if the number of entries is known
+        let entries = (0..num_entries).map(|key| (key, key.to_string()));
+        let deserializer: MapDeserializer<_, serde::de::value::Error> =
+            MapDeserializer::new(entries);
+        let deserialized: HashMap<u64, String, RandomState> =
+            HashMap::deserialize(deserializer).unwrap();
+        assert!(deserialized.capacity() >= num_entries as usize);
+        assert_eq!(deserialized.len(|capacity| capacity), num_entries as usize);
+
+        let statistics = hashmap.statistics();
+        let serialized = serde_json::to_string(&statistics).unwrap();
+        let deserialized: Statistics = serde_json::from_str(&serialized).unwrap();
+        assert_eq!(deserialized.to_string(), statistics.to_string());
+    }
+
+    #[test]
+    fn checkpoint() {
+        let num_accounts = 256;
+        let num_iterations = 16384;
+        let hashmap: Arc<HashMap<u64, u64, RandomState>> =
+            Arc::new(HashMap::new(RandomState::new(), None));
+        for account in 0..num_accounts {
+            assert!(hashmap.insert(account, 1000).is_ok());
+        }
+        let encoder = |key: &u64, value: &u64, buffer: &mut Vec<u8>| {
+            buffer.extend_from_slice(&key.to_le_bytes());
+            buffer.extend_from_slice(&value.to_le_bytes());
+        };
+        let decoder = |record: &[u8]| {
+            let key = u64::from_le_bytes(record.get(0..8)?.try_into().ok()?);
+            let value = u64::from_le_bytes(record.get(8..16)?.try_into().ok()?);
+            Some((key, value))
+        };
+        let num_threads = 4;
+        let barrier = Arc::new(Barrier::new(num_threads + 1));
+        let mut thread_handles = Vec::with_capacity(num_threads);
+        for thread_id in 0..num_threads {
+            let barrier_copied = barrier.clone();
+            let hashmap_copied = hashmap.clone();
+            thread_handles.push(thread::spawn(move || {
+                barrier_copied.wait();
+                for iteration in 0..num_iterations {
+                    if thread_id % 2 == 0 {
+                        // transfers a unit between accounts
+                        let from = (iteration * 7 + thread_id as u64) % num_accounts;
+                        let to = (iteration * 13 + 1) % num_accounts;
+                        let mut multi_accessor = hashmap_copied.lock_many(&[from, to]);
+                        if from != to && *multi_accessor.read(&from).unwrap() > 0 {
+                            *multi_accessor.get_mut(&from).unwrap() -= 1;
+                            *multi_accessor.get_mut(&to).unwrap() += 1;
+                        }
+                    } else {
+                        // enlarges and shrinks the HashMap
+                        let key = 1 << 24 | (thread_id as u64) << 16 | (iteration % 2048);
+                        if (iteration / 2048) % 2 == 0 {
+                            assert!(hashmap_copied.insert(key, 0).is_ok());
+                        } else {
+                            assert!(hashmap_copied.remove(key));
+                        }
+                    }
+                }
+            }));
+        }
+        barrier.wait();
+        for _ in 0..64 {
+            let mut checkpoint = Vec::new();
+            let num_entries = hashmap.checkpoint(&mut checkpoint, encoder).unwrap();
+            let restored: HashMap<u64, u64, RandomState> =
+                HashMap::restore(RandomState::new(), checkpoint.as_slice(), decoder).unwrap();
+            assert_eq!(restored.len(|capacity| capacity), num_entries);
+            let total: u64 = (0..num_accounts)
+                .map(|account| restored.read(account, |_, value| *value).unwrap())
+                .sum();
+            assert_eq!(total, num_accounts * 1000);
+
+            // a corrupted or truncated checkpoint is rejected
+            let index = checkpoint.len() / 2;
+            checkpoint[index] ^= 1;
+            assert!(HashMap::<u64, u64, RandomState>::restore(
+                RandomState::new(),
+                checkpoint.as_slice(),
+                decoder
+            )
+            .is_err());
+            checkpoint[index] ^= 1;
+            assert!(HashMap::<u64, u64, RandomState>::restore(
+                RandomState::new(),
+                &checkpoint[..checkpoint.len() - 1],
+                decoder
+            )
+            .is_err());
+        }
+        for handle in thread_handles {
+            handle.join().unwrap();
+        }
+        let mut checkpoint = Vec::new();
+        assert_eq!(
+            hashmap.checkpoint(&mut checkpoint, encoder).unwrap(),
+            hashmap.len(|capacity| capacity)
+        );
+
+        // untrusted capacities and record lengths do not allocate memory in advance
+        let mut checkpoint = b"SCCK\x01".to_vec();
+        checkpoint.extend_from_slice(&[0x80, 0x80, 0x80, 0x80, 0x80, 0x80, 0x80, 0x80, 0x01]);
+        let mut oversized = checkpoint.clone();
+        oversized.extend_from_slice(&[0x81, 0x80, 0x80, 0x80, 0x08]);
+        assert_eq!(
+            HashMap::<u64, u64, RandomState>::restore(
+                RandomState::new(),
+                oversized.as_slice(),
+                decoder
+            )
+            .err()
+            .map(|error| error.kind()),
+            Some(std::io::ErrorKind::InvalidData)
+        );
+        checkpoint.extend_from_slice(&[0x81, 0x80, 0x80, 0x80, 0x01]);
+        checkpoint.extend_from_slice(&[0; 64]);
+        assert_eq!(
+            HashMap::<u64, u64, RandomState>::restore(
+                RandomState::new(),
+                checkpoint.as_slice(),
+                decoder
+            )
+            .err()
+            .map(|error| error.kind()),
+            Some(std::io::ErrorKind::UnexpectedEof)
+        );
+    }
+
+    #[test]
+    fn change_listener() {
+        let num_threads = 4;
+        let num_iterations = 8192;
+        let log = Arc::new(Mutex::new(Vec::new()));
+        let log_copied = log.clone();
+        let hashmap: Arc<HashMap<usize, usize, RandomState>> = Arc::new(HashMap::with_listener(
+            RandomState::new(),
+            None,
+            move |key: &usize, _: Option<&usize>, new_value: Option<&usize>| {
+                log_copied.lock().unwrap().push((*key, new_value.copied()));
+            },
+        ));
+        let mut thread_handles = Vec::with_capacity(num_threads);
+        for thread_id in 0..num_threads {
+            let hashmap_copied = hashmap.clone();
+            thread_handles.push(thread::spawn(move || {
+                for iteration in 0..num_iterations {
+                    // the threads contend for the same keys
+                    let key = iteration % 1024;
+                    match (iteration + thread_id) % 6 {
+                        0 => {
+                            let _ = hashmap_copied.insert(key, thread_id);
+                        }
+                        1 => {
+                            hashmap_copied.upsert(key, iteration);
+                        }
+                        2 => {
+                            hashmap_copied.remove(key);
+                        }
+                        3 => {
+                            if let Some(accessor) = hashmap_copied.get(key + 1) {
+                                accessor.erase();
+                            }
+                        }
+                        4 => {
+                            let mut multi_accessor = hashmap_copied.lock_many(&[key, key + 2]);
+                            if multi_accessor.remove(&key).is_none() {
+                                let _ = multi_accessor.insert(key + 2, iteration);
+                            }
+                        }
+                        _ => {
+                            let mut transaction = hashmap_copied.transaction();
+                            transaction.upsert(key, thread_id);
+                            transaction.remove(key + 3);
+                            assert!(transaction.commit().is_ok());
+                        }
+                    }
+                }
+            }));
+        }
+        for handle in thread_handles {
+            handle.join().unwrap();
+        }
+        hashmap.retain(|key, _| key % 2 == 0);
+
+        let replayed: HashMap<usize, usize, RandomState> = HashMap::new(RandomState::new(), None);
+        let records = log.lock().unwrap().clone();
+        assert_eq!(replayed.replay(records), log.lock().unwrap().len());
+        assert_eq!(
+            replayed.len(|capacity| capacity),
+            hashmap.len(|capacity| capacity)
+        );
+        for (key, value) in hashmap.iter() {
+            assert_eq!(replayed.read(*key, |_, value| *value), Some(*value));
+        }
+
+        // replaying does not notify the listener
+        let num_records = log.lock().unwrap().len();
+        assert_eq!(hashmap.replay(vec![(1, Some(1)), (1, None)]), 2);
+        assert_eq!(log.lock().unwrap().len(), num_records);
+
+        // deadlines are logged and restored by replaying the records
+        type Record = (usize, Option<usize>, Option<Duration>);
+        #[derive(Default)]
+        struct DeadlineLog(Mutex<Vec<Record>>);
+        impl ChangeListener<usize, usize> for DeadlineLog {
+            fn on_change(&self, key: &usize, old_value: Option<&usize>, new_value: Option<&usize>) {
+                self.on_change_with_deadline(key, old_value, new_value, None);
+            }
+            fn on_change_with_deadline(
+                &self,
+                key: &usize,
+                _: Option<&usize>,
+                new_value: Option<&usize>,
+                deadline: Option<Duration>,
+            ) {
+                self.0
+                    .lock()
+                    .unwrap()
+                    .push((*key, new_value.copied(), deadline));
+            }
+        }
+        let clock = Arc::new(ManualClock::default());
+        let log = Arc::new(DeadlineLog::default());
+        let hashmap: HashMap<usize, usize, RandomState> =
+            HashMap::with_clock_and_listener(RandomState::new(), None, clock.clone(), log.clone());
+        for key in 0..64 {
+            assert!(hashmap
+                .insert_with_ttl(key, key, Duration::from_secs(key as u64 % 4 + 1))
+                .is_ok());
+        }
+        hashmap.upsert(3, 4);
+        clock.advance(Duration::from_secs(1));
+        assert_eq!(hashmap.purge_expired(), 16);
+
+        let replayed: HashMap<usize, usize, RandomState> =
+            HashMap::with_clock(RandomState::new(), None, clock.clone());
+        let records = log.0.lock().unwrap().clone();
+        assert_eq!(replayed.replay_with_deadlines(records), 64 + 1 + 16);
+        for _ in 0..4 {
+            for key in 0..64 {
+                assert_eq!(
+                    replayed.read(key, |_, value| *value),
+                    hashmap.read(key, |_, value| *value)
+                );
+            }
+            clock.advance(Duration::from_secs(1));
+        }
+        assert_eq!(replayed.purge_expired(), 48);
+    }
+
+    #[test]
+    fn subscription() {
+        let num_threads = 4;
+        let num_iterations = 4096;
+        let hashmap: Arc<HashMap<usize, usize, RandomState>> =
+            Arc::new(HashMap::new(RandomState::new(), None));
+        let subscription = hashmap.subscribe(num_threads * num_iterations + 1024);
+        let lagging_subscription = hashmap.subscribe(16);
+        drop(hashmap.subscribe(1));
+        let mut thread_handles = Vec::with_capacity(num_threads);
+        for thread_id in 0..num_threads {
+            let hashmap_copied = hashmap.clone();
+            thread_handles.push(thread::spawn(move || {
+                for iteration in 0..num_iterations {
+                    let key = iteration % 256;
+                    match (iteration + thread_id) % 3 {
+                        0 => {
+                            let _ = hashmap_copied.insert(key, thread_id);
+                        }
+                        1 => {
+                            hashmap_copied.upsert(key, iteration);
+                        }
+                        _ => {
+                            hashmap_copied.remove(key);
+                        }
+                    }
+                }
+            }));
+        }
+        for handle in thread_handles {
+            handle.join().unwrap();
+        }
+        hashmap.retain(|key, _| key % 2 == 0);
+
+        // the events of a key are delivered in the order in which the key was modified
+        let mut mirror = std::collections::HashMap::new();
+        while let Some(event) = subscription.try_recv() {
+            match event {
+                Event::Insert(key, value) => assert!(mirror.insert(key, value).is_none()),
+                Event::Update(key, value) => assert!(mirror.insert(key, value).is_some()),
+                Event::Remove(key) => assert!(mirror.remove(&key).is_some()),
+                Event::Overflow => unreachable!(),
+            }
+        }
+        assert_eq!(mirror.len(), hashmap.len(|capacity| capacity));
+        for (key, value) in hashmap.iter() {
+            assert_eq!(mirror.get(key), Some(&*value));
+        }
+
+        let mut events = Vec::new();
+        while let Some(event) = lagging_subscription.try_recv() {
+            events.push(event);
+        }
+        assert_eq!(events, vec![Event::Overflow]);
+
+        // the events following the overflow are delivered in order
+        assert!(hashmap.insert(1 << 20, 0).is_ok());
+        assert!(hashmap.remove(1 << 20));
+        assert_eq!(
+            lagging_subscription.try_recv(),
+            Some(Event::Insert(1 << 20, 0))
+        );
+        assert_eq!(
+            lagging_subscription.try_recv(),
+            Some(Event::Remove(1 << 20))
+        );
+        assert!(lagging_subscription.try_recv().is_none());
+
+        // erasing expired key-value pairs is notified as removal
+        let clock = Arc::new(ManualClock::default());
+        let hashmap: HashMap<usize, usize, RandomState> =
+            HashMap::with_clock(RandomState::new(), None, clock.clone());
+        let subscription = hashmap.subscribe(16);
+        assert!(hashmap
+            .insert_with_ttl(1, 1, Duration::from_secs(1))
+            .is_ok());
+        assert!(hashmap
+            .insert_with_ttl(2, 2, Duration::from_secs(2))
+            .is_ok());
+        clock.advance(Duration::from_secs(1));
+        assert_eq!(hashmap.purge_expired(), 1);
+        clock.advance(Duration::from_secs(1));
+        assert_eq!(hashmap.iter().count(), 0);
+        assert_eq!(subscription.try_recv(), Some(Event::Insert(1, 1)));
+        assert_eq!(subscription.try_recv(), Some(Event::Insert(2, 2)));
+        assert_eq!(subscription.try_recv(), Some(Event::Remove(1)));
+        assert_eq!(subscription.try_recv(), Some(Event::Remove(2)));
+        assert_eq!(subscription.try_recv(), None);
+
+        // values handed out through mutable references are notified as updates
+        assert!(hashmap.insert(1, 1).is_ok());
+        assert!(hashmap.insert(2, 2).is_ok());
+        *hashmap.get(1).unwrap().get().1 = 3;
+        drop(hashmap.get(2));
+        hashmap.iter().for_each(|(_, value)| *value += 1);
+        let mut multi_accessor = hashmap.lock_many(&[1, 2]);
+        *multi_accessor.get_mut(&1).unwrap() += 1;
+        *multi_accessor.get_mut(&2).unwrap() += 1;
+        assert!(multi_accessor.remove(&2).is_some());
+        drop(multi_accessor);
+        assert_eq!(hashmap.retain(|_, _| true), (1, 0));
+        let mut events = Vec::new();
+        while let Some(event) = subscription.try_recv() {
+            events.push(event);
+        }
+        assert_eq!(
+            &events[..3],
+            &[
+                Event::Insert(1, 1),
+                Event::Insert(2, 2),
+                Event::Update(1, 3)
+            ]
+        );
+        events[3..5].sort_by_key(|event| match event {
+            Event::Update(key, _) => *key,
+            _ => unreachable!(),
+        });
+        assert_eq!(
+            &events[3..],
+            &[
+                Event::Update(1, 4),
+                Event::Update(2, 3),
+                Event::Remove(2),
+                Event::Update(1, 5)
+            ]
+        );
+    }
+
+    #[test]
+    fn persistentmap() {
+        type Map = PersistentMap<u64, [u64; 2], BuildHasherDefault<DefaultHasher>>;
+        let path =
+            std::env::temp_dir().join(format!("scc-persistentmap-{}.map", std::process::id()));
+        let num_keys = 4096;
+
+        // the file survives a restart without reloading the entries
+        let persistentmap = Map::create(&path, Default::default(), num_keys as usize).unwrap();
+        for key in 0..num_keys {
+            assert_eq!(persistentmap.insert(key, [key, key]), Ok(true));
+        }
+        drop(persistentmap);
+        let persistentmap = Map::open(&path, Default::default()).unwrap();
+        assert!(persistentmap.was_flushed());
+        assert_eq!(persistentmap.len(), num_keys as usize);
+        for key in 0..num_keys {
+            assert_eq!(persistentmap.read(key, |_, value| *value), Some([key, key]));
+        }
+
+        // an update is made visible by a single store, therefore readers never see a torn value
+        let persistentmap = Arc::new(persistentmap);
+        let num_threads = 4;
+        let mut thread_handles = Vec::with_capacity(num_threads);
+        for thread_id in 0..num_threads {
+            let persistentmap_copied = persistentmap.clone();
+            thread_handles.push(thread::spawn(move || {
+                for iteration in 0..num_keys {
+                    let key = (iteration * 7 + thread_id as u64) % num_keys;
+                    if thread_id == 0 {
+                        let value = iteration + num_keys;
+                        assert!(persistentmap_copied.upsert(key, [value, value]).is_ok());
+                    } else {
+                        let value = persistentmap_copied.read(key, |_, value| *value).unwrap();
+                        assert_eq!(value[0], value[1]);
+                    }
+                }
+            }));
+        }
+        for handle in thread_handles {
+            handle.join().unwrap();
+        }
+
+        // a crash of the process before flushing loses no completed modification
+        assert!(persistentmap.remove(0));
+        assert!(persistentmap.upsert(1, [0, 0]).unwrap().is_some());
+        std::mem::forget(Arc::try_unwrap(persistentmap).ok().unwrap());
+        let persistentmap = Map::open(&path, Default::default()).unwrap();
+        assert!(!persistentmap.was_flushed());
+        assert!(persistentmap.read(0, |_, _| ()).is_none());
+        assert_eq!(persistentmap.read(1, |_, value| *value), Some([0, 0]));
+        assert_eq!(persistentmap.len(), num_keys as usize - 1);
+        assert!(persistentmap.flush().is_ok());
+        drop(persistentmap);
+        assert!(Map::open(&path, Default::default()).unwrap().was_flushed());
+
+        // a file of a different format, key type, value type or BuildHasher is rejected
+        let invalid_data = |result: std::io::Result<()>| {
+            result.err().map(|error| error.kind()) == Some(std::io::ErrorKind::InvalidData)
+        };
+        assert!(invalid_data(
+            PersistentMap::<u64, u64, BuildHasherDefault<DefaultHasher>>::open(
+                &path,
+                Default::default()
+            )
+            .map(|_| ())
+        ));
+        assert!(invalid_data(
+            PersistentMap::<u64, [u64; 2], RandomState>::open(&path, RandomState::new())
+                .map(|_| ())
+        ));
+        let mut bytes = std::fs::read(&path).unwrap();
+        bytes[8] += 1;
+        std::fs::write(&path, &bytes).unwrap();
+        assert!(invalid_data(
+            Map::open(&path, Default::default()).map(|_| ())
+        ));
+        std::fs::remove_file(&path).unwrap();
+
+        // writers of keys in different cells proceed in parallel, and overflowing keys stay reachable
+        let persistentmap = Arc::new(Map::create(&path, Default::default(), 64).unwrap());
+        let capacity = persistentmap.capacity() as u64;
+        let mut thread_handles = Vec::with_capacity(num_threads);
+        for thread_id in 0..num_threads as u64 {
+            let persistentmap_copied = persistentmap.clone();
+            thread_handles.push(thread::spawn(move || {
+                for iteration in 0..256 {
+                    for key in (thread_id..capacity).step_by(num_threads) {
+                        let value = [key, iteration];
+                        assert_eq!(persistentmap_copied.insert(key, value), Ok(true));
+                        assert_eq!(persistentmap_copied.read(key, |_, v| *v), Some(value));
+                    }
+                    for key in (thread_id..capacity).step_by(num_threads) {
+                        assert_eq!(
+                            persistentmap_copied.upsert(key, [key, 0]),
+                            Ok(Some([key, iteration]))
+                        );
+                        if key % 2 == iteration % 2 {
+                            assert!(persistentmap_copied.remove(key));
+                        }
+                    }
+                    for key in (thread_id..capacity).step_by(num_threads) {
+                        if key % 2 != iteration % 2 {
+                            assert!(persistentmap_copied.remove(key));
+                        }
+                        assert!(persistentmap_copied.read(key, |_, _| ()).is_none());
+                    }
+                }
+            }));
+        }
+        for handle in thread_handles {
+            handle.join().unwrap();
+        }
+        assert!(persistentmap.is_empty());
+        drop(persistentmap);
+
+        // the file grows while keys are inserted and read concurrently, and the grown file replaces the original one
+        let persistentmap = Arc::new(Map::create(&path, Default::default(), 1).unwrap());
+        let initial_capacity = persistentmap.capacity();
+        let mut thread_handles = Vec::with_capacity(num_threads);
+        for thread_id in 0..num_threads as u64 {
+            let persistentmap_copied = persistentmap.clone();
+            thread_handles.push(thread::spawn(move || {
+                for key in (thread_id..num_keys).step_by(num_threads) {
+                    assert_eq!(persistentmap_copied.insert(key, [key, key]), Ok(true));
+                    assert_eq!(
+                        persistentmap_copied.upsert(key, [key, 0]),
+                        Ok(Some([key, key]))
+                    );
+                    let probe = key / 2;
+                    if let Some(value) = persistentmap_copied.read(probe, |_, value| *value) {
+                        assert_eq!(value[0], probe);
+                    }
+                }
+            }));
+        }
+        for handle in thread_handles {
+            handle.join().unwrap();
+        }
+        assert!(persistentmap.capacity() >= num_keys as usize);
+        assert!(persistentmap.capacity() > initial_capacity);
+        assert_eq!(persistentmap.len(), num_keys as usize);
+        let mut grow_path = path.clone().into_os_string();
+        grow_path.push(".grow");
+        assert!(!std::path::Path::new(&grow_path).exists());
+        drop(persistentmap);
+        let persistentmap = Map::open(&path, Default::default()).unwrap();
+        assert!(persistentmap.was_flushed());
+        for key in 0..num_keys {
+            assert_eq!(persistentmap.read(key, |_, value| *value), Some([key, 0]));
+        }
+        drop(persistentmap);
+
+        // a corrupted occupancy bitmap does not make modifications panic
+        let persistentmap = Map::create(&path, Default::default(), 1).unwrap();
+        assert_eq!(persistentmap.insert(1, [1, 1]), Ok(true));
+        drop(persistentmap);
+        let mut bytes = std::fs::read(&path).unwrap();
+        let cell_size = (bytes.len() - 4096) / 2;
+        for offset in &[4096, 4096 + cell_size] {
+            bytes[*offset..*offset + 4].copy_from_slice(&0xFFFFu32.to_ne_bytes());
+        }
+        std::fs::write(&path, &bytes).unwrap();
+        let persistentmap = Map::open(&path, Default::default()).unwrap();
+        assert_eq!(persistentmap.read(1, |_, value| *value), Some([1, 1]));
+        assert_eq!(persistentmap.upsert(1, [2, 2]), Err(Full));
+        assert_eq!(persistentmap.insert(2, [2, 2]), Err(Full));
+        assert!(persistentmap.remove(1));
+        drop(persistentmap);
+        std::fs::remove_file(&path).unwrap();
+    }
+
     #[test]
     fn sample() {
         for s in vec![65536, 2097152, 16777216] {
@@ -115,6 +115,30 @@ mod test {
         )
     }
 
+    #[test]
+    fn hash_cache_benchmark() {
+        let keys: Vec<String> = (0..1048576).map(|key| format!("{:0>256}", key)).collect();
+        for cache_hash in [false, true] {
+            let hashmap: HashMap<String, usize, RandomState> = if cache_hash {
+                HashMap::with_hash_cache(RandomState::new(), None)
+            } else {
+                HashMap::new(RandomState::new(), None)
+            };
+            let start_time = Instant::now();
+            for (value, key) in keys.iter().enumerate() {
+                assert!(hashmap.insert(key.clone(), value).is_ok());
+            }
+            let insert_duration = Instant::now().saturating_duration_since(start_time);
+            let start_time = Instant::now();
+            assert_eq!(hashmap.clear(), keys.len());
+            let clear_duration = Instant::now().saturating_duration_since(start_time);
+            println!(
+                "hash cache: {}, insert: {:?}, clear: {:?}",
+                cache_hash, insert_duration, clear_duration
+            );
+        }
+    }
+
     #[test]
     fn hashmap_benchmark() {
         let num_threads_vector = vec![1, 2, 4, 8, 16];